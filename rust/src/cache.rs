@@ -0,0 +1,149 @@
+/*
+File: cache.rs
+
+Persistent cache of full content hashes, so a repeated duplicate search only hashes
+files that are new or have changed since the last run.
+Entries are keyed by absolute path and algorithm and are only trusted while the
+file's size and modification time are what they were when it was hashed.
+The cache is one JSON object in HASH_CACHE_FILE in the searched root; files whose
+path is not valid UTF-8 are never cached. With autosave the cache also writes itself
+out every CHECKPOINT_PERIOD while hashes come in, so an interrupted run keeps most
+of its work (see `checkpoint`).
+*/
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use crate::checkpoint::{write_atomically, CHECKPOINT_PERIOD};
+use crate::dedupe::HashAlgo;
+
+// Cache file name, created in the searched root
+pub const HASH_CACHE_FILE: &str = ".organizer_hash_cache.json";
+
+// One cached hash and the file state it belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedHash {
+    algo: String,
+    size: u64,
+    mtime_secs: i64,
+    mtime_nanos: u32,
+    hash: String,
+}
+
+// Hashes by absolute path; safe to share between hashing threads
+#[derive(Debug, Default)]
+pub struct HashCache {
+    entries: Mutex<HashMap<String, CachedHash>>,
+    // File written every CHECKPOINT_PERIOD, and when it was last written
+    autosave: Option<PathBuf>,
+    last_saved: Mutex<Option<Instant>>,
+}
+
+fn algo_name(algo: HashAlgo) -> &'static str {
+    match algo {
+        HashAlgo::Sha256 => "sha256",
+        HashAlgo::Blake3 => "blake3",
+    }
+}
+
+// Cache key and the current state of `path`, or None when it can't be cached
+fn file_state(path: &Path, algo: HashAlgo) -> Option<(String, CachedHash)> {
+    let key = std::path::absolute(path).ok()?.to_str()?.to_string();
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = FileTime::from_last_modification_time(&metadata);
+    let state = CachedHash {
+        algo: algo_name(algo).to_string(),
+        size: metadata.len(),
+        mtime_secs: mtime.unix_seconds(),
+        mtime_nanos: mtime.nanoseconds(),
+        hash: String::new(),
+    };
+    Some((key, state))
+}
+
+impl HashCache {
+    // Load the cache of `root`; a missing cache file gives an empty cache
+    pub fn load(root: &Path) -> io::Result<HashCache> {
+        HashCache::load_file(&root.join(HASH_CACHE_FILE))
+    }
+
+    // Load a cache from any file, such as a hash checkpoint; a missing file gives an empty cache
+    pub fn load_file(path: &Path) -> io::Result<HashCache> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashCache::default()),
+            Err(e) => return Err(e),
+        };
+        let entries = serde_json::from_reader(BufReader::new(file))?;
+        Ok(HashCache { entries: Mutex::new(entries), ..HashCache::default() })
+    }
+
+    // Write the cache to `path` every CHECKPOINT_PERIOD while hashes are inserted.
+    // Autosaves are best effort; a failure shows up in the final `save`.
+    pub fn with_autosave(mut self, path: PathBuf) -> HashCache {
+        self.autosave = Some(path);
+        self.last_saved = Mutex::new(Some(Instant::now()));
+        self
+    }
+
+    // Write the cache into `root`, dropping entries of files that no longer exist
+    pub fn save(&self, root: &Path) -> io::Result<()> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|path, _| Path::new(path).is_file());
+        self.save_file(&root.join(HASH_CACHE_FILE))
+    }
+
+    // Write the cache to its autosave path right away, as a run stopping early does
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.autosave {
+            Some(path) => self.save_file(path),
+            None => Ok(()),
+        }
+    }
+
+    // Write the cache as it is to `path`
+    fn save_file(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec(&*self.entries.lock().unwrap_or_else(|e| e.into_inner()))?;
+        write_atomically(path, &json)
+    }
+
+    // The cached hash of `path`, if the file hasn't changed since it was stored
+    pub fn get(&self, path: &Path, algo: HashAlgo) -> Option<String> {
+        let (key, state) = file_state(path, algo)?;
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let cached = entries.get(&key)?;
+        let unchanged = CachedHash { hash: cached.hash.clone(), ..state } == *cached;
+        unchanged.then(|| cached.hash.clone())
+    }
+
+    // Remember the hash of `path` as it is now
+    pub fn insert(&self, path: &Path, algo: HashAlgo, hash: &str) {
+        if let Some((key, state)) = file_state(path, algo) {
+            let entry = CachedHash { hash: hash.to_string(), ..state };
+            self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(key, entry);
+        }
+        self.autosave_if_due();
+    }
+
+    fn autosave_if_due(&self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        // Another thread is saving right now
+        let Ok(mut last_saved) = self.last_saved.try_lock() else {
+            return;
+        };
+        if last_saved.is_some_and(|last| last.elapsed() < CHECKPOINT_PERIOD) {
+            return;
+        }
+        let _ = self.save_file(path);
+        *last_saved = Some(Instant::now());
+    }
+}
@@ -0,0 +1,97 @@
+/*
+File: checkpoint.rs
+
+Checkpoints that let an interrupted run pick up where it stopped instead of walking
+and hashing everything again.
+- The scan checkpoint (CHECKPOINT_FILE in the organized root) holds the files
+  classified so far and the folders walked completely. The scan rewrites it every
+  CHECKPOINT_PERIOD and once the walk is over; a resumed walk skips the folders
+  already done, and drops listed files that are gone since (moved by the interrupted
+  run, whose moves are in the journal).
+- Full hashes are checkpointed by a `HashCache` that saves itself every
+  CHECKPOINT_PERIOD (`HashCache::with_autosave`): the hash cache itself when one is
+  used, otherwise CHECKPOINT_HASHES_FILE.
+A scan checkpoint is only resumed by a run with the same scan settings, and both
+files are removed once a run completes. Paths that are not valid UTF-8 can't be
+written, so a scan that meets one can't be checkpointed.
+*/
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+// Scan checkpoint, created in the organized root
+pub const CHECKPOINT_FILE: &str = ".organizer_checkpoint.json";
+// Hashes computed by a run without a hash cache, created in the organized root
+pub const CHECKPOINT_HASHES_FILE: &str = ".organizer_checkpoint_hashes.json";
+// Time between two checkpoint writes
+pub const CHECKPOINT_PERIOD: Duration = Duration::from_secs(30);
+
+// How far a scan got
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    // Scan settings of the run that wrote it, as given to `new`
+    pub settings: String,
+    // Whether the walk finished
+    pub complete: bool,
+    // Folders walked completely; none of them lies inside another
+    pub done_dirs: HashSet<PathBuf>,
+    // Files classified so far, by category folder
+    pub files: BTreeMap<String, Vec<PathBuf>>,
+    // Files no category claims found so far, when they are reported
+    #[serde(default)]
+    pub unmatched: Vec<PathBuf>,
+}
+
+impl ScanCheckpoint {
+    // An empty checkpoint for a scan with the given settings. Any string describing
+    // what decides the scan's result (filters, categories) will do; a checkpoint
+    // written with other settings is not resumed.
+    pub fn new(settings: &str) -> ScanCheckpoint {
+        ScanCheckpoint { settings: settings.to_string(), ..ScanCheckpoint::default() }
+    }
+
+    // The checkpoint of `root`, None when there is none or it was written with other settings
+    pub fn load(root: &Path, settings: &str) -> io::Result<Option<ScanCheckpoint>> {
+        let text = match fs::read(root.join(CHECKPOINT_FILE)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let checkpoint: ScanCheckpoint = serde_json::from_slice(&text)?;
+        Ok((checkpoint.settings == settings).then_some(checkpoint))
+    }
+
+    // Write the checkpoint into `root`. It is written to a temporary file first, so an
+    // interruption never leaves a torn checkpoint behind.
+    pub fn save(&self, root: &Path) -> io::Result<()> {
+        write_atomically(&root.join(CHECKPOINT_FILE), &serde_json::to_vec(self)?)
+    }
+}
+
+// Replace `path` with `contents` through a temporary file next to it
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
+
+// Whether `root` holds a checkpoint of an interrupted run
+pub fn has_checkpoint(root: &Path) -> bool {
+    root.join(CHECKPOINT_FILE).is_file() || root.join(CHECKPOINT_HASHES_FILE).is_file()
+}
+
+// Remove the checkpoints of `root` once a run is over; missing files are fine
+pub fn remove_checkpoint(root: &Path) -> io::Result<()> {
+    for name in [CHECKPOINT_FILE, CHECKPOINT_HASHES_FILE] {
+        match fs::remove_file(root.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
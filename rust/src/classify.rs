@@ -0,0 +1,565 @@
+/*
+File: classify.rs
+
+File categories and the extension lists behind them, kept in one place so a new
+extension only has to be added here.
+Extensions are lowercase and without the leading dot; compound extensions such as
+`tar.gz` are matched as a whole before the last extension. Files whose extension says
+nothing can still be classified from their leading bytes (`sniff_file_type`).
+
+A `Classifier` holds the categories actually used by a run: the built-in ones, or
+those from an optional TOML config that can extend or replace a built-in extension
+list, rename its folder, and add new categories:
+
+    [categories.image]
+    extensions = ["heic", "avif"]
+
+    [categories.ebooks]
+    extensions = ["epub", "mobi"]
+    folder = "books"
+    template = "{year}/{stem}.{ext}"
+
+A category's `template` lays out its folder with a destination path template (see
+`template`), taking precedence over the layout chosen for the run.
+Categories can also be added for a single run with `with_category` (the organizer's
+`--category fonts=ttf,otf`), which works like a config section with only `extensions`.
+Files no category claims are left alone by default; `with_unmatched` lists them
+instead, or collects them all into `other` (see `Unmatched`).
+A run can be limited to some categories (`with_only`, `--only image,video`) or leave some
+out (`with_skipped`, `--skip office`). A skipped category is set aside rather than
+dropped: its files are still recognized, so they are left alone instead of being counted
+as unmatched or collected into `other`, and its folder is still the organizer's.
+*/
+
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::Deserialize;
+use crate::template::PathTemplate;
+
+// Config file looked for in the working directory when none is given explicitly
+pub const CONFIG_FILE: &str = "organizer.toml";
+
+// Supported file extensions for each category
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "gif", "webp", "tiff"];
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "aac", "flac", "ogg", "m4a", "wma"];
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "avi", "wmv", "mov", "flv", "mkv", "webm"];
+pub const OFFICE_EXTENSIONS: &[&str] = &["doc", "docx", "xls", "xlsx", "ppt", "pptx", "pdf", "csv", "txt"];
+pub const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "rar", "7z", "tar", "gz", "bz2", "xz", "tgz", "tar.gz", "tar.bz2", "tar.xz",
+];
+pub const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "c", "h", "cpp", "hpp", "cc", "go", "java", "kt", "rb", "php", "cs",
+    "swift", "sh",
+];
+
+// Multi-part extensions kept together when splitting a file name
+pub const COMPOUND_EXTENSIONS: &[&str] = &["tar.gz", "tar.bz2", "tar.xz"];
+
+// Enum for file type categories
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileType {
+    Image,
+    Audio,
+    Video,
+    Office,
+    Archive,
+    // Source code
+    Code,
+    // Files without any extension (README, LICENSE, Unix executables), opt-in only
+    Other,
+    // Category defined in the config file, by name
+    Custom(String),
+}
+
+impl FileType {
+    // Every category, in display order
+    pub const ALL: [FileType; 7] = [
+        FileType::Image,
+        FileType::Audio,
+        FileType::Video,
+        FileType::Office,
+        FileType::Archive,
+        FileType::Code,
+        FileType::Other,
+    ];
+
+    // Default name of the category folder created under the organized root;
+    // a custom category's folder defaults to its name
+    pub fn folder_name(&self) -> &str {
+        match self {
+            FileType::Image => "image",
+            FileType::Audio => "audio",
+            FileType::Video => "video",
+            FileType::Office => "office",
+            FileType::Archive => "archive",
+            FileType::Code => "code",
+            FileType::Other => "other",
+            FileType::Custom(name) => name,
+        }
+    }
+
+    // Name shown in statistics and duplicate listings
+    pub fn label(&self) -> &str {
+        match self {
+            FileType::Image => "Image",
+            FileType::Audio => "Audio",
+            FileType::Video => "Video",
+            FileType::Office => "Office",
+            FileType::Archive => "Archive",
+            FileType::Code => "Code",
+            FileType::Other => "Other",
+            FileType::Custom(name) => name,
+        }
+    }
+
+    // Built-in extensions that map to this category; `Other` and custom categories have none
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            FileType::Image => IMAGE_EXTENSIONS,
+            FileType::Audio => AUDIO_EXTENSIONS,
+            FileType::Video => VIDEO_EXTENSIONS,
+            FileType::Office => OFFICE_EXTENSIONS,
+            FileType::Archive => ARCHIVE_EXTENSIONS,
+            FileType::Code => CODE_EXTENSIONS,
+            FileType::Other | FileType::Custom(_) => &[],
+        }
+    }
+}
+
+// Split a file name into stem and extension (without the dot), keeping a known compound
+// extension whole: "backup.tar.gz" -> ("backup", "tar.gz"). Names without an extension,
+// or with only a leading dot (".bashrc"), get an empty extension.
+pub fn split_extension(file_name: &str) -> (&str, &str) {
+    let lower = file_name.to_ascii_lowercase();
+    for compound in COMPOUND_EXTENSIONS {
+        // At least one character of stem before the dot
+        if lower.len() > compound.len() + 1 && lower.ends_with(&format!(".{}", compound)) {
+            let dot = file_name.len() - compound.len() - 1;
+            return (&file_name[..dot], &file_name[dot + 1..]);
+        }
+    }
+    match file_name.rfind('.') {
+        Some(dot) if dot > 0 => (&file_name[..dot], &file_name[dot + 1..]),
+        _ => (file_name, ""),
+    }
+}
+
+// Extension used for classification: lowercase and without the dot, compound extensions
+// whole ("backup.TAR.GZ" -> "tar.gz"), and empty for names without one ("Makefile") or
+// with only a leading dot (".bashrc").
+pub fn normalized_extension(file_name: &str) -> String {
+    split_extension(file_name).1.to_ascii_lowercase()
+}
+
+// `split_extension` for names that may not be valid UTF-8 (common on Linux and old media).
+// Such names are split with `Path::file_stem`/`extension`; a compound extension is plain
+// ASCII, so it is still recognized through the lossy form of the name.
+pub fn split_extension_os(file_name: &OsStr) -> (OsString, OsString) {
+    if let Some(name) = file_name.to_str() {
+        let (stem, extension) = split_extension(name);
+        return (stem.into(), extension.into());
+    }
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or(file_name);
+    let extension = path.extension().unwrap_or_default().to_os_string();
+    let lossy = file_name.to_string_lossy().to_ascii_lowercase();
+    let is_compound = COMPOUND_EXTENSIONS.iter().any(|compound| lossy.ends_with(&format!(".{}", compound)));
+    let inner = Path::new(stem);
+    match (is_compound, inner.file_stem(), inner.extension()) {
+        (true, Some(inner_stem), Some(inner_extension)) if !inner_stem.is_empty() => {
+            let mut compound = inner_extension.to_os_string();
+            compound.push(".");
+            compound.push(extension);
+            (inner_stem.to_os_string(), compound)
+        }
+        _ => (stem.to_os_string(), extension),
+    }
+}
+
+// Detect the file type based on its extension
+pub fn detect_file_type(file_name: &str) -> Option<FileType> {
+    let extension = normalized_extension(file_name);
+    FileType::ALL
+        .into_iter()
+        .find(|file_type| file_type.extensions().contains(&extension.as_str()))
+}
+
+// Detect the file type from its leading bytes (magic numbers), for files whose
+// extension is missing or unknown. Unreadable or unrecognized files yield None.
+pub fn sniff_file_type(path: &Path) -> Option<FileType> {
+    let kind = infer::get_from_path(path).ok()??;
+    match kind.matcher_type() {
+        infer::MatcherType::Image => Some(FileType::Image),
+        infer::MatcherType::Audio => Some(FileType::Audio),
+        infer::MatcherType::Video => Some(FileType::Video),
+        infer::MatcherType::Doc => Some(FileType::Office),
+        // infer files PDF under archives
+        infer::MatcherType::Archive if kind.mime_type() == "application/pdf" => Some(FileType::Office),
+        infer::MatcherType::Archive => Some(FileType::Archive),
+        _ => None,
+    }
+}
+
+// What happens to the files no category claims
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unmatched {
+    // Left where they are and not mentioned
+    #[default]
+    Ignore,
+    // Left where they are, but reported by the scan
+    Report,
+    // Classified as `FileType::Other`, so they are moved into the `other` folder
+    Move,
+}
+
+// One category of a `Classifier`
+#[derive(Debug, Clone)]
+pub struct Category {
+    pub file_type: FileType,
+    pub folder: String,
+    // Lowercase, without the leading dot
+    pub extensions: Vec<String>,
+    // Destination path template of this category's files, overriding the run's layout
+    pub template: Option<PathTemplate>,
+}
+
+// Categories used to classify files, in display order. Extensions of custom categories
+// are checked before the built-in ones, so a config can move an extension elsewhere.
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    categories: Vec<Category>,
+    // Categories left out of this run; their files are recognized but never classified
+    skipped: Vec<Category>,
+    // Let the content of a file override its extension (see `classify`)
+    sniff_content: bool,
+    unmatched: Unmatched,
+}
+
+// Shape of the config file
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    sniff_content: bool,
+    #[serde(default)]
+    categories: BTreeMap<String, CategoryConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoryConfig {
+    #[serde(default)]
+    extensions: Vec<String>,
+    folder: Option<String>,
+    // Built-in categories only: drop the built-in extensions instead of adding to them
+    #[serde(default)]
+    replace: bool,
+    template: Option<String>,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Classifier::builtin()
+    }
+}
+
+impl Classifier {
+    // The built-in categories and extension lists
+    pub fn builtin() -> Classifier {
+        let categories = FileType::ALL
+            .into_iter()
+            .map(|file_type| Category {
+                folder: file_type.folder_name().to_string(),
+                extensions: file_type.extensions().iter().map(|ext| ext.to_string()).collect(),
+                template: None,
+                file_type,
+            })
+            .collect();
+        Classifier { categories, skipped: Vec::new(), sniff_content: false, unmatched: Unmatched::default() }
+    }
+
+    // Parse a TOML config. Sections named after a built-in category (image, audio,
+    // video, office, archive, code, other) adjust it; any other name adds a category,
+    // listed after the built-in ones and before `other`. A top-level
+    // `sniff_content = true` turns on `with_content_sniffing`.
+    pub fn from_toml(text: &str) -> io::Result<Classifier> {
+        let config: ConfigFile =
+            toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut classifier = Classifier::builtin().with_content_sniffing(config.sniff_content);
+        for (name, section) in config.categories {
+            let extensions = section
+                .extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase());
+            let template = section
+                .template
+                .as_deref()
+                .map(PathTemplate::parse)
+                .transpose()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("category {}: {}", name, e)))?;
+            match classifier.category_mut(&name) {
+                Some(category) => {
+                    if section.replace {
+                        category.extensions.clear();
+                    }
+                    category.extensions.extend(extensions);
+                    if let Some(folder) = section.folder {
+                        category.folder = folder;
+                    }
+                    if template.is_some() {
+                        category.template = template;
+                    }
+                }
+                None => classifier.add_custom(Category {
+                    folder: section.folder.unwrap_or_else(|| name.clone()),
+                    extensions: extensions.collect(),
+                    template,
+                    file_type: FileType::Custom(name),
+                }),
+            }
+        }
+        Ok(classifier)
+    }
+
+    // Add extensions to the category called `name`, built-in or custom, or else add a
+    // custom category of that name (and folder) with them, as a config section with only
+    // `extensions` would
+    pub fn with_category(mut self, name: &str, extensions: &[String]) -> Classifier {
+        let extensions = extensions.iter().map(|ext| ext.trim_start_matches('.').to_ascii_lowercase());
+        match self.category_mut(name) {
+            Some(category) => category.extensions.extend(extensions),
+            None => self.add_custom(Category {
+                folder: name.to_string(),
+                extensions: extensions.collect(),
+                template: None,
+                file_type: FileType::Custom(name.to_string()),
+            }),
+        }
+        self
+    }
+
+    // The category called `name`, matched case-insensitively
+    fn category_mut(&mut self, name: &str) -> Option<&mut Category> {
+        self.categories
+            .iter_mut()
+            .find(|category| category.file_type.folder_name().eq_ignore_ascii_case(name))
+    }
+
+    // Add a custom category after the others, keeping `other` last
+    fn add_custom(&mut self, category: Category) {
+        let at = self
+            .categories
+            .iter()
+            .position(|category| category.file_type == FileType::Other)
+            .unwrap_or(self.categories.len());
+        self.categories.insert(at, category);
+    }
+
+    // Handle only the categories called `names` (built-in or custom, matched
+    // case-insensitively) and skip the others, as `with_skipped` does. Fails with the
+    // first name that is no category.
+    pub fn with_only(self, names: &[String]) -> Result<Classifier, String> {
+        if let Some(unknown) = names.iter().find(|name| !self.has_category(name)) {
+            return Err(unknown.clone());
+        }
+        let others: Vec<String> = self
+            .categories
+            .iter()
+            .map(|category| category.file_type.folder_name())
+            .filter(|name| !names.iter().any(|only| only.eq_ignore_ascii_case(name)))
+            .map(str::to_string)
+            .collect();
+        self.with_skipped(&others)
+    }
+
+    // Leave the categories called `names` out of the run: their files are neither
+    // classified nor counted as unmatched. Fails with the first name that is no category.
+    pub fn with_skipped(mut self, names: &[String]) -> Result<Classifier, String> {
+        for name in names {
+            let Some(at) = self.categories.iter().position(|category| category.file_type.folder_name().eq_ignore_ascii_case(name)) else {
+                if self.skipped.iter().any(|category| category.file_type.folder_name().eq_ignore_ascii_case(name)) {
+                    continue;
+                }
+                return Err(name.clone());
+            };
+            let category = self.categories.remove(at);
+            self.skipped.push(category);
+        }
+        Ok(self)
+    }
+
+    // Whether a category (skipped or not) is called `name`
+    fn has_category(&self, name: &str) -> bool {
+        self.categories
+            .iter()
+            .chain(&self.skipped)
+            .any(|category| category.file_type.folder_name().eq_ignore_ascii_case(name))
+    }
+
+    // Read and parse a TOML config file
+    pub fn load(path: &Path) -> io::Result<Classifier> {
+        Classifier::from_toml(&fs::read_to_string(path)?)
+    }
+
+    // Read the leading bytes of every file, not only of those with an unknown extension,
+    // and let a recognized content type win over the extension
+    pub fn with_content_sniffing(mut self, sniff_content: bool) -> Classifier {
+        self.sniff_content = sniff_content;
+        self
+    }
+
+    // Handle the files no category claims as `unmatched` says instead of ignoring them
+    pub fn with_unmatched(mut self, unmatched: Unmatched) -> Classifier {
+        self.unmatched = unmatched;
+        self
+    }
+
+    // What happens to the files no category claims
+    pub fn unmatched(&self) -> Unmatched {
+        self.unmatched
+    }
+
+    // Every category handled by the run, in display order
+    pub fn categories(&self) -> &[Category] {
+        &self.categories
+    }
+
+    // The categories left out of the run (see `with_skipped`)
+    pub fn skipped(&self) -> &[Category] {
+        &self.skipped
+    }
+
+    // Whether `file_type` is a category left out of the run
+    pub fn is_skipped(&self, file_type: &FileType) -> bool {
+        self.skipped.iter().any(|category| &category.file_type == file_type)
+    }
+
+    // Whether the file at `path` belongs to a category left out of the run
+    pub fn is_skipped_file(&self, path: &Path) -> bool {
+        !self.skipped.is_empty() && self.recognize(path).is_some_and(|file_type| self.is_skipped(&file_type))
+    }
+
+    // Folder of a category under the organized root
+    pub fn folder_name<'a>(&'a self, file_type: &'a FileType) -> &'a str {
+        self.categories
+            .iter()
+            .chain(&self.skipped)
+            .find(|category| &category.file_type == file_type)
+            .map_or(file_type.folder_name(), |category| category.folder.as_str())
+    }
+
+    // Destination path template configured for a category, if any
+    pub fn template(&self, file_type: &FileType) -> Option<&PathTemplate> {
+        self.categories
+            .iter()
+            .find(|category| &category.file_type == file_type)
+            .and_then(|category| category.template.as_ref())
+    }
+
+    // Detect the file type based on its extension; a skipped category's files are
+    // detected too (see `is_skipped`)
+    pub fn detect(&self, file_name: &str) -> Option<FileType> {
+        let extension = normalized_extension(file_name);
+        let (custom, builtin): (Vec<&Category>, Vec<&Category>) = self
+            .categories
+            .iter()
+            .chain(&self.skipped)
+            .partition(|category| matches!(category.file_type, FileType::Custom(_)));
+        custom
+            .into_iter()
+            .chain(builtin)
+            .find(|category| category.extensions.contains(&extension))
+            .map(|category| category.file_type.clone())
+    }
+
+    // Detect the file type of the file at `path`: by extension, falling back to the
+    // content when the extension is missing or unknown. With content sniffing on, the
+    // content decides whenever it is recognized, so a renamed JPEG still counts as an
+    // image; an archive verdict doesn't override the extension though, since zip-based
+    // formats (docx, epub, jar, ...) all look like archives.
+    // Files recognized neither way are `Other` when unmatched files are moved, None otherwise;
+    // files of a skipped category are None.
+    pub fn classify(&self, path: &Path) -> Option<FileType> {
+        match self.recognize(path) {
+            Some(file_type) if self.is_skipped(&file_type) => None,
+            Some(file_type) => Some(file_type),
+            None => (self.unmatched == Unmatched::Move && !self.is_skipped(&FileType::Other)).then_some(FileType::Other),
+        }
+    }
+
+    // The category the extension or content of a file points to, skipped or not
+    fn recognize(&self, path: &Path) -> Option<FileType> {
+        let by_name = path.file_name().and_then(|name| self.detect(&name.to_string_lossy()));
+        if by_name.is_some() && !self.sniff_content {
+            return by_name;
+        }
+        match (sniff_file_type(path), by_name) {
+            (Some(FileType::Archive), Some(by_name)) => Some(by_name),
+            (sniffed, by_name) => sniffed.or(by_name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Signature and IHDR chunk of a 1x1 PNG, enough for the content to be recognized
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0\x1f\x15\xc4\x89";
+
+    #[test]
+    fn png_with_a_wrong_extension_is_an_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let renamed = dir.path().join("x.txt");
+        let unknown = dir.path().join("x.dat");
+        fs::write(&renamed, PNG_HEADER).unwrap();
+        fs::write(&unknown, PNG_HEADER).unwrap();
+
+        assert_eq!(sniff_file_type(&renamed), Some(FileType::Image));
+        // An unknown extension falls back to the content, a known one wins unless sniffing is on
+        assert_eq!(Classifier::builtin().classify(&unknown), Some(FileType::Image));
+        assert_eq!(Classifier::builtin().classify(&renamed), Some(FileType::Office));
+        assert_eq!(Classifier::builtin().with_content_sniffing(true).classify(&renamed), Some(FileType::Image));
+    }
+
+    #[test]
+    fn extensions_map_to_their_category() {
+        let cases = [
+            ("photo.jpg", Some(FileType::Image)),
+            ("song.flac", Some(FileType::Audio)),
+            ("clip.mkv", Some(FileType::Video)),
+            ("report.docx", Some(FileType::Office)),
+            ("backup.7z", Some(FileType::Archive)),
+            ("main.rs", Some(FileType::Code)),
+            ("Photo.JpEg", Some(FileType::Image)),
+            ("MOVIE.MP4", Some(FileType::Video)),
+            ("notes.xyz", None),
+            ("README", None),
+            (".bashrc", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(detect_file_type(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn names_split_into_stem_and_extension() {
+        let cases = [
+            ("archive.tar.gz", ("archive", "tar.gz"), "tar.gz"),
+            ("backup.TAR.GZ", ("backup", "TAR.GZ"), "tar.gz"),
+            ("PHOTO.JPG", ("PHOTO", "JPG"), "jpg"),
+            ("Makefile", ("Makefile", ""), ""),
+            (".bashrc", (".bashrc", ""), ""),
+            (".tar.gz", (".tar", "gz"), "gz"),
+            ("report.v2.pdf", ("report.v2", "pdf"), "pdf"),
+        ];
+        for (name, split, extension) in cases {
+            assert_eq!(split_extension(name), split, "{}", name);
+            assert_eq!(normalized_extension(name), extension, "{}", name);
+            let (stem, ext) = split_extension_os(OsStr::new(name));
+            assert_eq!((stem.to_str().unwrap(), ext.to_str().unwrap()), split, "{}", name);
+        }
+        assert_eq!(detect_file_type("archive.tar.gz"), Some(FileType::Archive));
+    }
+}
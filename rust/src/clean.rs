@@ -0,0 +1,125 @@
+/*
+File: clean.rs
+
+Cleanup of what organizing leaves behind: zero-byte files, and the skeleton of source
+folders emptied by the moves. `find_empty` walks a directory and lists the empty files
+and every folder that is empty or would be once those files and its empty subfolders
+are gone; `remove_empty` then removes them, files first and folders deepest first.
+The organizer's own files in the root (journal, caches, checkpoints, config and ignore
+file) and the trash folder are never touched, and neither are entries excluded by the
+filter: a folder holding one (a hidden file, say) is not empty. The filter's depth limit
+and filesystem bound the walk as well.
+*/
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cache::HASH_CACHE_FILE;
+use crate::checkpoint::{CHECKPOINT_FILE, CHECKPOINT_HASHES_FILE};
+use crate::classify::CONFIG_FILE;
+use crate::error::{Operation, OperationError};
+use crate::filter::{ScanFilter, IGNORE_FILE};
+use crate::journal::JOURNAL_FILE;
+use crate::scan::TRASH_FOLDER;
+
+// What `find_empty` found
+#[derive(Debug, Clone, Default)]
+pub struct EmptyEntries {
+    // Sorted by path
+    pub files: Vec<PathBuf>,
+    // Deepest folders first, so they can be removed in this order
+    pub dirs: Vec<PathBuf>,
+}
+
+impl EmptyEntries {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.dirs.is_empty()
+    }
+}
+
+// Find the zero-byte files under `root` and the folders that are empty, or hold nothing
+// but such files and empty folders. The root itself is never listed.
+pub fn find_empty(root: &Path, filter: &ScanFilter) -> (EmptyEntries, Vec<OperationError>) {
+    let protected: Vec<PathBuf> = [JOURNAL_FILE, HASH_CACHE_FILE, CHECKPOINT_FILE, CHECKPOINT_HASHES_FILE, CONFIG_FILE, IGNORE_FILE, TRASH_FOLDER]
+        .iter()
+        .map(|name| root.join(name))
+        .collect();
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut errors = Vec::new();
+    let entries = filter
+        .walk(root)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !protected.iter().any(|p| e.path() == p) && !filter.is_excluded(e, root));
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().unwrap_or(root).to_path_buf();
+                errors.push(OperationError::new(Operation::ReadMetadata, &path, e.into()));
+                continue;
+            }
+        };
+        if entry.file_type().is_dir() {
+            dirs.push(entry.into_path());
+        } else if entry.file_type().is_file() {
+            match entry.metadata() {
+                Ok(metadata) if metadata.len() == 0 => files.push(entry.into_path()),
+                Ok(_) => {}
+                Err(e) => errors.push(OperationError::new(Operation::ReadMetadata, entry.path(), e.into())),
+            }
+        }
+    }
+
+    // Deepest first, every folder is decided after its subfolders: it is empty when each
+    // of its entries (excluded ones included) is an empty file or an empty folder
+    let mut removable: HashSet<PathBuf> = files.iter().cloned().collect();
+    dirs.sort_by(|a, b| b.components().count().cmp(&a.components().count()).then(a.cmp(b)));
+    let mut empty_dirs = Vec::new();
+    for dir in dirs {
+        let empty = match fs::read_dir(&dir) {
+            Ok(entries) => entries.into_iter().all(|entry| entry.is_ok_and(|entry| removable.contains(&entry.path()))),
+            Err(e) => {
+                errors.push(OperationError::new(Operation::ReadMetadata, &dir, e));
+                false
+            }
+        };
+        if empty {
+            removable.insert(dir.clone());
+            empty_dirs.push(dir);
+        }
+    }
+    files.sort();
+    (EmptyEntries { files, dirs: empty_dirs }, errors)
+}
+
+// Remove what `find_empty` found: the files for good (they hold nothing to recover),
+// then the folders, deepest first. A folder that gained an entry in the meantime stays
+// and is reported.
+// Returns the files and folders removed and the failures.
+pub fn remove_empty(entries: &EmptyEntries) -> (Vec<PathBuf>, Vec<PathBuf>, Vec<OperationError>) {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut errors = Vec::new();
+    for path in &entries.files {
+        // Only while still empty, it may have been written to since the scan
+        let result = match fs::metadata(path) {
+            Ok(metadata) if metadata.len() > 0 => continue,
+            Ok(_) => fs::remove_file(path),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => files.push(path.clone()),
+            Err(e) => errors.push(OperationError::new(Operation::Delete, path, e)),
+        }
+    }
+    for path in &entries.dirs {
+        match fs::remove_dir(path) {
+            Ok(()) => dirs.push(path.clone()),
+            Err(e) => errors.push(OperationError::new(Operation::RemoveFolder, path, e)),
+        }
+    }
+    (files, dirs, errors)
+}
@@ -0,0 +1,786 @@
+/*
+File: dedupe.rs
+
+Duplicate detection for the organizer.
+Files are grouped by a hash of their content (SHA-256 by default, the faster BLAKE3
+on request); only groups with more than one file are reported as duplicates.
+The duplicates found can then be deleted (`delete_files`) or replaced with hard or
+symbolic links to the file kept from their group (`hardlink_files`, `symlink_files`).
+Duplicates moved into the trash folder are journaled like the organizer's own moves,
+so undo restores them.
+*/
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+use crate::cache::HashCache;
+use crate::error::{Operation, OperationError};
+use crate::filter::ScanFilter;
+use crate::interrupt::is_interrupted;
+use crate::journal::{Journal, JOURNAL_FILE};
+use crate::mover::{get_non_duplicate_name, move_file_support_cross_partition};
+
+// Content hash used to find duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    // Cryptographic and widely known, but slow on large media files. The default, so
+    // hash caches written by earlier runs keep matching.
+    #[default]
+    Sha256,
+    // Several times faster than SHA-256 on large files, using SIMD where available
+    Blake3,
+}
+
+// How same-size files are narrowed down before the full `HashAlgo` hash decides the groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashStrategy {
+    // Only the partial-hash prefilter (see `DedupOptions::partial_window`)
+    #[default]
+    Partial,
+    // After the partial prefilter, files still colliding are hashed whole with the fast,
+    // non-cryptographic xxh3, and only those whose xxh3 matches too get the cryptographic
+    // hash. Saves time when many large same-size files differ somewhere in the middle,
+    // at the cost of reading the real duplicates twice.
+    Xxh3,
+}
+
+// Default number of bytes read from each end of a file for the partial-hash prefilter
+pub const DEFAULT_PARTIAL_WINDOW: u64 = 64 * 1024;
+
+// Settings for `find_duplicates`
+#[derive(Debug, Clone, Copy)]
+pub struct DedupOptions {
+    pub algo: HashAlgo,
+    // Compare same-hash files byte for byte before reporting them as duplicates
+    pub verify: bool,
+    // Bytes hashed from the start and the end of same-size files before hashing them
+    // fully; 0 disables the prefilter
+    pub partial_window: u64,
+    pub strategy: HashStrategy,
+    // Number of hashing threads; None uses rayon's global pool (one thread per CPU).
+    // Some(1) hashes one file at a time, for fragile network mounts.
+    pub max_threads: Option<usize>,
+    // Pause before each file is read, per thread, to keep slow storage responsive
+    pub read_delay: Duration,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        DedupOptions {
+            algo: HashAlgo::default(),
+            verify: false,
+            partial_window: DEFAULT_PARTIAL_WINDOW,
+            strategy: HashStrategy::default(),
+            max_threads: None,
+            read_delay: Duration::ZERO,
+        }
+    }
+}
+
+// Run `work` on a rayon pool of `max_threads` threads, so every parallel iterator inside
+// it is bounded by that count; None (or a pool that can't be built) uses the global pool.
+pub fn with_thread_limit<R: Send>(max_threads: Option<usize>, work: impl FnOnce() -> R + Send) -> R {
+    let pool = max_threads.and_then(|threads| rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok());
+    match pool {
+        Some(pool) => pool.install(work),
+        None => work(),
+    }
+}
+
+// Sleep for the configured read delay, if any
+fn throttle(options: &DedupOptions) {
+    if !options.read_delay.is_zero() {
+        thread::sleep(options.read_delay);
+    }
+}
+
+// Compute the hash of the file content with `algo`. Returns lowercase hex string.
+pub fn calc_hash(path: &Path, algo: HashAlgo) -> io::Result<String> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = [0u8; 8192];
+    match algo {
+        HashAlgo::Sha256 => {
+            let mut hasher = Sha256::new();
+            loop {
+                let len = reader.read(&mut buffer)?;
+                if len == 0 { break; }
+                hasher.update(&buffer[..len]);
+            }
+            Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+        }
+        // Read in blocks large enough for every SIMD implementation
+        HashAlgo::Blake3 => Ok(blake3::Hasher::new().update_reader(reader)?.finalize().to_hex().to_string()),
+    }
+}
+
+// BLAKE3 hash of the first and the last `window` bytes of a file, a cheap way to tell
+// apart same-size files that differ near either end. Short files are hashed whole.
+pub fn calc_partial_hash(path: &Path, window: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; window.min(len) as usize];
+    let read = read_full(&mut file, &mut buffer)?;
+    hasher.update(&buffer[..read]);
+    if len > window {
+        // The tail overlaps the head on files shorter than two windows
+        file.seek(SeekFrom::Start(len - window))?;
+        let read = read_full(&mut file, &mut buffer)?;
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// xxh3 (128 bits) of the whole file: not cryptographic, but fast enough to be limited by
+// the disk rather than the CPU
+pub fn calc_xxh3(path: &Path) -> io::Result<u128> {
+    let mut reader = BufReader::with_capacity(64 * 1024, File::open(path)?);
+    let mut hasher = Xxh3::new();
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        hasher.update(buffer);
+        let len = buffer.len();
+        reader.consume(len);
+    }
+    Ok(hasher.digest128())
+}
+
+// Identity of the file behind a path, shared by all of its hard links: the device and
+// inode. Other platforms don't expose it through std, so their links count as copies.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Whether two paths are hard links to the same file (or the same path)
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    let id = file_id(&fs::metadata(a)?);
+    Ok(id.is_some() && id == file_id(&fs::metadata(b)?))
+}
+
+// Group files by size; files whose size can't be read are skipped and returned as errors.
+// Only the first path of a file is kept: its other hard links take up no space of their
+// own, so they are neither duplicates nor anything to compare against.
+pub fn group_by_size(paths: &[PathBuf]) -> (HashMap<u64, Vec<PathBuf>>, Vec<OperationError>) {
+    let mut size_map: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match fs::metadata(path) {
+            Ok(metadata) if file_id(&metadata).is_some_and(|id| !seen.insert(id)) => {}
+            Ok(metadata) => size_map.entry(metadata.len()).or_default().push(path.clone()),
+            Err(e) => errors.push(OperationError::new(Operation::ReadMetadata, path, e)),
+        }
+    }
+    (size_map, errors)
+}
+
+// Given file paths, group files with same contents (hash) as duplicates.
+// Only files sharing their size with another file are hashed, since a unique size
+// can never be a duplicate. Same-size files larger than two partial windows are first
+// split by `calc_partial_hash`, and only those still colliding are hashed fully (with
+// `HashStrategy::Xxh3`, by xxh3 before the full hash); the groups are always decided
+// by the full hash. Hashing runs in parallel; each group is sorted so
+// output order is stable. With `options.verify`, every group is also compared byte
+// for byte and split if the contents differ (see `verify_group`), which is returned as a
+// `HashMismatch`.
+// Files that can't be read are left out of the groups and returned as errors.
+pub fn find_duplicates(
+    paths: &[PathBuf],
+    options: DedupOptions,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<OperationError>) {
+    find_duplicates_with_progress(paths, options, |_, _| {})
+}
+
+// `find_duplicates`, calling `on_hashed(hashed, total)` after each candidate file is
+// hashed. It is called from the hashing threads, in no particular order of files.
+// Hashing uses at most `options.max_threads` threads.
+pub fn find_duplicates_with_progress(
+    paths: &[PathBuf],
+    options: DedupOptions,
+    on_hashed: impl Fn(usize, usize) + Sync,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<OperationError>) {
+    find_duplicates_cached(paths, options, None, on_hashed)
+}
+
+// `find_duplicates_with_progress`, taking the full hash of unchanged files from `cache`
+// instead of reading them again, and storing every hash it computes there.
+pub fn find_duplicates_cached(
+    paths: &[PathBuf],
+    options: DedupOptions,
+    cache: Option<&HashCache>,
+    on_hashed: impl Fn(usize, usize) + Sync,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<OperationError>) {
+    with_thread_limit(options.max_threads, || find_duplicates_in_pool(paths, options, cache, &on_hashed))
+}
+
+// Full hash of `path`, from `cache` when it has the file as it is now
+fn cached_hash(path: &Path, options: &DedupOptions, cache: Option<&HashCache>) -> io::Result<String> {
+    let Some(cache) = cache else {
+        throttle(options);
+        return calc_hash(path, options.algo);
+    };
+    if let Some(hash) = cache.get(path, options.algo) {
+        return Ok(hash);
+    }
+    throttle(options);
+    let hash = calc_hash(path, options.algo)?;
+    cache.insert(path, options.algo, &hash);
+    Ok(hash)
+}
+
+fn find_duplicates_in_pool(
+    paths: &[PathBuf],
+    options: DedupOptions,
+    cache: Option<&HashCache>,
+    on_hashed: impl Fn(usize, usize) + Sync,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<OperationError>) {
+    let (size_map, mut errors) = group_by_size(paths);
+    let window = options.partial_window;
+    let mut candidates: Vec<(u64, PathBuf)> = Vec::new();
+    let mut large: Vec<(u64, Vec<PathBuf>)> = Vec::new();
+    for (size, files) in size_map.into_iter().filter(|(_, files)| files.len() > 1) {
+        if window == 0 || size <= window.saturating_mul(2) {
+            candidates.extend(files.into_iter().map(|path| (size, path)));
+        } else {
+            large.push((size, files));
+        }
+    }
+
+    // Prefilter: files whose ends differ from every other same-size file are done
+    let partial: Vec<(u64, PathBuf, io::Result<String>)> = large
+        .par_iter()
+        .flat_map_iter(|(size, files)| files.iter().map(move |path| (*size, path)))
+        .filter(|_| !is_interrupted())
+        .map(|(size, path)| {
+            throttle(&options);
+            (size, path.clone(), calc_partial_hash(path, window))
+        })
+        .collect();
+    let mut partial_map: HashMap<(u64, String), Vec<PathBuf>> = HashMap::new();
+    for (size, path, hash) in partial {
+        match hash {
+            Ok(hash) => partial_map.entry((size, hash)).or_default().push(path),
+            Err(e) => errors.push(OperationError::new(Operation::Hash, &path, e)),
+        }
+    }
+    for ((size, _), files) in partial_map.into_iter().filter(|(_, files)| files.len() > 1) {
+        candidates.extend(files.into_iter().map(|path| (size, path)));
+    }
+    if options.strategy == HashStrategy::Xxh3 {
+        let (narrowed, xxh3_errors) = narrow_by_xxh3(candidates, &options, cache);
+        errors.extend(xxh3_errors);
+        candidates = narrowed;
+    }
+
+    let done = AtomicUsize::new(0);
+    let hashed: Vec<(PathBuf, io::Result<String>)> = candidates
+        .par_iter()
+        .filter(|_| !is_interrupted())
+        .map(|(_, path)| {
+            let hash = cached_hash(path, &options, cache);
+            on_hashed(done.fetch_add(1, Ordering::Relaxed) + 1, candidates.len());
+            (path.clone(), hash)
+        })
+        .collect();
+
+    let mut hash_map: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for (path, hash) in hashed {
+        match hash {
+            Ok(hash) => hash_map.entry(hash).or_default().push(path),
+            Err(e) => errors.push(OperationError::new(Operation::Hash, &path, e)),
+        }
+    }
+    // Retain only those hashes with more than 1 file (i.e., actual duplicates)
+    let duplicates = hash_map
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, mut files)| {
+            files.sort();
+            (hash, files)
+        });
+    if !options.verify {
+        return (duplicates.collect(), errors);
+    }
+
+    let mut verified = HashMap::new();
+    for (hash, files) in duplicates {
+        let (subgroups, compare_errors) = verify_group(&files);
+        errors.extend(compare_errors);
+        if subgroups.len() > 1 {
+            let mismatch = HashMismatch { hash: hash.clone(), files: files.len(), groups: subgroups.len() };
+            let error = io::Error::new(io::ErrorKind::InvalidData, mismatch);
+            errors.push(OperationError::new(Operation::Compare, &files[0], error));
+        }
+        // Split groups get a numbered key so they stay distinct in the map
+        for (i, group) in subgroups.into_iter().filter(|g| g.len() > 1).enumerate() {
+            let key = if i == 0 { hash.clone() } else { format!("{}-{}", hash, i) };
+            verified.insert(key, group);
+        }
+    }
+    (verified, errors)
+}
+
+// Files sharing a hash whose contents differ, found by `DedupOptions::verify`: most likely
+// one of them changed after it was hashed. Returned as the `io::Error` of an
+// `Operation::Compare` failure on the group's first file; the files are regrouped by content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMismatch {
+    pub hash: String,
+    // Files that shared the hash
+    pub files: usize,
+    // Groups of equal content they were split into
+    pub groups: usize,
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hash {} matched {} files whose contents differ; split into {} groups", self.hash, self.files, self.groups)
+    }
+}
+
+impl Error for HashMismatch {}
+
+// The xxh3 stage of `HashStrategy::Xxh3`: keep the `(size, path)` candidates whose xxh3
+// matches another same-size candidate's. Sizes with a file whose full hash is in
+// `cache` are kept whole, as comparing with it costs no reading.
+fn narrow_by_xxh3(
+    candidates: Vec<(u64, PathBuf)>,
+    options: &DedupOptions,
+    cache: Option<&HashCache>,
+) -> (Vec<(u64, PathBuf)>, Vec<OperationError>) {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for (size, path) in candidates {
+        by_size.entry(size).or_default().push(path);
+    }
+    let mut narrowed = Vec::new();
+    let mut to_hash = Vec::new();
+    for (size, files) in by_size {
+        let any_cached = cache.is_some_and(|cache| files.iter().any(|path| cache.get(path, options.algo).is_some()));
+        let files = files.into_iter().map(|path| (size, path));
+        if any_cached {
+            narrowed.extend(files);
+        } else {
+            to_hash.extend(files);
+        }
+    }
+    let hashed: Vec<(u64, PathBuf, io::Result<u128>)> = to_hash
+        .into_par_iter()
+        .filter(|_| !is_interrupted())
+        .map(|(size, path)| {
+            throttle(options);
+            let hash = calc_xxh3(&path);
+            (size, path, hash)
+        })
+        .collect();
+    let mut errors = Vec::new();
+    let mut xxh3_map: HashMap<(u64, u128), Vec<PathBuf>> = HashMap::new();
+    for (size, path, hash) in hashed {
+        match hash {
+            Ok(hash) => xxh3_map.entry((size, hash)).or_default().push(path),
+            Err(e) => errors.push(OperationError::new(Operation::Hash, &path, e)),
+        }
+    }
+    for ((size, _), files) in xxh3_map.into_iter().filter(|(_, files)| files.len() > 1) {
+        narrowed.extend(files.into_iter().map(|path| (size, path)));
+    }
+    (narrowed, errors)
+}
+
+// Split a group of same-hash files into sets whose contents are byte-for-byte equal.
+// Files that can't be read are dropped from the group and returned as errors.
+pub fn verify_group(files: &[PathBuf]) -> (Vec<Vec<PathBuf>>, Vec<OperationError>) {
+    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    let mut errors = Vec::new();
+    'files: for path in files {
+        for group in groups.iter_mut() {
+            match files_equal(&group[0], path) {
+                Ok(true) => {
+                    group.push(path.clone());
+                    continue 'files;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    errors.push(OperationError::new(Operation::Compare, path, e));
+                    continue 'files;
+                }
+            }
+        }
+        groups.push(vec![path.clone()]);
+    }
+    (groups, errors)
+}
+
+// Streaming byte comparison of two files
+pub fn files_equal(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut reader_a = BufReader::new(File::open(a)?);
+    let mut reader_b = BufReader::new(File::open(b)?);
+    let mut buffer_a = [0u8; 8192];
+    let mut buffer_b = [0u8; 8192];
+    loop {
+        let len_a = read_full(&mut reader_a, &mut buffer_a)?;
+        let len_b = read_full(&mut reader_b, &mut buffer_b)?;
+        if len_a != len_b || buffer_a[..len_a] != buffer_b[..len_b] {
+            return Ok(false);
+        }
+        if len_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// Fill `buffer` as far as possible; a short count means end of file
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let len = reader.read(&mut buffer[filled..])?;
+        if len == 0 { break; }
+        filled += len;
+    }
+    Ok(filled)
+}
+
+// Check whether two files have identical content: compare sizes first, then hashes
+pub fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    Ok(calc_hash(a, HashAlgo::Blake3)? == calc_hash(b, HashAlgo::Blake3)?)
+}
+
+// Replace `duplicate` with a hard link to `kept`. The link is created under a temporary
+// name next to `duplicate` and renamed over it, so the copy is only gone once the link
+// exists. Fails with `ErrorKind::CrossesDevices` when the two are on different filesystems.
+// Paths already linked to `kept` are left alone: renaming a link over another link to the
+// same file does nothing, which would leave the temporary link behind.
+pub fn replace_with_hard_link(kept: &Path, duplicate: &Path) -> io::Result<()> {
+    if same_file(kept, duplicate)? {
+        return Ok(());
+    }
+    let mut temp_name = OsString::from(".");
+    temp_name.push(duplicate.file_name().unwrap_or_default());
+    temp_name.push(".organizer-link");
+    let temp = duplicate.with_file_name(temp_name);
+    fs::hard_link(kept, &temp)?;
+    if let Err(e) = fs::rename(&temp, duplicate) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// How a symbolic link made by `replace_with_symlink` refers to the kept file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkTarget {
+    // Path from the duplicate's folder, so the tree can be moved as a whole
+    #[default]
+    Relative,
+    // Full path, so the link can be moved on its own
+    Absolute,
+}
+
+// Path to `to` as seen from the folder `from_dir`; both must be absolute
+fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    path.extend(&to[common..]);
+    path
+}
+
+#[cfg(unix)]
+pub(crate) fn create_file_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+pub(crate) fn create_file_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn create_file_symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symbolic links are not supported on this platform"))
+}
+
+// Replace `duplicate` with a symbolic link to `kept`, swapped in like `replace_with_hard_link`.
+// Unlike a hard link it works across filesystems, but the duplicate's path breaks if `kept`
+// is later moved or deleted.
+pub fn replace_with_symlink(kept: &Path, duplicate: &Path, target: LinkTarget) -> io::Result<()> {
+    let kept = std::path::absolute(kept)?;
+    let duplicate = std::path::absolute(duplicate)?;
+    let link_target = match (target, duplicate.parent()) {
+        (LinkTarget::Relative, Some(folder)) => relative_path(folder, &kept),
+        _ => kept,
+    };
+    let mut temp_name = OsString::from(".");
+    temp_name.push(duplicate.file_name().unwrap_or_default());
+    temp_name.push(".organizer-link");
+    let temp = duplicate.with_file_name(temp_name);
+    create_file_symlink(&link_target, &temp)?;
+    if let Err(e) = fs::rename(&temp, &duplicate) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Which file of a duplicate group survives; the rest are candidates for deletion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeepStrategy {
+    // Keep the first file of the (path-sorted) group
+    #[default]
+    FirstFound,
+    // Keep the file with the oldest modification time
+    Oldest,
+    // Keep the file with the newest modification time
+    Newest,
+    // Keep the file with the shortest full path
+    ShortestPath,
+    // Keep the file with the longest full path
+    LongestPath,
+}
+
+// Reorder a duplicate group so that the file to keep comes first.
+// Files whose modification time can't be read sort last for Oldest/Newest.
+pub fn order_by_keep_strategy(files: &mut [PathBuf], strategy: KeepStrategy) {
+    let modified = |path: &PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match strategy {
+        KeepStrategy::FirstFound => {}
+        KeepStrategy::Oldest => files.sort_by_cached_key(|path| {
+            let time = modified(path);
+            (time.is_none(), time)
+        }),
+        KeepStrategy::Newest => files.sort_by_cached_key(|path| {
+            let time = modified(path);
+            (time.is_none(), Reverse(time))
+        }),
+        KeepStrategy::ShortestPath => files.sort_by_key(|path| path.as_os_str().len()),
+        KeepStrategy::LongestPath => files.sort_by_key(|path| Reverse(path.as_os_str().len())),
+    }
+}
+
+// Move the files inside one of `preferred_dirs` to the front of a group already ordered
+// by `order_by_keep_strategy`, earlier folders first; the strategy still decides between
+// files of the same folder, and between files outside all of them.
+pub fn prefer_files_under(files: &mut [PathBuf], preferred_dirs: &[PathBuf]) {
+    if preferred_dirs.is_empty() {
+        return;
+    }
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let preferred: Vec<PathBuf> = preferred_dirs.iter().map(|dir| absolute(dir)).collect();
+    files.sort_by_cached_key(|path| {
+        let path = absolute(path);
+        preferred.iter().position(|dir| path.starts_with(dir)).unwrap_or(preferred.len())
+    });
+}
+
+// Recursively gather all regular files below `folder` (the folder itself excluded),
+// skipping entries excluded by `filter`. Exclusion globs are matched relative to
+// `base`, normally the organized root that contains `folder`; the filter's depth limit
+// doesn't apply, but other filesystems are left alone if it says so. Symlinked folders are
+// never entered, even when links are followed: the files found there belong to the
+// link's target and must not be removed or replaced as duplicates.
+pub fn collect_files(folder: &Path, filter: &ScanFilter, base: &Path) -> Vec<PathBuf> {
+    WalkDir::new(folder)
+        .follow_links(filter.follow_symlinks())
+        .same_file_system(filter.same_file_system())
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !filter.is_excluded(e, base) && (!e.path_is_symlink() || !e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+// How duplicate files are removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteMode {
+    // fs::remove_file, unrecoverable
+    Permanent,
+    // Move into this folder (TRASH_FOLDER under the root), keeping names unique
+    TrashFolder(PathBuf),
+    // Send to the operating system's recycle bin
+    RecycleBin,
+}
+
+// What happens to the duplicates of each group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DedupAction {
+    // Remove them as described by the delete mode
+    Delete(DeleteMode),
+    // Replace each duplicate with a hard link to the kept file
+    Hardlink,
+    // Replace each duplicate with a symbolic link to the kept file
+    Symlink(LinkTarget),
+}
+
+// Delete files: depending on `mode` they are removed for good, moved into a trash
+// folder, or sent to the OS recycle bin.
+// Moves into the trash folder are appended to the journal in `root`, so undo puts the
+// files back; the other modes can't be undone by the organizer.
+// Returns the files deleted and the failures.
+pub fn delete_files(paths: &[PathBuf], mode: &DeleteMode, root: &Path) -> (Vec<PathBuf>, Vec<OperationError>) {
+    let mut journal = None;
+    let mut errors = Vec::new();
+    if let DeleteMode::TrashFolder(folder) = mode {
+        if let Err(e) = fs::create_dir_all(folder) {
+            return (Vec::new(), vec![OperationError::new(Operation::CreateFolder, folder, e)]);
+        }
+        match Journal::open(root) {
+            Ok(opened) => journal = Some(opened),
+            Err(e) => errors.push(OperationError::new(Operation::Journal, &root.join(JOURNAL_FILE), e)),
+        }
+    }
+    let mut deleted = Vec::new();
+    for path in paths {
+        if is_interrupted() {
+            break;
+        }
+        let result = match mode {
+            DeleteMode::Permanent => fs::remove_file(path),
+            DeleteMode::TrashFolder(folder) => match path.file_name() {
+                Some(file_name) => {
+                    let target = get_non_duplicate_name(folder, file_name, &HashSet::new());
+                    // The copy in the trash doesn't need its original metadata
+                    move_file_support_cross_partition(path, &target).map(|_| {
+                        if let Some(journal) = journal.as_mut() {
+                            if let Err(e) = journal.record(path, &target) {
+                                errors.push(OperationError::new(Operation::Journal, path, e));
+                            }
+                        }
+                    })
+                }
+                None => Err(io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")),
+            },
+            DeleteMode::RecycleBin => trash::delete(path).map_err(io::Error::other),
+        };
+        match result {
+            Ok(()) => deleted.push(path.clone()),
+            Err(e) => errors.push(OperationError::new(Operation::Delete, path, e)),
+        }
+    }
+    (deleted, errors)
+}
+
+// Replace every duplicate of the `(duplicate, kept)` pairs with a hard link to its kept file.
+// Duplicates on another filesystem than their kept file are skipped and reported, those
+// already linked to it are skipped silently, as there is nothing left to reclaim.
+// Returns the pairs relinked and the failures.
+pub fn hardlink_files(pairs: &[(PathBuf, PathBuf)]) -> (Vec<(PathBuf, PathBuf)>, Vec<OperationError>) {
+    let mut linked = Vec::new();
+    let mut errors = Vec::new();
+    for (duplicate, kept) in pairs {
+        if is_interrupted() {
+            break;
+        }
+        if same_file(kept, duplicate).unwrap_or(false) {
+            continue;
+        }
+        match replace_with_hard_link(kept, duplicate) {
+            Ok(()) => linked.push((duplicate.clone(), kept.clone())),
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                let message = format!("hard links can't cross filesystems ({} is on another one)", kept.display());
+                let error = io::Error::new(e.kind(), message);
+                errors.push(OperationError::new(Operation::Link, duplicate, error));
+            }
+            Err(e) => errors.push(OperationError::new(Operation::Link, duplicate, e)),
+        }
+    }
+    (linked, errors)
+}
+
+// Replace every duplicate of the `(duplicate, kept)` pairs with a symbolic link to its kept file.
+// Returns the pairs relinked and the failures.
+pub fn symlink_files(pairs: &[(PathBuf, PathBuf)], target: LinkTarget) -> (Vec<(PathBuf, PathBuf)>, Vec<OperationError>) {
+    let mut linked = Vec::new();
+    let mut errors = Vec::new();
+    for (duplicate, kept) in pairs {
+        if is_interrupted() {
+            break;
+        }
+        match replace_with_symlink(kept, duplicate, target) {
+            Ok(()) => linked.push((duplicate.clone(), kept.clone())),
+            Err(e) => errors.push(OperationError::new(Operation::Link, duplicate, e)),
+        }
+    }
+    (linked, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests_match_only_for_identical_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, c) = (dir.path().join("a"), dir.path().join("b"), dir.path().join("c"));
+        fs::write(&a, b"the same bytes").unwrap();
+        fs::write(&b, b"the same bytes").unwrap();
+        fs::write(&c, b"other bytes").unwrap();
+
+        for algo in [HashAlgo::Blake3, HashAlgo::Sha256] {
+            let (hash_a, hash_b, hash_c) = (calc_hash(&a, algo).unwrap(), calc_hash(&b, algo).unwrap(), calc_hash(&c, algo).unwrap());
+            assert_eq!(hash_a, hash_b, "{:?}", algo);
+            assert_ne!(hash_a, hash_c, "{:?}", algo);
+            assert!(hash_a.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()), "{:?}", algo);
+        }
+        assert_eq!(calc_xxh3(&a).unwrap(), calc_xxh3(&b).unwrap());
+        assert_ne!(calc_xxh3(&a).unwrap(), calc_xxh3(&c).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_linked_duplicates_share_an_inode_and_are_not_linked_again() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("a.jpg");
+        let duplicate = dir.path().join("b.jpg");
+        fs::write(&kept, b"same content").unwrap();
+        fs::write(&duplicate, b"same content").unwrap();
+        let pairs = vec![(duplicate.clone(), kept.clone())];
+
+        let (linked, errors) = hardlink_files(&pairs);
+        assert!(errors.is_empty());
+        assert_eq!(linked, pairs);
+        let (kept_meta, duplicate_meta) = (fs::metadata(&kept).unwrap(), fs::metadata(&duplicate).unwrap());
+        assert_eq!((kept_meta.dev(), kept_meta.ino()), (duplicate_meta.dev(), duplicate_meta.ino()));
+
+        // A second run finds nothing to link, reclaim or leave behind
+        let (duplicates, errors) = find_duplicates(&[kept.clone(), duplicate.clone()], DedupOptions::default());
+        assert!(errors.is_empty());
+        assert!(duplicates.is_empty());
+        let (linked, errors) = hardlink_files(&pairs);
+        assert!(errors.is_empty());
+        assert!(linked.is_empty());
+        assert!(!dir.path().join(".b.jpg.organizer-link").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
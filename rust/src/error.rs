@@ -0,0 +1,86 @@
+/*
+File: error.rs
+
+Per-file failures collected while organizing. Functions that touch many files return
+these instead of printing them, so a caller can carry on with the remaining files and
+report every failure once at the end of the run. They print in the language of the
+run (`crate::i18n`); the JSON report records the English operation instead.
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::i18n::{fill, tr};
+
+// What was being done to a file when it failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    ReadMetadata,
+    Hash,
+    Compare,
+    Decode,
+    DecodeAudio,
+    DecodeVideo,
+    CreateFolder,
+    Move,
+    Copy,
+    PreserveMetadata,
+    Journal,
+    HashCache,
+    Checkpoint,
+    Delete,
+    Link,
+    RemoveFolder,
+}
+
+impl Operation {
+    // Verb phrase used in messages, e.g. "Failed to move <path>"
+    pub fn describe(self) -> &'static str {
+        match self {
+            Operation::ReadMetadata => "read metadata of",
+            Operation::Hash => "hash",
+            Operation::Compare => "compare",
+            Operation::Decode => "decode image",
+            Operation::DecodeAudio => "decode audio",
+            Operation::DecodeVideo => "decode video",
+            Operation::CreateFolder => "create folder",
+            Operation::Move => "move",
+            Operation::Copy => "copy",
+            Operation::PreserveMetadata => "preserve modification time and permissions of",
+            Operation::Journal => "record in journal",
+            Operation::HashCache => "read or write hash cache",
+            Operation::Checkpoint => "read or write checkpoint",
+            Operation::Delete => "delete",
+            Operation::Link => "link",
+            Operation::RemoveFolder => "remove folder",
+        }
+    }
+}
+
+// A failed operation on a single path
+#[derive(Debug)]
+pub struct OperationError {
+    pub path: PathBuf,
+    pub operation: Operation,
+    pub error: io::Error,
+}
+
+impl OperationError {
+    pub fn new(operation: Operation, path: &Path, error: io::Error) -> OperationError {
+        OperationError { path: path.to_path_buf(), operation, error }
+    }
+}
+
+impl fmt::Display for OperationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = fill(tr("Failed to {} {}: {}"), &[&tr(self.operation.describe()), &self.path.display(), &self.error]);
+        f.write_str(&message)
+    }
+}
+
+impl Error for OperationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
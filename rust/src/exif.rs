@@ -0,0 +1,99 @@
+/*
+File: exif.rs
+
+Photo metadata read from the Exif block of JPEG, HEIF/HEIC, PNG, WebP and TIFF-based
+RAW files: when the picture was taken, with which camera, and where. The mover uses
+the capture date and the camera to lay out the image category folder.
+*/
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use ::exif::{DateTime, Exif, Field, In, Reader, Tag, Value};
+
+// What the Exif block of a photo says; every field is optional, cameras and editors
+// leave out whatever they like
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhotoInfo {
+    // DateTimeOriginal (or DateTime) as `(year, month, day)`, in the camera's local time
+    pub taken: Option<(i32, u32, u32)>,
+    // Model, prefixed with Make unless the model already names it
+    pub camera: Option<String>,
+    // `(latitude, longitude)` in decimal degrees, south and west negative
+    pub gps: Option<(f64, f64)>,
+}
+
+// Read the Exif metadata of a photo. Files without an Exif block (or in a format that
+// can't carry one) give an error of kind InvalidData.
+pub fn read_photo_info(path: &Path) -> io::Result<PhotoInfo> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let exif = match Reader::new().read_from_container(&mut reader) {
+        Ok(exif) => exif,
+        Err(::exif::Error::Io(e)) => return Err(e),
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    };
+    Ok(PhotoInfo { taken: capture_date(&exif), camera: camera(&exif), gps: gps(&exif) })
+}
+
+// First string of an ASCII field, trimmed of padding
+fn ascii(field: &Field) -> Option<String> {
+    let Value::Ascii(strings) = &field.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(strings.first()?).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn capture_date(exif: &Exif) -> Option<(i32, u32, u32)> {
+    [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime].into_iter().find_map(|tag| {
+        let Value::Ascii(strings) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let date = DateTime::from_ascii(strings.first()?).ok()?;
+        // Unset dates are often written as zeros
+        (date.year > 0 && (1..=12).contains(&date.month) && date.day > 0)
+            .then_some((i32::from(date.year), u32::from(date.month), u32::from(date.day)))
+    })
+}
+
+fn camera(exif: &Exif) -> Option<String> {
+    let model = exif.get_field(Tag::Model, In::PRIMARY).and_then(ascii);
+    let make = exif.get_field(Tag::Make, In::PRIMARY).and_then(ascii);
+    match (make, model) {
+        (Some(make), Some(model)) => {
+            // "Canon" + "Canon EOS 5D" should not become "Canon Canon EOS 5D"
+            let brand = make.split_whitespace().next().unwrap_or(&make).to_lowercase();
+            if model.to_lowercase().starts_with(&brand) {
+                Some(model)
+            } else {
+                Some(format!("{} {}", make, model))
+            }
+        }
+        (make, model) => model.or(make),
+    }
+}
+
+// One GPS coordinate from its degrees/minutes/seconds field and N/S or E/W reference
+fn coordinate(exif: &Exif, value: Tag, reference: Tag, negative: &str) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(value, In::PRIMARY)?.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = parts.as_slice() else {
+        return None;
+    };
+    let degrees = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    if !degrees.is_finite() {
+        return None;
+    }
+    let is_negative = exif
+        .get_field(reference, In::PRIMARY)
+        .and_then(ascii)
+        .is_some_and(|reference| reference.eq_ignore_ascii_case(negative));
+    Some(if is_negative { -degrees } else { degrees })
+}
+
+fn gps(exif: &Exif) -> Option<(f64, f64)> {
+    let latitude = coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let longitude = coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+    Some((latitude, longitude))
+}
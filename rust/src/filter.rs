@@ -0,0 +1,395 @@
+/*
+File: filter.rs
+
+Exclusion rules applied while walking a directory tree, shared by the scan
+and the duplicate-gathering walk so both skip the same entries.
+- Hidden entries are skipped unless included explicitly: names starting with '.' (which
+  covers `.DS_Store`), the folder files Windows leaves behind (`Thumbs.db`, `desktop.ini`,
+  see SYSTEM_FILES) and, on Windows, entries with the hidden attribute. Skipping a hidden
+  directory skips everything below it.
+- Glob patterns (globset syntax) are matched against the path relative to the
+  walk root and against the entry's own name, so `node_modules` excludes that
+  folder anywhere while `photos/raw` only excludes that relative path.
+- An IGNORE_FILE in the walk root lists more exclusions in gitignore syntax: one
+  pattern per line, `#` comments, `!` to re-include, a trailing `/` for folders
+  only, and a leading or inner `/` to anchor a pattern to the root. As in git, a
+  file inside an excluded folder can't be re-included. Nested ignore files are not read.
+- Optional minimum/maximum sizes (bytes) skip regular files outside the range.
+- An optional maximum depth stops the walk that many levels below the root (1: only the
+  entries directly in it), and the walk can be kept to the root's filesystem so mounted
+  drives and network shares below it aren't entered. Walks apply both with `walk`.
+- Symbolic links are not followed by default, so symlinked files and folders are
+  never classified, moved or hashed. When following is enabled, links that resolve
+  inside the walk root are still skipped (their target is walked on its own, and a
+  link to a parent folder can't loop); only links leading outside the root are
+  followed, and `is_outside_link` lets callers flag them. A followed link to a folder
+  holding the root would walk the root again: `is_link_loop` flags it so the walk can
+  skip it, and `FollowedLinks` makes sure a target reached through two links is walked
+  once. Files found below a followed folder link belong to the link's target, which
+  `is_reached_through_link` tells, so they are never moved or removed.
+*/
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use walkdir::{DirEntry, WalkDir};
+
+// Ignore file read from the walk root
+pub const IGNORE_FILE: &str = ".organizerignore";
+
+// Files an operating system keeps next to the user's files, skipped like hidden ones;
+// matched case-insensitively, Windows writes both `desktop.ini` and `Desktop.ini`
+pub const SYSTEM_FILES: &[&str] = &["Thumbs.db", "ehthumbs.db", "desktop.ini"];
+
+// One line of an ignore file
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    matcher: GlobMatcher,
+    // `!pattern`: re-include what an earlier line excluded
+    negated: bool,
+    // `pattern/`: only matches folders
+    dir_only: bool,
+}
+
+impl IgnoreRule {
+    // Parse one gitignore line; None for blank lines and comments
+    fn parse(line: &str) -> Option<Result<IgnoreRule, globset::Error>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            // `\#` and `\!` start patterns with a literal `#` or `!`
+            None if line.starts_with("\\#") || line.starts_with("\\!") => (false, &line[1..]),
+            None => (false, line),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // A slash anywhere but at the end anchors the pattern to the root
+        let glob = match pattern.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if pattern.contains('/') => pattern.to_string(),
+            None => format!("**/{}", pattern),
+        };
+        let matcher = GlobBuilder::new(&glob).literal_separator(true).build().map(|glob| glob.compile_matcher());
+        Some(matcher.map(|matcher| IgnoreRule { matcher, negated, dir_only }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanFilter {
+    include_hidden: bool,
+    excludes: GlobSet,
+    // Lines of the ignore file, in order: the last matching one decides
+    ignore_rules: Vec<IgnoreRule>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    same_file_system: bool,
+}
+
+impl Default for ScanFilter {
+    // Skip hidden entries, no exclusion patterns, no size or depth limits, don't follow
+    // symlinks, cross filesystems
+    fn default() -> Self {
+        ScanFilter {
+            include_hidden: false,
+            excludes: GlobSet::empty(),
+            ignore_rules: Vec::new(),
+            min_size: None,
+            max_size: None,
+            follow_symlinks: false,
+            max_depth: None,
+            same_file_system: false,
+        }
+    }
+}
+
+impl ScanFilter {
+    // Build a filter from glob patterns; fails on the first invalid pattern
+    pub fn new(patterns: &[String], include_hidden: bool) -> Result<ScanFilter, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern)?);
+        }
+        Ok(ScanFilter { include_hidden, excludes: builder.build()?, ..ScanFilter::default() })
+    }
+
+    // Also skip what IGNORE_FILE in `root` lists, if there is one. An invalid pattern
+    // gives an error of kind InvalidData naming its line.
+    pub fn with_ignore_file(mut self, root: &Path) -> io::Result<ScanFilter> {
+        let text = match fs::read_to_string(root.join(IGNORE_FILE)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(self),
+            Err(e) => return Err(e),
+        };
+        for (number, line) in text.lines().enumerate() {
+            match IgnoreRule::parse(line) {
+                Some(Ok(rule)) => self.ignore_rules.push(rule),
+                Some(Err(e)) => {
+                    let message = format!("{} line {}: {}", IGNORE_FILE, number + 1, e);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+                None => {}
+            }
+        }
+        Ok(self)
+    }
+
+    // Only keep regular files whose size lies within `min_size..=max_size`; `None` means no limit
+    pub fn with_size_range(mut self, min_size: Option<u64>, max_size: Option<u64>) -> ScanFilter {
+        self.min_size = min_size;
+        self.max_size = max_size;
+        self
+    }
+
+    // Follow symbolic links that lead outside the walk root
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> ScanFilter {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    // Value for `WalkDir::follow_links`
+    pub fn follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    // Walk at most `max_depth` levels below the root; None means no limit
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> ScanFilter {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Don't enter folders on another filesystem than the root's
+    pub fn with_same_file_system(mut self, same_file_system: bool) -> ScanFilter {
+        self.same_file_system = same_file_system;
+        self
+    }
+
+    // Value for `WalkDir::same_file_system`
+    pub fn same_file_system(&self) -> bool {
+        self.same_file_system
+    }
+
+    // A walk of `root` following symlinks, limited in depth and kept to one filesystem as
+    // this filter says. Exclusions are still to be applied with `is_excluded`.
+    pub fn walk(&self, root: &Path) -> WalkDir {
+        WalkDir::new(root)
+            .follow_links(self.follow_symlinks)
+            .max_depth(self.max_depth.unwrap_or(usize::MAX))
+            .same_file_system(self.same_file_system)
+    }
+
+    // Whether an entry reached through a followed symlink resolves outside `root`
+    pub fn is_outside_link(&self, entry: &DirEntry, root: &Path) -> bool {
+        entry.path_is_symlink() && leads_outside(entry.path(), root)
+    }
+
+    // Whether a followed symlink leads to a folder holding `root`, so walking it would
+    // walk the root again
+    pub fn is_link_loop(&self, entry: &DirEntry, root: &Path) -> bool {
+        entry.path_is_symlink() && leads_above(entry.path(), root)
+    }
+
+    // Whether a walked entry (and, for directories, everything below it) is skipped.
+    // Globs see the path relative to `root`. The walk root itself (depth 0) is never excluded.
+    pub fn is_excluded(&self, entry: &DirEntry, root: &Path) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        if self.excludes_path(entry.path(), root, entry.file_type().is_dir(), entry.path_is_symlink()) {
+            return true;
+        }
+        entry.file_type().is_file() && !self.size_in_range(|| entry.metadata().map(|m| m.len()).ok())
+    }
+
+    // Whether a file under `root` that wasn't found by walking (one reported by a file
+    // watcher, say) is skipped: it, or a folder between it and the root, is excluded the
+    // way a walk would exclude it, or lies deeper than the maximum depth. Paths outside the
+    // root and vanished files are skipped too.
+    pub fn is_path_excluded(&self, path: &Path, root: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return true;
+        };
+        if self.max_depth.is_some_and(|max_depth| relative.components().count() > max_depth) {
+            return true;
+        }
+        let mut current = root.to_path_buf();
+        for component in relative.components() {
+            current.push(component);
+            let Ok(metadata) = fs::symlink_metadata(&current) else {
+                return true;
+            };
+            let is_symlink = metadata.file_type().is_symlink();
+            let is_dir = if is_symlink { current.is_dir() } else { metadata.is_dir() };
+            if self.excludes_path(&current, root, is_dir, is_symlink) {
+                return true;
+            }
+        }
+        match fs::metadata(path) {
+            Ok(metadata) => metadata.is_file() && !self.size_in_range(|| Some(metadata.len())),
+            Err(_) => true,
+        }
+    }
+
+    // The rules shared by walked and watched paths, all but the size range
+    fn excludes_path(&self, path: &Path, root: &Path, is_dir: bool, is_symlink: bool) -> bool {
+        let name = path.file_name().unwrap_or_default();
+        if !self.include_hidden && is_hidden(path, &name.to_string_lossy()) {
+            return true;
+        }
+        if is_symlink && (!self.follow_symlinks || !leads_outside(path, root)) {
+            return true;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.excludes.is_match(relative) || self.excludes.is_match(name) || self.is_ignored(relative, is_dir)
+    }
+
+    // Whether the ignore file excludes an entry; the ignore file itself is never scanned
+    fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        if relative == Path::new(IGNORE_FILE) {
+            return true;
+        }
+        self.ignore_rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.matcher.is_match(relative))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    // Files whose size can't be read are kept so the later stages report the error
+    fn size_in_range(&self, len: impl FnOnce() -> Option<u64>) -> bool {
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return true;
+        }
+        let Some(len) = len() else {
+            return true;
+        };
+        self.min_size.is_none_or(|min| len >= min) && self.max_size.is_none_or(|max| len <= max)
+    }
+}
+
+// Targets of the symlinks followed in one walk, so a target reached through two links
+// is walked once: its files aren't found twice and taken for duplicates of themselves
+#[derive(Debug, Default)]
+pub struct FollowedLinks {
+    targets: HashSet<PathBuf>,
+}
+
+impl FollowedLinks {
+    // Whether a walked entry is new: anything but a symlink whose target was reached
+    // through another link already
+    pub fn is_new(&mut self, entry: &DirEntry) -> bool {
+        if !entry.path_is_symlink() {
+            return true;
+        }
+        match fs::canonicalize(entry.path()) {
+            Ok(target) => self.targets.insert(target),
+            Err(_) => true,
+        }
+    }
+}
+
+// Whether a file lies below a symlinked folder between `base` and itself, as the files
+// found by following a link to a folder do: moving or removing it would take it out of
+// the link's target. Only the folders below `base` are looked at, so a path outside it
+// never counts as reached through a link.
+pub fn is_reached_through_link(path: &Path, base: &Path) -> bool {
+    let Ok(relative) = path.parent().unwrap_or(path).strip_prefix(base) else {
+        return false;
+    };
+    let mut current = base.to_path_buf();
+    relative.components().any(|component| {
+        current.push(component);
+        fs::symlink_metadata(&current).is_ok_and(|metadata| metadata.file_type().is_symlink())
+    })
+}
+
+// Whether a symlink resolves to `root` or a folder holding it
+fn leads_above(path: &Path, root: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(root)) {
+        (Ok(target), Ok(root)) => root.starts_with(target),
+        _ => false,
+    }
+}
+
+// Whether an entry is hidden: a dotfile, a system file or, on Windows, marked hidden
+fn is_hidden(path: &Path, name: &str) -> bool {
+    name.starts_with('.') || SYSTEM_FILES.iter().any(|system| name.eq_ignore_ascii_case(system)) || has_hidden_attribute(path)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_path: &Path) -> bool {
+    false
+}
+
+// Whether a symlink resolves outside `root`
+fn leads_outside(path: &Path, root: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(root)) {
+        (Ok(target), Ok(root)) => !target.starts_with(root),
+        // A dangling link or an unreadable root: treat as foreign
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::classify::{Classifier, FileType};
+    use crate::dedupe::collect_files;
+    use crate::scan::scan_and_classify_files;
+
+    #[test]
+    fn excluded_files_and_folders_are_not_scanned() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join("node_modules/pkg/logo.png"), b"").unwrap();
+        fs::write(root.join("photo.jpg"), b"").unwrap();
+        fs::write(root.join("photo_copy.jpg"), b"").unwrap();
+        fs::write(root.join("song.mp3"), b"").unwrap();
+
+        let filter = ScanFilter::new(&["*_copy.jpg".to_string(), "node_modules".to_string()], false).unwrap();
+        let scan = scan_and_classify_files(root, true, &filter, &Classifier::builtin(), |_| {});
+        let mut found: Vec<PathBuf> = scan.files.values().flatten().cloned().collect();
+        found.sort();
+        assert_eq!(found, [root.join("photo.jpg"), root.join("song.mp3")]);
+        assert_eq!(scan.stats[&FileType::Image], 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_skipped_by_default() {
+        use std::os::unix::fs::symlink;
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(outside.path().join("elsewhere.jpg"), b"").unwrap();
+        fs::create_dir(outside.path().join("album")).unwrap();
+        fs::write(outside.path().join("album/cover.jpg"), b"").unwrap();
+        fs::write(root.join("photo.jpg"), b"").unwrap();
+        symlink(root.join("photo.jpg"), root.join("link.jpg")).unwrap();
+        symlink(outside.path().join("elsewhere.jpg"), root.join("outside.jpg")).unwrap();
+        symlink(outside.path().join("album"), root.join("album")).unwrap();
+
+        let scan = scan_and_classify_files(root, false, &ScanFilter::default(), &Classifier::builtin(), |_| {});
+        assert_eq!(scan.files[&FileType::Image], [root.join("photo.jpg")]);
+        assert!(scan.followed_links.is_empty());
+        assert_eq!(collect_files(root, &ScanFilter::default(), root), [root.join("photo.jpg")]);
+    }
+}
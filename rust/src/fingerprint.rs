@@ -0,0 +1,321 @@
+/*
+File: fingerprint.rs
+
+Acoustic fingerprints of audio files, to find the same recording stored in different
+encodings (an MP3 and a FLAC rip of one song) that no content hash can match.
+The start of each file is decoded (symphonia) to mono PCM at a low sample rate and cut
+into short overlapping frames; every frame gives 32 bits that record, for neighbouring
+frequency bands between 300 and 2000 Hz, whether the energy difference between the two
+bands grew or shrank since the previous frame. Lossy encoding changes the samples but
+hardly these bits, so two files whose fingerprints differ in at most `max_bit_error` of
+their bits, at the best alignment, are reported as the same recording. Pairs are
+reported with the quality of both files (`AudioQuality`), better file first; nothing
+is ever removed on the strength of a fingerprint.
+*/
+
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use crate::error::{Operation, OperationError};
+
+// Default share of differing fingerprint bits up to which two files count as the same recording
+pub const DEFAULT_MAX_BIT_ERROR: f64 = 0.35;
+
+// Seconds decoded from the start of each file
+const FINGERPRINT_SECONDS: u32 = 30;
+// Sample rate the audio is reduced to before fingerprinting
+const SAMPLE_RATE: u32 = 5512;
+// Samples per frame (about 0.37 s) and between the starts of two frames (about 11.6 ms)
+const FRAME_SIZE: usize = 2048;
+const FRAME_STEP: usize = 64;
+// Frequency range of the bands; 33 bands give the 32 bits of a frame
+const LOW_FREQUENCY: f64 = 300.0;
+const HIGH_FREQUENCY: f64 = 2000.0;
+const BANDS: usize = 33;
+// Largest shift, in frames, tried when aligning two fingerprints (about 1 s), for
+// encoder delays and leading silence
+const MAX_SHIFT: usize = 86;
+// Fewest frames two aligned fingerprints must share to be compared (about 5 s)
+const MIN_OVERLAP: usize = 430;
+// Largest difference in length, in seconds, between two files of the same recording
+const MAX_DURATION_DIFFERENCE: f64 = 2.0;
+
+// What decides how faithful an encoding is
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioQuality {
+    // Short codec name, e.g. "mp3" or "flac"
+    pub codec: String,
+    pub lossless: bool,
+    pub sample_rate: u32,
+    pub bits_per_sample: Option<u32>,
+    // Average bitrate of the whole file in kbit/s, when its length is known
+    pub bitrate: Option<u32>,
+}
+
+impl AudioQuality {
+    // Lossless beats lossy, then the higher bitrate, then the higher sample rate
+    fn rank(&self, other: &AudioQuality) -> Ordering {
+        self.lossless
+            .cmp(&other.lossless)
+            .then(self.bitrate.cmp(&other.bitrate))
+            .then(self.sample_rate.cmp(&other.sample_rate))
+            .then(self.bits_per_sample.cmp(&other.bits_per_sample))
+    }
+
+    // e.g. "flac, lossless, 44100 Hz, 16 bit, 880 kbit/s"
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.codec.clone()];
+        parts.push(if self.lossless { "lossless" } else { "lossy" }.to_string());
+        parts.push(format!("{} Hz", self.sample_rate));
+        if let Some(bits) = self.bits_per_sample {
+            parts.push(format!("{} bit", bits));
+        }
+        if let Some(bitrate) = self.bitrate {
+            parts.push(format!("{} kbit/s", bitrate));
+        }
+        parts.join(", ")
+    }
+}
+
+// Fingerprint of the start of an audio file
+#[derive(Debug, Clone)]
+pub struct AudioFingerprint {
+    // 32 bits per frame
+    pub frames: Vec<u32>,
+    // Length of the whole file in seconds, when the container tells it
+    pub duration: Option<f64>,
+    pub quality: AudioQuality,
+}
+
+// Two files found to hold the same recording
+#[derive(Debug, Clone)]
+pub struct SimilarAudio {
+    // The file of higher quality, and the other one
+    pub better: PathBuf,
+    pub better_quality: AudioQuality,
+    pub other: PathBuf,
+    pub other_quality: AudioQuality,
+    // Share of fingerprint bits that differ, 0.0 for identical audio
+    pub bit_error: f64,
+}
+
+fn decode_error(e: SymphoniaError) -> io::Error {
+    match e {
+        SymphoniaError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+// Decode up to FINGERPRINT_SECONDS of a file to mono samples. Returns the samples, their
+// sample rate, and what is known about the file.
+fn decode_start(path: &Path) -> io::Result<(Vec<f32>, u32, Option<f64>, AudioQuality)> {
+    let file_len = fs::metadata(path)?.len();
+    let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(decode_error)?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no audio track"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(decode_error)?;
+    let codec = decoder.codec_params().codec;
+    let codec = symphonia::default::get_codecs()
+        .get_codec(codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown sample rate"))?;
+    let duration = params.n_frames.map(|frames| frames as f64 / f64::from(sample_rate));
+    let quality = AudioQuality {
+        lossless: codec == "flac" || codec == "alac" || codec.starts_with("pcm"),
+        codec,
+        sample_rate,
+        bits_per_sample: params.bits_per_sample,
+        bitrate: duration.filter(|seconds| *seconds > 0.0).map(|seconds| (file_len as f64 * 8.0 / seconds / 1000.0) as u32),
+    };
+
+    let limit = (sample_rate * FINGERPRINT_SECONDS) as usize;
+    let mut samples: Vec<f32> = Vec::with_capacity(limit);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    while samples.len() < limit {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // End of stream
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(decode_error(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet is skipped, like players do
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(decode_error(e)),
+        };
+        let channels = decoded.spec().channels.count().max(1);
+        let buffer = buffer.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        if buffer.capacity() < decoded.capacity() * channels {
+            *buffer = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        }
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    samples.truncate(limit);
+    Ok((samples, sample_rate, duration, quality))
+}
+
+// Reduce samples to SAMPLE_RATE, averaging the samples each output sample covers
+fn downsample(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let ratio = f64::from(sample_rate) / f64::from(SAMPLE_RATE);
+    let count = (samples.len() as f64 / ratio) as usize;
+    (0..count)
+        .map(|i| {
+            let start = (i as f64 * ratio) as usize;
+            let end = (((i + 1) as f64 * ratio) as usize).clamp(start + 1, samples.len());
+            samples[start..end].iter().sum::<f32>() / (end - start) as f32
+        })
+        .collect()
+}
+
+// Fingerprint bits of mono samples at SAMPLE_RATE
+fn fingerprint_samples(samples: &[f32]) -> Vec<u32> {
+    if samples.len() < FRAME_SIZE {
+        return Vec::new();
+    }
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(FRAME_SIZE);
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|n| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * n as f32 / (FRAME_SIZE - 1) as f32).cos())
+        .collect();
+    // FFT bins at the band edges, spaced logarithmically
+    let bin = |frequency: f64| (frequency * FRAME_SIZE as f64 / f64::from(SAMPLE_RATE)).round() as usize;
+    let edges: Vec<usize> = (0..=BANDS)
+        .map(|band| bin(LOW_FREQUENCY * (HIGH_FREQUENCY / LOW_FREQUENCY).powf(band as f64 / BANDS as f64)))
+        .collect();
+
+    let mut frames = Vec::new();
+    let mut previous: Option<Vec<f32>> = None;
+    let mut spectrum = vec![Complex::new(0.0f32, 0.0); FRAME_SIZE];
+    for start in (0..=samples.len() - FRAME_SIZE).step_by(FRAME_STEP) {
+        for (value, (sample, weight)) in spectrum.iter_mut().zip(samples[start..].iter().zip(&window)) {
+            *value = Complex::new(sample * weight, 0.0);
+        }
+        fft.process(&mut spectrum);
+        let energies: Vec<f32> = edges
+            .windows(2)
+            .map(|edge| spectrum[edge[0]..edge[1].max(edge[0] + 1)].iter().map(|c| c.norm_sqr()).sum())
+            .collect();
+        if let Some(previous) = &previous {
+            let bits = (0..BANDS - 1).fold(0u32, |bits, band| {
+                let difference = energies[band] - energies[band + 1] - (previous[band] - previous[band + 1]);
+                (bits << 1) | u32::from(difference > 0.0)
+            });
+            frames.push(bits);
+        }
+        previous = Some(energies);
+    }
+    frames
+}
+
+// Fingerprint the start of an audio file
+pub fn fingerprint(path: &Path) -> io::Result<AudioFingerprint> {
+    let (samples, sample_rate, duration, quality) = decode_start(path)?;
+    let frames = fingerprint_samples(&downsample(&samples, sample_rate));
+    Ok(AudioFingerprint { frames, duration, quality })
+}
+
+// Share of differing bits between two fingerprints at their best alignment, None when
+// they overlap too little to tell
+pub fn bit_error(a: &[u32], b: &[u32]) -> Option<f64> {
+    let mut best: Option<f64> = None;
+    for shift in 0..=MAX_SHIFT {
+        for (first, second) in [(a, b), (b, a)] {
+            if shift >= first.len() {
+                continue;
+            }
+            let first = &first[shift..];
+            let overlap = first.len().min(second.len());
+            if overlap < MIN_OVERLAP {
+                continue;
+            }
+            let differing: u32 = first.iter().zip(second).map(|(x, y)| (x ^ y).count_ones()).sum();
+            let error = f64::from(differing) / (overlap * 32) as f64;
+            best = Some(best.map_or(error, |best| best.min(error)));
+        }
+    }
+    best
+}
+
+// Find the pairs of audio files that hold the same recording: fingerprints within
+// `max_bit_error` of each other and, when both lengths are known, lengths within two
+// seconds. Fingerprinting runs in parallel; files that can't be decoded are skipped
+// and returned as errors. Pairs come sorted by path.
+pub fn find_similar_audio(paths: &[PathBuf], max_bit_error: f64) -> (Vec<SimilarAudio>, Vec<OperationError>) {
+    let results: Vec<(PathBuf, io::Result<AudioFingerprint>)> =
+        paths.par_iter().map(|path| (path.clone(), fingerprint(path))).collect();
+    let mut fingerprints = Vec::new();
+    let mut errors = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(fingerprint) if fingerprint.frames.len() >= MIN_OVERLAP => fingerprints.push((path, fingerprint)),
+            Ok(_) => {}
+            Err(e) => errors.push(OperationError::new(Operation::DecodeAudio, &path, e)),
+        }
+    }
+    fingerprints.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut pairs: Vec<SimilarAudio> = fingerprints
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, (path, fingerprint))| {
+            fingerprints[i + 1..].iter().filter_map(move |(other_path, other)| {
+                if let (Some(a), Some(b)) = (fingerprint.duration, other.duration) {
+                    if (a - b).abs() > MAX_DURATION_DIFFERENCE {
+                        return None;
+                    }
+                }
+                let error = bit_error(&fingerprint.frames, &other.frames).filter(|error| *error <= max_bit_error)?;
+                let (better, worse) = if other.quality.rank(&fingerprint.quality) == Ordering::Greater {
+                    ((other_path, other), (path, fingerprint))
+                } else {
+                    ((path, fingerprint), (other_path, other))
+                };
+                Some(SimilarAudio {
+                    better: better.0.clone(),
+                    better_quality: better.1.quality.clone(),
+                    other: worse.0.clone(),
+                    other_quality: worse.1.quality.clone(),
+                    bit_error: error,
+                })
+            })
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.better.cmp(&b.better).then(a.other.cmp(&b.other)));
+    (pairs, errors)
+}
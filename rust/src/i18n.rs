@@ -0,0 +1,358 @@
+/*
+File: i18n.rs
+
+Language of the messages shown to the user, English or Chinese. Messages are written
+in English where they are printed and looked up in a message table by that English text
+(`tr`), so a message missing from the table still shows, in English. Placeholders are
+`{}`, filled in order by `fill` (`{{` and `}}` stand for braces), so a translation keeps
+the order of its arguments.
+The language is chosen once per run with `set_lang`: by the `--lang` flag, or else from
+the locale in LC_ALL, LC_MESSAGES or LANG (`Lang::from_env`). Machine-readable output,
+such as the JSON report and the journal, is never translated.
+*/
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Display, Write};
+use std::sync::OnceLock;
+
+// Language messages are shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Lang {
+    // Language named by a `--lang` value, "en" or "zh"
+    pub fn parse(name: &str) -> Option<Lang> {
+        match name {
+            "en" => Some(Lang::En),
+            "zh" => Some(Lang::Zh),
+            _ => None,
+        }
+    }
+
+    // Language of the user's locale: Chinese for a zh_* locale, English otherwise
+    pub fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.to_lowercase().starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+// Choose the language of the run; only the first call counts
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+// Language of the run, English until one is chosen
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or_default()
+}
+
+// The message in the language of the run
+pub fn tr(message: &str) -> &str {
+    match lang() {
+        Lang::En => message,
+        Lang::Zh => {
+            static TABLE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+            let table = TABLE.get_or_init(|| ZH.iter().copied().collect());
+            table.get(message).copied().unwrap_or(message)
+        }
+    }
+}
+
+// Replace every `{}` of a message with the next argument, `{{` and `}}` with braces
+pub fn fill(message: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::with_capacity(message.len());
+    let mut args = args.iter();
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(arg) = args.next() {
+                    let _ = write!(filled, "{}", arg);
+                }
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                filled.push(c);
+            }
+            _ => filled.push(c),
+        }
+    }
+    filled
+}
+
+// Chinese messages, by their English text
+const ZH: &[(&str, &str)] = &[
+    ("\nFile category statistics:", "\n文件分类统计："),
+    ("Failed to read journal {}: {}", "无法读取日志 {}：{}"),
+    ("Skipped {} (no longer exists)", "已跳过 {}（已不存在）"),
+    ("Skipped {} ({} is taken)", "已跳过 {}（{} 已被占用）"),
+    ("Failed to create folder {}: {}", "无法创建文件夹 {}：{}"),
+    ("Restored {} -> {}", "已恢复 {} -> {}"),
+    ("Failed to preserve metadata of {}: {}", "无法保留 {} 的元数据：{}"),
+    ("Failed to restore {}: {}", "无法恢复 {}：{}"),
+    ("Restored {} files, skipped {}, failed {}", "已恢复 {} 个文件，跳过 {} 个，失败 {} 个"),
+    ("Failed to clear journal: {}", "无法清空日志：{}"),
+    ("No duplicate {} files found.", "未发现重复的{}文件。"),
+    ("\nDuplicate {} files found:", "\n发现重复的{}文件："),
+    ("  Hash: {} ({} files)", "  哈希：{}（{} 个文件）"),
+    ("   Keep: {}", "   保留：{}"),
+    ("   DELETE: {}", "   删除：{}"),
+    ("Total duplicate {} files to delete: {}", "待删除的重复{}文件总数：{}"),
+    ("Space to be reclaimed from {} files: {}", "可从{}文件回收的空间：{}"),
+    ("No duplicate folders found.", "未发现重复的文件夹。"),
+    ("\nDuplicate folders found (their files are not listed again below):", "\n发现重复的文件夹（其中的文件不再在下方逐一列出）："),
+    ("  {} files, {} each", "  {} 个文件，每份 {}"),
+    ("Space to be reclaimed from duplicate folders: {}", "可从重复文件夹回收的空间：{}"),
+    ("No images that look alike found.", "未发现外观相似的图片。"),
+    ("\nImages that look alike (for review, not removed):", "\n外观相似的图片（仅供查看，不会删除）："),
+    ("  {} bits apart", "  相差 {} 位"),
+    ("No re-encoded copies of the same audio found.", "未发现同一音频的不同编码副本。"),
+    ("\nSame audio in different encodings (not removed):", "\n不同编码的相同音频（不会删除）："),
+    ("  {}% of fingerprint bits differ", "  {}% 的指纹位不同"),
+    ("   Better: {} ({})", "   较好：{}（{}）"),
+    ("   Other:  {} ({})", "   其他：{}（{}）"),
+    ("No copies of the same video found.", "未发现同一视频的副本。"),
+    ("\nVideos that look alike (candidates, not removed):", "\n外观相似的视频（候选，不会删除）："),
+    ("  Frames {} bits apart on average", "  帧平均相差 {} 位"),
+    ("\nGroup {} of {} ({}, {} files):", "\n第 {} 组，共 {} 组（{}，{} 个文件）："),
+    ("Keep {} (suggested)", "保留 {}（建议）"),
+    ("Choose the files to keep", "选择要保留的文件"),
+    ("Skip this group", "跳过此组"),
+    ("Open the files", "打开这些文件"),
+    ("Accept the suggestions for this and all remaining groups", "此组及其余所有组都接受建议"),
+    ("What should happen to this group?", "此组如何处理？"),
+    ("Files to keep (space toggles, enter confirms)", "要保留的文件（空格切换，回车确认）"),
+    ("At least one file has to be kept.", "至少要保留一个文件。"),
+    ("Failed to open {}: {}", "无法打开 {}：{}"),
+    ("Would delete {}", "将删除 {}"),
+    ("Would move {} to {}", "将把 {} 移动到 {}"),
+    ("Would send {} to the recycle bin", "将把 {} 移入系统回收站"),
+    ("Would link {} to {}", "将把 {} 硬链接到 {}"),
+    ("Would symlink {} to {}", "将把 {} 符号链接到 {}"),
+    ("{spinner} {msg} {human_pos} entries ({rate})", "{spinner} {msg} {human_pos} 个条目（{rate}）"),
+    ("\n{} errors occurred:", "\n发生了 {} 个错误："),
+    ("Not a number, no limit applied.", "不是数字，不设限制。"),
+    ("\nPermanently delete, move to trash, or replace with links?", "\n永久删除、移入回收站，还是替换为链接？"),
+    ("  1) Move to {}/ inside the directory (default)", "  1) 移动到目录内的 {}/（默认）"),
+    ("  2) Send to the system recycle bin", "  2) 移入系统回收站"),
+    ("  3) Permanently delete", "  3) 永久删除"),
+    ("  4) Replace with hard links to the kept file", "  4) 替换为指向保留文件的硬链接"),
+    ("  5) Replace with relative symbolic links to the kept file", "  5) 替换为指向保留文件的相对符号链接"),
+    ("  6) Replace with absolute symbolic links to the kept file", "  6) 替换为指向保留文件的绝对符号链接"),
+    ("Choose 1-6: ", "请选择 1-6："),
+    ("Using categories from {}", "使用 {} 中的分类"),
+    ("Failed to load config {}: {}", "无法加载配置 {}：{}"),
+    ("Invalid exclude pattern: {}", "无效的排除模式：{}"),
+    ("Failed to read {}: {}", "无法读取 {}：{}"),
+    ("\nHow should files be arranged inside each category folder?", "\n文件在各分类文件夹内如何排列？"),
+    ("  1) Flat (default)", "  1) 平铺（默认）"),
+    ("  2) Keep their subfolders (e.g. image/2020/summer/)", "  2) 保留原子文件夹（如 image/2020/summer/）"),
+    ("  3) By year and month taken or modified (e.g. image/2023/07/)", "  3) 按拍摄或修改的年月（如 image/2023/07/）"),
+    ("  4) Photos by camera model (e.g. image/Canon EOS 5D/)", "  4) 照片按相机型号（如 image/Canon EOS 5D/）"),
+    ("  5) Music by artist and album (e.g. audio/Artist/Album/)", "  5) 音乐按艺术家和专辑（如 audio/Artist/Album/）"),
+    ("  6) A path template (e.g. {{category}}/{{year}}/{{month}}/{{filename}})", "  6) 路径模板（如 {{category}}/{{year}}/{{month}}/{{filename}}）"),
+    ("Template: ", "模板："),
+    ("Invalid template ({}), keeping files flat.", "无效的模板（{}），文件保持平铺。"),
+    ("\nWhat should happen when a file of the same name is already there?", "\n目标位置已有同名文件时如何处理？"),
+    ("  1) Add a number to the name, e.g. photo_1.jpg (default)", "  1) 在文件名后加数字，如 photo_1.jpg（默认）"),
+    ("  2) Add the start of the content hash, e.g. photo_3f2a9c1e.jpg", "  2) 在文件名后加内容哈希的开头，如 photo_3f2a9c1e.jpg"),
+    ("  3) Skip the file", "  3) 跳过该文件"),
+    ("  4) Overwrite the existing file (it goes to {}/)", "  4) 覆盖已有文件（已有文件移入 {}/）"),
+    ("  5) Overwrite only with a newer file, skip it otherwise", "  5) 仅当文件更新时覆盖，否则跳过"),
+    ("Choose 1-5: ", "请选择 1-5："),
+    ("Skipped {} (same as {})", "已跳过 {}（与 {} 相同）"),
+    ("Would remove {} (same as {})", "将移除 {}（与 {} 相同）"),
+    ("Removed {} (same as {})", "已移除 {}（与 {} 相同）"),
+    ("Skipped {} ({} exists)", "已跳过 {}（{} 已存在）"),
+    ("Would overwrite {} with {}", "将覆盖 {}（替换为 {}）"),
+    ("Overwrote {} with {}", "已覆盖 {}（替换为 {}）"),
+    ("{} exists, would move {} to {} ({})", "{} 已存在，将把 {} 移动到 {}（{}）"),
+    ("{} exists, moved {} to {} ({})", "{} 已存在，已把 {} 移动到 {}（{}）"),
+    ("{} exists, would copy {} to {} ({})", "{} 已存在，将把 {} 复制到 {}（{}）"),
+    ("{} exists, copied {} to {} ({})", "{} 已存在，已把 {} 复制到 {}（{}）"),
+    ("Hash algorithm, sha256 (default) or blake3 (faster): ", "哈希算法，sha256（默认）或 blake3（更快）："),
+    ("Perceptual hash, dhash (default, faster) or phash (also matches edited brightness): ", "感知哈希，dhash（默认，更快）或 phash（也能匹配调整过亮度的图片）："),
+    ("Maximum perceptual hash distance in bits, 0-64 (default {}): ", "感知哈希的最大距离（位），0-64（默认 {}）："),
+    ("\nWhich file of each duplicate group should be kept?", "\n每组重复文件保留哪一个？"),
+    ("  1) First found (default)", "  1) 最先找到的（默认）"),
+    ("  2) Oldest (by modification time)", "  2) 最旧的（按修改时间）"),
+    ("  3) Newest (by modification time)", "  3) 最新的（按修改时间）"),
+    ("  4) Shortest path", "  4) 路径最短的"),
+    ("  5) Longest path", "  5) 路径最长的"),
+    ("Invalid directory.", "无效的目录。"),
+    ("No empty files or folders found.", "未发现空文件或空文件夹。"),
+    ("Empty file: {}", "空文件：{}"),
+    ("Empty folder: {}", "空文件夹：{}"),
+    ("{} empty files and {} empty folders", "{} 个空文件和 {} 个空文件夹"),
+    ("\nWould remove {}", "\n将移除 {}"),
+    ("\nRemove {}? (y/n): ", "\n移除 {}？(y/n)："),
+    ("Removed {} empty files and {} empty folders", "已移除 {} 个空文件和 {} 个空文件夹"),
+    ("Cleanup cancelled. Nothing was removed.", "已取消清理，未移除任何内容。"),
+    ("Watching {} for new files (Ctrl+C to stop)", "正在监视 {} 中的新文件（按 Ctrl+C 停止）"),
+    ("Moved {} -> {}", "已移动 {} -> {}"),
+    ("Watch error: {}", "监视出错：{}"),
+    ("Failed to watch {}: {}", "无法监视 {}：{}"),
+    ("Invalid directory: {}", "无效的目录：{}"),
+    ("Source {} holds the destination {}; organize it in place or choose a destination outside it.", "源目录 {} 包含目标目录 {}；请原地整理，或选择该目录之外的目标目录。"),
+    ("Skipping source {}: it lies inside {}", "跳过源目录 {}：它位于 {} 之内"),
+    ("Please input the directory to organize: ", "请输入要整理的目录："),
+    ("Other directories whose files go with it, comma-separated (empty for none): ", "要一并整理的其他目录，以逗号分隔（留空表示没有）："),
+    ("Directory to create the category folders in (leave empty to organize in place): ", "创建分类文件夹的目录（留空则原地整理）："),
+    ("Dry run (only preview changes, touch nothing)? (y/n): ", "演练模式（只预览更改，不做任何改动）？(y/n)："),
+    ("Failed to create {}: {}", "无法创建 {}：{}"),
+    ("JSON report file (leave empty to skip): ", "JSON 报告文件（留空跳过）："),
+    ("Report written to {}", "报告已写入 {}"),
+    ("Failed to write report {}: {}", "无法写入报告 {}：{}"),
+    ("Check every file's content and trust it over the extension (slower)? (y/n): ", "检查每个文件的内容，并以内容为准而非扩展名（较慢）？(y/n)："),
+    ("Organize files without an extension into 'other'? (y/n): ", "将没有扩展名的文件整理到 'other'？(y/n)："),
+    ("Exclude glob patterns, comma-separated (e.g. node_modules,*.tmp; empty for none): ", "排除的通配模式，以逗号分隔（如 node_modules,*.tmp；留空表示没有）："),
+    ("Include hidden files and folders? (y/n): ", "包含隐藏文件和文件夹？(y/n)："),
+    ("Minimum file size in bytes (leave empty for no limit): ", "最小文件大小，单位字节（留空不限）："),
+    ("Maximum file size in bytes (leave empty for no limit): ", "最大文件大小，单位字节（留空不限）："),
+    ("Follow symbolic links leading outside the directory? (y/n): ", "跟随指向目录之外的符号链接？(y/n)："),
+    ("An interrupted run left a checkpoint here. Resume where it stopped? (y/n): ", "上次中断的运行在此留下了检查点。从中断处继续？(y/n)："),
+    ("Scanning", "正在扫描"),
+    ("Following symlink {} outside the directory", "跟随指向目录之外的符号链接 {}"),
+    ("Resuming the scan of the interrupted run", "从中断的运行处继续扫描"),
+    ("The checkpoint was made with other scan settings, scanning again", "检查点的扫描设置不同，重新扫描"),
+    ("Existing category folders found. Merge new files into them (skip sorted and already-present files)? (y/n): ", "发现已有的分类文件夹。将新文件合并进去（跳过已整理和已存在的文件）？(y/n)："),
+    ("Already present: {} (same as {})", "已存在：{}（与 {} 相同）"),
+    ("Skipped {} files already organized or present.", "已跳过 {} 个已整理或已存在的文件。"),
+    ("\nMove files to corresponding folders? (y/n): ", "\n将文件移动到对应的文件夹？(y/n)："),
+    ("Operation cancelled.", "操作已取消。"),
+    ("Also re-sort the files already in category folders into this layout? (y/n): ", "也将已在分类文件夹中的文件按此布局重新整理？(y/n)："),
+    ("Copy the files instead of moving them (the originals stay where they are)? (y/n): ", "复制文件而不是移动（原文件保留在原处）？(y/n)："),
+    ("Copying", "正在复制"),
+    ("Moving", "正在移动"),
+    ("Would copy {} -> {}", "将复制 {} -> {}"),
+    ("Would move {} -> {}", "将移动 {} -> {}"),
+    ("Moving skipped.", "已跳过移动。"),
+    ("Would copy {} across {} files", "将复制 {}，共 {} 个文件"),
+    ("Would move {} across {} files", "将移动 {}，共 {} 个文件"),
+    ("Copied {} across {} files", "已复制 {}，共 {} 个文件"),
+    ("File organization completed!", "文件整理完成！"),
+    ("Moved {} across {} files", "已移动 {}，共 {} 个文件"),
+    ("Remove source folders left empty by the move? (y/n): ", "移除移动后留下的空源文件夹？(y/n)："),
+    ("Removed empty folder {}", "已移除空文件夹 {}"),
+    ("Removed {} empty folders", "已移除 {} 个空文件夹"),
+    ("\nCheck and remove duplicate files? (y/n): ", "\n检查并删除重复文件？(y/n)："),
+    ("Duplicate removal skipped.", "已跳过重复文件删除。"),
+    ("\nWould copy {} files, would delete 0 files", "\n将复制 {} 个文件，将删除 0 个文件"),
+    ("\nWould move {} files, would delete 0 files", "\n将移动 {} 个文件，将删除 0 个文件"),
+    ("Folder whose copies are always kept (empty for none): ", "始终保留其中副本的文件夹（留空表示没有）："),
+    ("Verify duplicates byte for byte (slower, never trusts the hash alone)? (y/n): ", "逐字节核对重复文件（较慢，从不只信任哈希）？(y/n)："),
+    ("Compare files across all categories instead of within each category folder? (y/n): ", "跨所有分类比较文件，而不是在每个分类文件夹内比较？(y/n)："),
+    ("Look for whole folders duplicated elsewhere, e.g. in backups? (y/n): ", "查找在别处（如备份中）整个重复的文件夹？(y/n)："),
+    ("Also look for images that look alike, e.g. resized or re-compressed copies (reported only)? (y/n): ", "同时查找外观相似的图片，如缩放或重新压缩的副本（仅报告）？(y/n)："),
+    ("Also look for the same songs in other encodings, e.g. MP3 and FLAC (reported only)? (y/n): ", "同时查找其他编码的相同歌曲，如 MP3 和 FLAC（仅报告）？(y/n)："),
+    ("Also look for the same videos at other bitrates or in other containers, using ffmpeg (reported only)? (y/n): ", "同时使用 ffmpeg 查找其他码率或其他容器格式的相同视频（仅报告）？(y/n)："),
+    ("Hashing", "正在计算哈希"),
+    ("\nFingerprinting audio files...", "\n正在提取音频指纹..."),
+    ("\nSampling video frames...", "\n正在采样视频帧..."),
+    ("JSON file to write the near-duplicates to for review (leave empty to skip): ", "写入待查看近似重复项的 JSON 文件（留空跳过）："),
+    ("Near-duplicates written to {}", "近似重复项已写入 {}"),
+    ("Failed to write {}: {}", "无法写入 {}：{}"),
+    ("Review each duplicate group before removing anything? (y/n): ", "删除前逐组查看重复文件？(y/n)："),
+    ("Review failed, no duplicates will be removed: {}", "查看失败，不会删除任何重复文件：{}"),
+    ("\nAfter review: {} files to remove, reclaiming {}", "\n查看后：待移除 {} 个文件，回收 {}"),
+    ("CSV file to export the duplicate groups to (leave empty to skip): ", "导出重复文件组的 CSV 文件（留空跳过）："),
+    ("Duplicate groups exported to {}", "重复文件组已导出到 {}"),
+    ("\nNo duplicate files detected!", "\n未检测到重复文件！"),
+    ("\nDelete {} files, reclaiming {}? (y/n): ", "\n删除 {} 个文件，回收 {}？(y/n)："),
+    ("\nReplace {} files with hard links, reclaiming {}? (y/n): ", "\n将 {} 个文件替换为硬链接，回收 {}？(y/n)："),
+    ("\nReplace {} files with symbolic links, reclaiming {}? (y/n): ", "\n将 {} 个文件替换为符号链接，回收 {}？(y/n)："),
+    ("Deleted {}", "已删除 {}"),
+    ("Duplicate files deleted!", "重复文件已删除！"),
+    ("Removed {} emptied duplicate folders", "已移除 {} 个已清空的重复文件夹"),
+    ("Linked {} to {}", "已将 {} 硬链接到 {}"),
+    ("Duplicate files replaced with hard links!", "重复文件已替换为硬链接！"),
+    ("Symlinked {} to {}", "已将 {} 符号链接到 {}"),
+    ("Duplicate files replaced with symbolic links!", "重复文件已替换为符号链接！"),
+    ("Deletion cancelled. No files were removed.", "已取消删除，未移除任何文件。"),
+    ("\nDuplicates listed only, pass --dedupe-action (or --delete-dupes, --hardlink) to remove them.", "\n仅列出了重复文件，传入 --dedupe-action（或 --delete-dupes、--hardlink）以移除它们。"),
+    ("\nWould copy {} files, would delete {} files, would link {} files", "\n将复制 {} 个文件，将删除 {} 个文件，将链接 {} 个文件"),
+    ("\nWould move {} files, would delete {} files, would link {} files", "\n将移动 {} 个文件，将删除 {} 个文件，将链接 {} 个文件"),
+    ("Failed to {} {}: {}", "无法{} {}：{}"),
+    ("read metadata of", "读取元数据"),
+    ("hash", "计算哈希"),
+    ("compare", "比较"),
+    ("decode image", "解码图片"),
+    ("decode audio", "解码音频"),
+    ("decode video", "解码视频"),
+    ("create folder", "创建文件夹"),
+    ("move", "移动"),
+    ("copy", "复制"),
+    ("preserve modification time and permissions of", "保留修改时间和权限"),
+    ("record in journal", "写入日志"),
+    ("read or write hash cache", "读写哈希缓存"),
+    ("read or write checkpoint", "读写检查点"),
+    ("delete", "删除"),
+    ("link", "链接"),
+    ("remove folder", "移除文件夹"),
+    ("Failed to read the answer: {}", "无法读取回答：{}"),
+    ("{} has no subfolders to organize.", "{} 下没有可整理的子文件夹。"),
+    ("Directories to organize, the first one holds the category folders (space toggles, enter confirms)", "要整理的目录，类别文件夹建在第一个目录中（空格切换选择，回车确认）"),
+    ("No directory selected, nothing to organize.", "未选择任何目录，无需整理。"),
+    ("\nInput closed, stopping without further changes.", "\n输入已关闭，停止且不再做任何更改。"),
+    ("  Hash {} matched {} files whose contents differ (starting with {}); split into {} groups", "  哈希 {} 对应的 {} 个文件内容不同（首个为 {}），已拆分为 {} 组"),
+    ("Extra categories as name=extensions, separated by spaces (e.g. ebooks=epub,mobi fonts=ttf,otf; empty for none): ", "本次运行额外的分类，格式为 名称=扩展名，以空格分隔（如 ebooks=epub,mobi fonts=ttf,otf；留空表示没有）："),
+    ("Invalid category {}: {}", "无效的分类 {}：{}"),
+    ("expected NAME=EXT[,EXT...]", "应为 名称=扩展名[,扩展名...]"),
+    ("the name must be a plain folder name", "名称必须是普通的文件夹名"),
+    ("no extensions given", "未给出扩展名"),
+    ("No category: {}", "无分类：{}"),
+    ("{} files match no category and stay where they are.", "{} 个文件不属于任何分类，保留在原处。"),
+    ("\nWhat should happen to files that match no category?", "\n不属于任何分类的文件如何处理？"),
+    ("  1) Leave them where they are (default)", "  1) 保留在原处（默认）"),
+    ("  2) Leave them, but list them", "  2) 保留在原处，但列出它们"),
+    ("  3) Move them into 'other'", "  3) 移动到 'other'"),
+    ("Choose 1-3: ", "请选择 1-3："),
+    ("Skipping symlink {}, it leads back to a folder being scanned", "跳过符号链接 {}，它指回正在扫描的文件夹"),
+    ("Maximum folder depth to scan, 1 for only the top level (leave empty for no limit): ", "最大扫描文件夹深度，1 表示只扫描顶层（留空表示不限制）："),
+    ("Stay on the directory's filesystem, skipping mounted drives and network shares? (y/n): ", "只扫描该目录所在的文件系统，跳过挂载的磁盘和网络共享吗？(y/n)："),
+    ("Category", "类别"),
+    ("Files", "文件数"),
+    ("Size", "大小"),
+    ("Total", "合计"),
+    ("\nLargest files:", "\n最大的文件："),
+    ("\nSpace savings from removing duplicates:", "\n移除重复文件可节省的空间："),
+    ("Duplicates", "重复文件"),
+    ("Folders", "文件夹"),
+    ("Not enough space on {}: {} needed, {} available", "{} 空间不足：需要 {}，可用 {}"),
+    ("\nInterrupted after organizing {} files and removing {} duplicates. Run again to continue where this run stopped.", "\n已整理 {} 个文件、删除 {} 个重复文件后中断。再次运行即可从中断处继续。"),
+    ("Failed to install the Ctrl+C handler: {}", "无法安装 Ctrl+C 处理程序：{}"),
+    ("Analyzing", "分析中"),
+    ("Unknown category: {}", "未知类别：{}"),
+    ("Categories to organize, comma-separated (e.g. image,video; empty for all): ", "要整理的类别，用逗号分隔（如 image,video；留空表示全部）："),
+    ("Unknown category {}, organizing all categories", "未知类别 {}，将整理所有类别"),
+    ("Categories to organize (space toggles, enter confirms)", "要整理的类别（空格切换选择，回车确认）"),
+    ("No category selected, nothing to organize.", "未选择任何类别，无需整理。"),
+    ("scanned", "已扫描"),
+    ("Also compare the files outside the category folders, classified or not? (y/n): ", "是否同时比较类别文件夹以外的文件（无论是否已分类）？(y/n)："),
+    ("Reference folder {} lies inside a folder being organized, its files would be moved", "参考文件夹 {} 位于要整理的文件夹中，其中的文件会被移动"),
+    ("Reference folder whose files are never removed, only files also in it count as duplicates (empty for none): ", "参考文件夹，其中的文件绝不会被删除，只有在其中也存在的文件才算重复（留空表示没有）："),
+    ("Image", "图片"),
+    ("Audio", "音频"),
+    ("Video", "视频"),
+    ("Office", "文档"),
+    ("Archive", "压缩包"),
+    ("Code", "代码"),
+    ("Other", "其他"),
+    ("classified", "已分类"),
+];
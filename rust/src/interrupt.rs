@@ -0,0 +1,102 @@
+/*
+File: interrupt.rs
+
+Stopping a run cleanly on Ctrl+C (or SIGTERM). `install` replaces the default handling,
+which kills the process on the spot and can leave a half-copied `.part` file behind,
+with a flag that the long-running steps check between files: the scan stops walking,
+the mover finishes the files it has started and plans no more, hashing and duplicate
+removal skip the files left. The caller then sees `is_interrupted` and wraps up; the
+journal records every move as it happens and checkpoints keep the scan and the hashes,
+so the next run resumes where this one stopped. A second interrupt exits at once, and
+so does one arriving while the program waits for an answer (`exit_on_interrupt`), when
+nothing is in flight. Without `install` the flag is never raised.
+*/
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Exit code of a process ended by an interrupt, as shells report for SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+// Set while an interrupt should end the program right away
+static IDLE: AtomicBool = AtomicBool::new(false);
+
+// Handle interrupts by raising the flag instead of ending the process
+pub fn install() -> io::Result<()> {
+    install_handler()
+}
+
+// Whether an interrupt arrived since `install`
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+// Run `f`, typically a wait for user input, exiting at once on an interrupt meanwhile
+pub fn exit_on_interrupt<T>(f: impl FnOnce() -> T) -> T {
+    IDLE.store(true, Ordering::SeqCst);
+    let result = f();
+    IDLE.store(false, Ordering::SeqCst);
+    result
+}
+
+// Called from the signal handler: only atomics and an immediate exit are safe there
+fn on_interrupt() {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) || IDLE.load(Ordering::SeqCst) {
+        exit_now();
+    }
+}
+
+#[cfg(unix)]
+fn install_handler() -> io::Result<()> {
+    extern "C" fn handler(_signal: libc::c_int) {
+        on_interrupt();
+    }
+    let handler: extern "C" fn(libc::c_int) = handler;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches an atomic and may call _exit, both async-signal-safe
+        if unsafe { libc::signal(signal, handler as libc::sighandler_t) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn exit_now() {
+    // SAFETY: _exit ends the process without running anything that isn't signal-safe
+    unsafe { libc::_exit(INTERRUPTED_EXIT_CODE) }
+}
+
+#[cfg(windows)]
+fn install_handler() -> io::Result<()> {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+    // Runs on a thread of its own; Ctrl+C and Ctrl+Break are handled, closing the console isn't
+    unsafe extern "system" fn handler(ctrl_type: u32) -> i32 {
+        if ctrl_type > 1 {
+            return 0;
+        }
+        on_interrupt();
+        1
+    }
+    // SAFETY: `handler` lives for the whole program
+    if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn exit_now() {
+    std::process::exit(INTERRUPTED_EXIT_CODE);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn install_handler() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "interrupts can't be handled on this platform"))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn exit_now() {
+    std::process::exit(INTERRUPTED_EXIT_CODE);
+}
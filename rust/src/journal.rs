@@ -0,0 +1,218 @@
+/*
+File: journal.rs
+
+Transaction log of the moves made while organizing, so a run can be undone.
+Every successful move is appended as one JSON object per line
+(`{"src": ..., "dst": ...}`) to JOURNAL_FILE in the organized root.
+Paths are written as strings; a path that is not valid UTF-8 is written as the array of
+its raw bytes (Unix) or UTF-16 code units (Windows) instead, so undo restores it exactly.
+*/
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+// Journal file name, created in the organized root
+pub const JOURNAL_FILE: &str = ".organizer_journal.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    #[serde(with = "path_repr")]
+    pub src: PathBuf,
+    #[serde(with = "path_repr")]
+    pub dst: PathBuf,
+}
+
+// Lossless JSON form of a path: a string when possible, raw units otherwise
+mod path_repr {
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[cfg(unix)]
+    type Unit = u8;
+    #[cfg(windows)]
+    type Unit = u16;
+    #[cfg(not(any(unix, windows)))]
+    type Unit = u8;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Text(String),
+        Raw(Vec<Unit>),
+    }
+
+    #[cfg(unix)]
+    fn to_units(path: &Path) -> Vec<Unit> {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    }
+
+    #[cfg(unix)]
+    fn from_units(units: Vec<Unit>) -> OsString {
+        use std::os::unix::ffi::OsStringExt;
+        OsString::from_vec(units)
+    }
+
+    #[cfg(windows)]
+    fn to_units(path: &Path) -> Vec<Unit> {
+        use std::os::windows::ffi::OsStrExt;
+        path.as_os_str().encode_wide().collect()
+    }
+
+    #[cfg(windows)]
+    fn from_units(units: Vec<Unit>) -> OsString {
+        use std::os::windows::ffi::OsStringExt;
+        OsString::from_wide(&units)
+    }
+
+    // Elsewhere every path is valid UTF-8, so the raw form is never written
+    #[cfg(not(any(unix, windows)))]
+    fn to_units(path: &Path) -> Vec<Unit> {
+        path.to_string_lossy().into_owned().into_bytes()
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn from_units(units: Vec<Unit>) -> OsString {
+        String::from_utf8_lossy(&units).into_owned().into()
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        match path.to_str() {
+            Some(text) => Repr::Text(text.to_string()),
+            None => Repr::Raw(to_units(path)),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => PathBuf::from(text),
+            Repr::Raw(units) => PathBuf::from(from_units(units)),
+        })
+    }
+}
+
+// Appends move records to the journal of one root
+pub struct Journal {
+    writer: BufWriter<File>,
+}
+
+impl Journal {
+    // Open (or create) the journal in `root` for appending
+    pub fn open(root: &Path) -> io::Result<Journal> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(root.join(JOURNAL_FILE))?;
+        Ok(Journal { writer: BufWriter::new(file) })
+    }
+
+    // Record one successful move; the line is flushed immediately so a crash loses nothing.
+    // Paths are stored absolute so undo works from any working directory.
+    pub fn record(&mut self, src: &Path, dst: &Path) -> io::Result<()> {
+        let entry = JournalEntry { src: std::path::absolute(src)?, dst: std::path::absolute(dst)? };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+// Read every entry of the journal in `root`, in the order the moves happened
+pub fn read_journal(root: &Path) -> io::Result<Vec<JournalEntry>> {
+    let reader = BufReader::new(File::open(root.join(JOURNAL_FILE))?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line)?);
+    }
+    Ok(entries)
+}
+
+// Remove the journal of `root` once it has been undone
+pub fn clear_journal(root: &Path) -> io::Result<()> {
+    fs::remove_file(root.join(JOURNAL_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::classify::{Classifier, FileType};
+    use crate::mover::{move_files, CollisionPolicy, MoveOptions};
+    use crate::scan::TRASH_FOLDER;
+
+    #[test]
+    fn entries_read_back_in_the_order_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let mut journal = Journal::open(root).unwrap();
+        journal.record(&root.join("a.jpg"), &root.join("image").join("a.jpg")).unwrap();
+        journal.record(&root.join("b.mp3"), &root.join("audio").join("b.mp3")).unwrap();
+        drop(journal);
+
+        let entries = read_journal(root).unwrap();
+        let pairs: Vec<(PathBuf, PathBuf)> = entries.into_iter().map(|entry| (entry.src, entry.dst)).collect();
+        assert_eq!(
+            pairs,
+            [(root.join("a.jpg"), root.join("image").join("a.jpg")), (root.join("b.mp3"), root.join("audio").join("b.mp3"))]
+        );
+        clear_journal(root).unwrap();
+        assert!(!root.join(JOURNAL_FILE).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_are_journaled_as_raw_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let src = root.join(OsStr::from_bytes(b"\xff.jpg"));
+        let dst = root.join("image").join("photo.jpg");
+        Journal::open(root).unwrap().record(&src, &dst).unwrap();
+
+        // The source is written as an array of bytes, the destination as a string
+        let line = fs::read_to_string(root.join(JOURNAL_FILE)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert!(value["src"].is_array());
+        assert_eq!(value["dst"].as_str(), dst.to_str());
+        let entries = read_journal(root).unwrap();
+        assert_eq!((entries[0].src.as_path(), entries[0].dst.as_path()), (src.as_path(), dst.as_path()));
+    }
+
+    #[test]
+    fn undoing_an_overwrite_newest_first_restores_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let target = root.join("image").join("photo.jpg");
+        fs::create_dir(root.join("image")).unwrap();
+        fs::write(&target, b"old").unwrap();
+        fs::write(root.join("photo.jpg"), b"new").unwrap();
+        let files = HashMap::from([(FileType::Image, vec![root.join("photo.jpg")])]);
+        let options = MoveOptions { on_collision: CollisionPolicy::Overwrite, ..MoveOptions::default() };
+        let (moved, errors) = move_files(&files, root, &[], &Classifier::builtin(), &options, |_, _| {});
+        assert!(errors.is_empty());
+        assert_eq!(moved.moves, [(root.join("photo.jpg"), target.clone())]);
+
+        // The overwritten file's trip to the trash folder is journaled before the move
+        // that replaced it
+        let entries = read_journal(root).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].src, target);
+        assert!(entries[0].dst.starts_with(root.join(TRASH_FOLDER)));
+        assert_eq!((entries[1].src.as_path(), entries[1].dst.as_path()), (root.join("photo.jpg").as_path(), target.as_path()));
+
+        // Undone newest first, as `organizer undo` does
+        for entry in entries.iter().rev() {
+            fs::rename(&entry.dst, &entry.src).unwrap();
+        }
+        assert_eq!(fs::read(root.join("photo.jpg")).unwrap(), b"new");
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+    }
+}
@@ -0,0 +1,60 @@
+/*
+File: lib.rs
+
+Building blocks of the `organizer` binary (src/main.rs).
+- `cache`: persistent cache of content hashes, so unchanged files aren't hashed again.
+- `clean`: finding and removing zero-byte files and folders left empty.
+- `checkpoint`: checkpoints of the scan and of hashing, so an interrupted run can resume.
+- `classify`: file categories (`FileType`), their extension lists and type detection.
+- `dedupe`: content hashing (BLAKE3 or SHA-256), duplicate grouping, and deleting or
+  hard-linking the duplicates.
+- `error`: per-file failures (`OperationError`) returned instead of printed.
+- `exif`: capture date, camera and GPS position of photos, for the by-date and
+  by-camera layouts.
+- `i18n`: English and Chinese user-facing messages, and the language of the run.
+- `fingerprint`: acoustic fingerprints of audio files, pairing the same recording
+  stored in different encodings and telling which copy has the higher quality.
+- `filter`: hidden-file and glob exclusion rules applied while walking.
+- `interrupt`: Ctrl+C handling, so a run stops between files instead of mid-copy.
+- `journal`: log of the moves made, used to undo a run.
+- `mover`: moving classified files into category folders (flat, mirrored, by date,
+  camera or artist, or by template), name-collision policies, merge-mode filtering and
+  empty-folder cleanup.
+- `organize`: a whole run (scan, move, duplicate search) as one `organize` call.
+- `report`: JSON report of classification and duplicate results.
+- `scan`: walking the directory and classifying the files found.
+- `space`: free space on the destination, checked before files are copied there.
+- `similar`: perceptual (dHash or pHash) grouping of near-duplicate images.
+- `tags`: artist, album and title tags of MP3, FLAC and Ogg files, for the by-artist layout.
+- `template`: destination path templates such as `{category}/{year}/{month}/{filename}`.
+- `trees`: whole folders duplicated elsewhere (e.g. in backups), found from their files' hashes.
+- `units`: human-readable byte sizes and file counts for run summaries.
+- `video`: frames sampled with ffmpeg, pairing copies of a video at other bitrates or
+  in other containers.
+- `watch`: watch mode, moving new files into their category folders once they settle.
+*/
+
+pub mod cache;
+pub mod checkpoint;
+pub mod clean;
+pub mod classify;
+pub mod dedupe;
+pub mod error;
+pub mod exif;
+pub mod filter;
+pub mod fingerprint;
+pub mod i18n;
+pub mod interrupt;
+pub mod journal;
+pub mod mover;
+pub mod organize;
+pub mod report;
+pub mod scan;
+pub mod similar;
+pub mod space;
+pub mod tags;
+pub mod template;
+pub mod trees;
+pub mod units;
+pub mod video;
+pub mod watch;
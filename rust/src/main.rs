@@ -1,315 +1,2186 @@
 /*
 File: organizer.rs
 
-A simple file organizer utility in Rust.
-Features:
-- Scans a user-specified directory.
-- Classifies files into Image, Audio, Video, and Office document types by extension.
-- Moves files into type-specific subdirectories (supports cross-filesystem move).
-- After moving, optionally scans for duplicates (by SHA-256 hash) of images, audio, video, and office files.
-- Displays duplicate sets and can optionally delete all duplicate files except one in each group.
-- Outputs errors to stderr if encountered (file access, I/O etc).
-3rd party dependencies: walkdir, sha2, console
+Command-line front end of the organizer library: classifies the files of a directory by
+type, moves them into category folders and finds (and optionally removes) duplicates.
+
+Usage:
+  organizer                             ask for every decision on stdin
+  organizer --dir <path> [options]      organize non-interactively, see --help for the options
+  organizer --base-path <path>          pick the directories to organize from its subfolders
+  organizer analyze <dir>               report categories and duplicates, write nothing
+  organizer dedupe <dir>...             find duplicates across directories
+  organizer clean <dir>                 remove empty files and folders
+  organizer watch <dir>                 keep organizing a hot folder
+  organizer undo <dir>                  move journaled files back
+
+The scan, move and duplicate steps live in the library (`organizer::scan`, `organizer::mover`,
+`organizer::dedupe`, `organizer::organize`); this binary only prompts and prints around them.
+Messages are in English or Chinese (`--lang en|zh`) through `organizer::i18n`.
 Author: wangyifan
 Date: 2026
 */
 
-use std::fs::{self, File};
-use std::io::{self, Write, Read, BufReader};
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
-use console::Style;
+use std::process::{self, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+use clap::{Parser, Subcommand};
+use console::{pad_str, Alignment, Style};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{MultiSelect, Select};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use std::collections::HashMap;
-use sha2::{Sha256, Digest};
-
-// Supported file extensions for each category
-const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "gif", "webp", "tiff"];
-const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "aac", "flac", "ogg", "m4a", "wma"];
-const VIDEO_EXTENSIONS: &[&str] = &["mp4", "avi", "wmv", "mov", "flv", "mkv", "webm"];
-const OFFICE_EXTENSIONS: &[&str] = &["doc", "docx", "xls", "xlsx", "ppt", "pptx", "pdf", "csv", "txt"];
-
-// Enum for file type categories
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum FileType {
-    Image,
-    Audio,
-    Video,
-    Office,
-}
-
-// Detect the file type based on its extension
-fn detect_file_type(file_name: &str) -> Option<FileType> {
-    let extension = Path::new(file_name)
-        .extension().and_then(|s| s.to_str()).unwrap_or("").to_ascii_lowercase();
-    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
-        Some(FileType::Image)
-    } else if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
-        Some(FileType::Audio)
-    } else if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
-        Some(FileType::Video)
-    } else if OFFICE_EXTENSIONS.contains(&extension.as_str()) {
-        Some(FileType::Office)
-    } else {
-        None
+use organizer::classify::{Classifier, FileType, Unmatched, CONFIG_FILE};
+use organizer::clean::{find_empty, remove_empty};
+use organizer::dedupe::{
+    delete_files, hardlink_files, symlink_files, DedupAction, DedupOptions, DeleteMode, HashAlgo, HashMismatch, HashStrategy, KeepStrategy,
+    LinkTarget, DEFAULT_PARTIAL_WINDOW,
+};
+use organizer::error::{Operation, OperationError};
+use organizer::filter::{ScanFilter, IGNORE_FILE};
+use organizer::fingerprint::{SimilarAudio, DEFAULT_MAX_BIT_ERROR};
+use organizer::similar::{PerceptualHash, SimilarImage, DEFAULT_MAX_DISTANCE};
+use organizer::template::PathTemplate;
+use organizer::trees::{remove_emptied_trees, DuplicateTree};
+use organizer::video::{SimilarVideo, DEFAULT_MAX_FRAME_DISTANCE};
+use organizer::journal::{clear_journal, read_journal, JOURNAL_FILE};
+use organizer::mover::{move_file_support_cross_partition, Collision, CollisionPolicy, Layout};
+use organizer::cache::HASH_CACHE_FILE;
+use organizer::checkpoint::has_checkpoint;
+use organizer::organize::{
+    add_reference_files, directories_scope, duplicate_groups, has_category_folders, reference_groups, find_scope_duplicates, load_hash_cache, organize_with_progress,
+    reclaimable_bytes, tree_reclaimable_bytes, DuplicateGroup, OrganizeEvent, OrganizeOptions, OrganizeResult,
+};
+use organizer::organize;
+use organizer::scan::{ScanResult, SizeStats, TRASH_FOLDER};
+use organizer::report::{write_duplicates_csv, Report};
+use organizer::units::{format_bytes, format_count};
+use organizer::watch::{watch_and_organize, WatchEvent, DEFAULT_SETTLE_TIME};
+use organizer::i18n::{self, Lang};
+use organizer::interrupt::{self, exit_on_interrupt, is_interrupted, INTERRUPTED_EXIT_CODE};
+
+// A message in the language of the run (`organizer::i18n`), looked up by its English
+// text; with arguments its `{}` placeholders are filled in order
+macro_rules! tr {
+    ($message:literal) => {
+        i18n::tr($message)
+    };
+    ($message:literal, $($arg:expr),+ $(,)?) => {
+        i18n::fill(i18n::tr($message), &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+
+// `println!` of a message in the language of the run; only `{}` placeholders
+macro_rules! say {
+    ($message:literal $(, $arg:expr)* $(,)?) => {
+        println!("{}", i18n::fill(i18n::tr($message), &[$(&$arg as &dyn std::fmt::Display),*]))
+    };
+}
+
+// `eprintln!` of a message in the language of the run
+macro_rules! say_err {
+    ($message:literal $(, $arg:expr)* $(,)?) => {
+        eprintln!("{}", i18n::fill(i18n::tr($message), &[$(&$arg as &dyn std::fmt::Display),*]))
+    };
+}
+
+// Print how many files were found in each category; `other` only when it was collected
+fn print_file_stats(stats: &HashMap<FileType, usize>, sizes: &SizeStats, classifier: &Classifier) {
+    let heading = Style::new().blue().bold();
+    println!("{}", heading.apply_to(tr!("\nFile category statistics:")));
+    print_row(tr!("Category"), tr!("Files"), tr!("Size"));
+    for category in classifier.categories() {
+        if let Some(count) = stats.get(&category.file_type) {
+            let bytes = sizes.bytes.get(&category.file_type).copied().unwrap_or(0);
+            print_row(i18n::tr(category.file_type.label()), &format_count(*count), &format_bytes(bytes));
+        }
+    }
+    print_row(tr!("Total"), &format_count(stats.values().sum()), &format_bytes(sizes.total()));
+    if !sizes.largest.is_empty() {
+        println!("{}", heading.apply_to(tr!("\nLargest files:")));
+        for (path, bytes) in &sizes.largest {
+            println!("{:>10}  {}", format_bytes(*bytes), path.display());
+        }
     }
 }
 
-// Scans a directory and returns statistics and full file paths grouped by type
-fn scan_and_classify_files(root: &Path) -> (HashMap<FileType, usize>, HashMap<FileType, Vec<PathBuf>>) {
-    let mut stats = HashMap::from([
-        (FileType::Image, 0),
-        (FileType::Audio, 0),
-        (FileType::Video, 0),
-        (FileType::Office, 0),
-    ]);
-    let mut files: HashMap<FileType, Vec<PathBuf>> = HashMap::new();
+// Report the symlinks a scan followed out of its directory, and those it skipped as loops
+fn print_scan_links(scan: &ScanResult) {
+    for link in &scan.followed_links {
+        say!("Following symlink {} outside the directory", link.display());
+    }
+    for link in &scan.link_loops {
+        say!("Skipping symlink {}, it leads back to a folder being scanned", link.display());
+    }
+}
 
-    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
-        if !entry.file_type().is_file() {
-            continue;
+// Print what a scan found, as sorted into categories, and record it in `report`: the
+// counts and sizes per category, and the unmatched files when they are reported
+fn show_scan(scan: &ScanResult, sizes: &SizeStats, classifier: &Classifier, report: &mut Report) {
+    report.files_scanned = scan.stats.values().sum();
+    print_file_stats(&scan.stats, sizes, classifier);
+    if classifier.unmatched() == Unmatched::Report {
+        for path in &scan.unmatched {
+            say!("No category: {}", path.display());
         }
-        let file_name = entry.file_name().to_string_lossy();
-        if let Some(file_type) = detect_file_type(&file_name) {
-            stats.entry(file_type.clone()).and_modify(|e| *e += 1);
-            files.entry(file_type).or_insert(Vec::new()).push(entry.path().to_path_buf());
+        say!("{} files match no category and stay where they are.", format_count(scan.unmatched.len()));
+        report.add_unmatched(&scan.unmatched);
+    }
+    for category in classifier.categories() {
+        if let Some(paths) = scan.files.get(&category.file_type) {
+            let bytes = sizes.bytes.get(&category.file_type).copied().unwrap_or(0);
+            report.add_category(&category.folder, paths, bytes);
         }
     }
-    (stats, files)
+    report.add_largest_files(&sizes.largest);
 }
 
-// Print how many files were found in each category
-fn print_file_stats(stats: &HashMap<FileType, usize>) {
-    let heading = Style::new().blue().bold();
-    println!("{}", heading.apply_to("\nFile category statistics:"));
-    println!("Images : {}", stats.get(&FileType::Image).unwrap_or(&0));
-    println!("Audio  : {}", stats.get(&FileType::Audio).unwrap_or(&0));
-    println!("Video  : {}", stats.get(&FileType::Video).unwrap_or(&0));
-    println!("Office : {}", stats.get(&FileType::Office).unwrap_or(&0));
-}
-
-// Returns a file name (with numeric suffix if needed) that does not exist in dest_folder
-fn get_non_duplicate_name(dest_folder: &Path, file_name: &str) -> PathBuf {
-    let stem = Path::new(file_name).file_stem().unwrap_or_default().to_os_string();
-    let ext = Path::new(file_name).extension().and_then(|s| s.to_str()).unwrap_or("");
-    let mut counter = 1;
-    let mut candidate = dest_folder.join(file_name);
-    while candidate.exists() {
-        let mut new_stem = stem.clone();
-        new_stem.push(format!("_{}", counter));
-        let mut new_name = new_stem.into_string().unwrap();
-        if !ext.is_empty() {
-            new_name.push('.');
-            new_name.push_str(ext);
-        }
-        candidate = dest_folder.join(&new_name);
-        counter += 1;
-    }
-    candidate
-}
-
-// Move a file. If rename fails due to cross-device, fall back to copy and delete
-fn move_file_support_cross_partition(src: &Path, dst: &Path) -> io::Result<()> {
-    match fs::rename(src, dst) {
-        Ok(_) => Ok(()),
+// Print one row of a summary table: a label and two right-aligned columns. Widths are
+// measured as shown, so Chinese labels line up too.
+fn print_row(label: &str, first: &str, second: &str) {
+    println!(
+        "{} {} {}",
+        pad_str(label, 10, Alignment::Left, None),
+        pad_str(first, 10, Alignment::Right, None),
+        pad_str(second, 10, Alignment::Right, None)
+    );
+}
+
+// Print how much space removing the duplicates frees: the number of duplicate files and
+// their size per category (or for the duplicate folders), and in total
+fn print_savings(savings: &[(String, usize, u64)]) {
+    println!("{}", Style::new().blue().bold().apply_to(tr!("\nSpace savings from removing duplicates:")));
+    print_row(tr!("Category"), tr!("Duplicates"), tr!("Size"));
+    for (label, count, bytes) in savings {
+        print_row(label, &format_count(*count), &format_bytes(*bytes));
+    }
+    let count = savings.iter().map(|(_, count, _)| count).sum();
+    let bytes = savings.iter().map(|(_, _, bytes)| bytes).sum();
+    print_row(tr!("Total"), &format_count(count), &format_bytes(bytes));
+}
+
+// Undo a previous run: move every journaled file back to its original location,
+// newest move first. Entries whose destination is gone, or whose original location
+// is taken again, are skipped. The journal is cleared when nothing failed.
+// Returns the process exit code.
+fn undo(root: &Path) -> i32 {
+    let entries = match read_journal(root) {
+        Ok(entries) => entries,
         Err(e) => {
-            #[allow(deprecated)]
-            if e.kind() == io::ErrorKind::CrossDeviceLink {
-                fs::copy(src, dst)?;
-                fs::remove_file(src)?;
-                Ok(())
-            } else {
-                Err(e)
+            say_err!("Failed to read journal {}: {}", root.join(JOURNAL_FILE).display(), e);
+            return 1;
+        }
+    };
+    let (mut restored, mut skipped, mut failed) = (0usize, 0usize, 0usize);
+    for entry in entries.iter().rev() {
+        if !entry.dst.exists() {
+            say!("Skipped {} (no longer exists)", entry.dst.display());
+            skipped += 1;
+            continue;
+        }
+        if entry.src.exists() {
+            say!("Skipped {} ({} is taken)", entry.dst.display(), entry.src.display());
+            skipped += 1;
+            continue;
+        }
+        if let Some(parent) = entry.src.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                say_err!("Failed to create folder {}: {}", parent.display(), e);
+                failed += 1;
+                continue;
             }
         }
+        match move_file_support_cross_partition(&entry.dst, &entry.src) {
+            Ok(restore_error) => {
+                say!("Restored {} -> {}", entry.dst.display(), entry.src.display());
+                if let Some(e) = restore_error {
+                    say_err!("Failed to preserve metadata of {}: {}", entry.src.display(), e);
+                }
+                restored += 1;
+            }
+            Err(e) => {
+                say_err!("Failed to restore {}: {}", entry.dst.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    say!("Restored {} files, skipped {}, failed {}", restored, skipped, failed);
+    if failed > 0 {
+        return 1;
+    }
+    if let Err(e) = clear_journal(root) {
+        say_err!("Failed to clear journal: {}", e);
     }
+    0
 }
 
-// Move all files for each type into its dedicated subdirectory under root_dir
-fn move_files(file_map: &HashMap<FileType, Vec<PathBuf>>, root_dir: &Path) {
-    // Mapping of file type to folder names
-    let folder_map = [
-        (FileType::Image, "image"),
-        (FileType::Audio, "audio"),
-        (FileType::Video, "video"),
-        (FileType::Office, "office"),
-    ];
-    for (file_type, folder_name) in folder_map.iter() {
-        let dest_folder = root_dir.join(folder_name);
-        // Create subdirectory if missing
-        if !dest_folder.exists() {
-            if let Err(e) = fs::create_dir_all(&dest_folder) {
-                eprintln!("Failed to create folder {}: {}", dest_folder.display(), e);
-                continue;
-            }
+// Print the duplicate groups found in one category and record them in `report`.
+// Returns every duplicate paired with the file kept from its group, plus the bytes
+// the duplicates take up and the files whose size couldn't be read.
+fn show_and_list_duplicates(
+    groups: &[DuplicateGroup],
+    category: &str,
+    report: &mut Report,
+) -> (Vec<(PathBuf, PathBuf)>, u64, Vec<OperationError>) {
+    if groups.is_empty() {
+        say!("No duplicate {} files found.", i18n::tr(category));
+        return (Vec::new(), 0, Vec::new());
+    }
+
+    println!("{}", Style::new().red().bold().apply_to(tr!("\nDuplicate {} files found:", i18n::tr(category))));
+    let mut files_to_delete = Vec::new();
+    for group in groups {
+        say!("  Hash: {} ({} files)", group.hash, group.duplicates.len() + 1);
+        say!("   Keep: {}", group.kept.display());
+        for dup in &group.duplicates {
+            say!("   DELETE: {}", dup.display());
+            files_to_delete.push((dup.clone(), group.kept.clone()));
+        }
+        report.add_duplicate_group(&category.to_lowercase(), &group.hash, &group.kept, &group.duplicates);
+    }
+    // A file whose size can't be read just doesn't count towards the total
+    let (reclaimed, errors) = reclaimable_bytes(groups);
+    say!("Total duplicate {} files to delete: {}", i18n::tr(category), files_to_delete.len());
+    say!("Space to be reclaimed from {} files: {}", i18n::tr(category), format_bytes(reclaimed));
+    (files_to_delete, reclaimed, errors)
+}
+
+// Print the folders with whole copies elsewhere and record them in `report`. Returns
+// every file of the copies paired with its kept file, and the bytes the copies take up.
+fn show_duplicate_trees(trees: &[DuplicateTree], report: &mut Report) -> (Vec<(PathBuf, PathBuf)>, u64) {
+    if trees.is_empty() {
+        say!("No duplicate folders found.");
+        return (Vec::new(), 0);
+    }
+    println!("{}", Style::new().red().bold().apply_to(tr!("\nDuplicate folders found (their files are not listed again below):")));
+    for tree in trees {
+        say!("  {} files, {} each", format_count(tree.file_count), format_bytes(tree.bytes));
+        say!("   Keep: {}", tree.kept.display());
+        for copy in &tree.duplicates {
+            say!("   DELETE: {}", copy.display());
+        }
+        report.add_duplicate_tree(tree);
+    }
+    let reclaimed = tree_reclaimable_bytes(trees);
+    say!("Space to be reclaimed from duplicate folders: {}", format_bytes(reclaimed));
+    (trees.iter().flat_map(|tree| tree.pairs.iter().cloned()).collect(), reclaimed)
+}
+
+// Print the pairs of images that look alike and record them in `report`
+fn show_similar_images(pairs: &[SimilarImage], report: &mut Report) {
+    if pairs.is_empty() {
+        say!("No images that look alike found.");
+        return;
+    }
+    println!("{}", Style::new().yellow().bold().apply_to(tr!("\nImages that look alike (for review, not removed):")));
+    for pair in pairs {
+        say!("  {} bits apart", pair.distance);
+        println!("   {}", pair.first.display());
+        println!("   {}", pair.second.display());
+        report.add_similar_image(pair);
+    }
+}
+
+// Print the pairs of audio files holding the same recording and record them in `report`
+fn show_similar_audio(pairs: &[SimilarAudio], report: &mut Report) {
+    if pairs.is_empty() {
+        say!("No re-encoded copies of the same audio found.");
+        return;
+    }
+    println!("{}", Style::new().yellow().bold().apply_to(tr!("\nSame audio in different encodings (not removed):")));
+    for pair in pairs {
+        say!("  {}% of fingerprint bits differ", format!("{:.0}", pair.bit_error * 100.0));
+        say!("   Better: {} ({})", pair.better.display(), pair.better_quality.describe());
+        say!("   Other:  {} ({})", pair.other.display(), pair.other_quality.describe());
+        report.add_similar_audio(pair);
+    }
+}
+
+// Print the pairs of videos that look alike and record them in `report`
+fn show_similar_videos(pairs: &[SimilarVideo], report: &mut Report) {
+    if pairs.is_empty() {
+        say!("No copies of the same video found.");
+        return;
+    }
+    println!("{}", Style::new().yellow().bold().apply_to(tr!("\nVideos that look alike (candidates, not removed):")));
+    for pair in pairs {
+        say!("  Frames {} bits apart on average", format!("{:.1}", pair.distance));
+        println!("   {}", pair.first.display());
+        println!("   {}", pair.second.display());
+        report.add_similar_video(pair);
+    }
+}
+
+// Print what the duplicate search of a run found and record it in `report`: the duplicate
+// folders, the groups of every set searched, the near-duplicates asked for in `options`
+// and the savings. Returns every file to remove paired with its kept file, and the bytes
+// they take up.
+fn show_found_duplicates(result: &OrganizeResult, options: &OrganizeOptions, report: &mut Report) -> (Vec<(PathBuf, PathBuf)>, u64) {
+    let mut files_to_delete = Vec::new();
+    let mut reclaimable = 0u64;
+    // Duplicate files and their size per set searched, for the summary
+    let mut savings = Vec::new();
+    if options.duplicate_dirs {
+        let (pairs, bytes) = show_duplicate_trees(&result.duplicate_trees, report);
+        if !pairs.is_empty() {
+            savings.push((tr!("Folders").to_string(), pairs.len(), bytes));
+        }
+        files_to_delete.extend(pairs);
+        reclaimable += bytes;
+    }
+    for label in &result.searched {
+        let groups: Vec<DuplicateGroup> = result.duplicates.iter().filter(|group| &group.category == label).cloned().collect();
+        // Files whose size can't be read are already among the run's errors
+        let (pairs, bytes, _) = show_and_list_duplicates(&groups, label, report);
+        if !pairs.is_empty() {
+            savings.push((i18n::tr(label).to_string(), pairs.len(), bytes));
         }
-        if let Some(paths) = file_map.get(file_type) {
-            for file_path in paths {
-                let file_name = file_path.file_name().unwrap().to_str().unwrap();
-                let target_path = get_non_duplicate_name(&dest_folder, file_name);
-                if file_path != &target_path {
-                    if let Err(e) = move_file_support_cross_partition(file_path, &target_path) {
-                        eprintln!("Failed to move {}: {}", file_path.display(), e);
+        files_to_delete.extend(pairs);
+        reclaimable += bytes;
+    }
+    if options.similar_images.is_some() {
+        show_similar_images(&result.similar_images, report);
+    }
+    if options.similar_audio.is_some() {
+        show_similar_audio(&result.similar_audio, report);
+    }
+    if options.similar_videos.is_some() {
+        show_similar_videos(&result.similar_videos, report);
+    }
+    if !savings.is_empty() {
+        print_savings(&savings);
+    }
+    (files_to_delete, reclaimable)
+}
+
+// Open a file with the desktop's default application, without waiting for it
+fn open_file(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        process::Command::new("open")
+    } else {
+        process::Command::new("xdg-open")
+    };
+    command.arg(path).stdout(Stdio::null()).stderr(Stdio::null()).spawn().map(|_| ())
+}
+
+// Walk through the duplicate groups one by one and let the user decide what happens to
+// each: accept the suggested file to keep, pick the files to keep, skip the group, or
+// open its files first. Returns the groups to remove duplicates from, as decided;
+// files picked to keep besides the first one are left out of their group's duplicates.
+fn review_duplicate_groups(groups: &[DuplicateGroup]) -> dialoguer::Result<Vec<DuplicateGroup>> {
+    let theme = ColorfulTheme::default();
+    let mut reviewed = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        let files: Vec<&PathBuf> = std::iter::once(&group.kept).chain(&group.duplicates).collect();
+        say!("\nGroup {} of {} ({}, {} files):", index + 1, groups.len(), group.category, files.len());
+        for file in &files {
+            println!("  {}", file.display());
+        }
+        let choices = [
+            tr!("Keep {} (suggested)", group.kept.display()),
+            tr!("Choose the files to keep").to_string(),
+            tr!("Skip this group").to_string(),
+            tr!("Open the files").to_string(),
+            tr!("Accept the suggestions for this and all remaining groups").to_string(),
+        ];
+        loop {
+            let choice = Select::with_theme(&theme)
+                .with_prompt(tr!("What should happen to this group?"))
+                .items(&choices)
+                .default(0)
+                .interact()?;
+            match choice {
+                0 => reviewed.push(group.clone()),
+                1 => {
+                    let labels: Vec<String> = files.iter().map(|file| file.display().to_string()).collect();
+                    let mut defaults = vec![false; files.len()];
+                    defaults[0] = true;
+                    let keep = MultiSelect::with_theme(&theme)
+                        .with_prompt(tr!("Files to keep (space toggles, enter confirms)"))
+                        .items(&labels)
+                        .defaults(&defaults)
+                        .interact()?;
+                    let Some(&first) = keep.first() else {
+                        say!("At least one file has to be kept.");
+                        continue;
+                    };
+                    let duplicates: Vec<PathBuf> = (0..files.len())
+                        .filter(|i| !keep.contains(i))
+                        .map(|i| files[i].clone())
+                        .collect();
+                    if !duplicates.is_empty() {
+                        reviewed.push(DuplicateGroup { kept: files[first].clone(), duplicates, ..group.clone() });
                     }
                 }
+                2 => {}
+                3 => {
+                    for file in &files {
+                        if let Err(e) = open_file(file) {
+                            say_err!("Failed to open {}: {}", file.display(), e);
+                        }
+                    }
+                    continue;
+                }
+                _ => {
+                    reviewed.extend(groups[index..].iter().cloned());
+                    return Ok(reviewed);
+                }
+            }
+            break;
+        }
+    }
+    Ok(reviewed)
+}
+
+// The confirmation asked before removing `count` duplicates taking `bytes` as `action` says
+fn dedup_question(action: &DedupAction, count: usize, bytes: u64) -> String {
+    let (count, bytes) = (format_count(count), format_bytes(bytes));
+    match action {
+        DedupAction::Delete(_) => tr!("\nDelete {} files, reclaiming {}? (y/n): ", count, bytes),
+        DedupAction::Hardlink => tr!("\nReplace {} files with hard links, reclaiming {}? (y/n): ", count, bytes),
+        DedupAction::Symlink(_) => tr!("\nReplace {} files with symbolic links, reclaiming {}? (y/n): ", count, bytes),
+    }
+}
+
+// Remove the duplicates of the `(duplicate, kept)` pairs as `action` says, printing each
+// one, and then the folders of `trees` the deletion emptied. Deleted duplicates go
+// into the trash folder and journal of `root` in trash mode. Returns the duplicates
+// removed, the bytes that freed and the failures.
+fn remove_duplicates(
+    action: &DedupAction,
+    pairs: &[(PathBuf, PathBuf)],
+    root: &Path,
+    trees: &[DuplicateTree],
+) -> (Vec<PathBuf>, u64, Vec<OperationError>) {
+    let paths: Vec<PathBuf> = pairs.iter().map(|(dup, _)| dup.clone()).collect();
+    // Sizes are read up front, the duplicates are gone afterwards
+    let sizes: HashMap<&Path, u64> = paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok().map(|m| (path.as_path(), m.len())))
+        .collect();
+    let mut errors = Vec::new();
+    let removed: Vec<PathBuf> = match action {
+        DedupAction::Delete(mode) => {
+            let (deleted, delete_errors) = delete_files(&paths, mode, root);
+            errors.extend(delete_errors);
+            for path in &deleted {
+                say!("Deleted {}", path.display());
+            }
+            say!("Duplicate files deleted!");
+            if !trees.is_empty() {
+                let (removed, remove_errors) = remove_emptied_trees(trees);
+                errors.extend(remove_errors);
+                say!("Removed {} emptied duplicate folders", format_count(removed.len()));
+            }
+            deleted
+        }
+        DedupAction::Hardlink => {
+            let (linked, link_errors) = hardlink_files(pairs);
+            errors.extend(link_errors);
+            for (duplicate, kept) in &linked {
+                say!("Linked {} to {}", duplicate.display(), kept.display());
+            }
+            say!("Duplicate files replaced with hard links!");
+            linked.into_iter().map(|(duplicate, _)| duplicate).collect()
+        }
+        DedupAction::Symlink(target) => {
+            let (linked, link_errors) = symlink_files(pairs, *target);
+            errors.extend(link_errors);
+            for (duplicate, kept) in &linked {
+                say!("Symlinked {} to {}", duplicate.display(), kept.display());
+            }
+            say!("Duplicate files replaced with symbolic links!");
+            linked.into_iter().map(|(duplicate, _)| duplicate).collect()
+        }
+    };
+    let reclaimed = removed.iter().filter_map(|path| sizes.get(path.as_path())).sum();
+    (removed, reclaimed, errors)
+}
+
+// Print what removing the duplicates with `action` would do, for a dry run
+fn print_planned_removals(pairs: &[(PathBuf, PathBuf)], action: &DedupAction) {
+    for (duplicate, kept) in pairs {
+        match action {
+            DedupAction::Delete(DeleteMode::Permanent) => say!("Would delete {}", duplicate.display()),
+            DedupAction::Delete(DeleteMode::TrashFolder(folder)) => {
+                say!("Would move {} to {}", duplicate.display(), folder.display())
+            }
+            DedupAction::Delete(DeleteMode::RecycleBin) => {
+                say!("Would send {} to the recycle bin", duplicate.display())
+            }
+            DedupAction::Hardlink => say!("Would link {} to {}", duplicate.display(), kept.display()),
+            DedupAction::Symlink(_) => say!("Would symlink {} to {}", duplicate.display(), kept.display()),
+        }
+    }
+}
+
+// Progress bar on stderr for a long step, showing counts and ETA.
+// indicatif hides it by itself when stderr is not a terminal; `quiet` hides it always.
+fn progress_bar(message: &'static str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{msg} [{bar:40.cyan/blue}] {pos}/{len} ({rate}, {eta})")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .with_key("rate", write_rate)
+        .progress_chars("=> ");
+    ProgressBar::new(0).with_style(style).with_message(message)
+}
+
+// Whole items per second, for the progress templates
+fn write_rate(state: &ProgressState, out: &mut dyn std::fmt::Write) {
+    let _ = write!(out, "{:.0}/s", state.per_sec());
+}
+
+// Spinner with a running count and rate, for steps whose total isn't known in advance
+fn progress_spinner(message: &'static str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    // A translated template that fails to parse falls back to the plain spinner
+    let style = ProgressStyle::with_template(tr!("{spinner} {msg} {human_pos} entries ({rate})"))
+        .unwrap_or_else(|_| ProgressStyle::default_spinner())
+        .with_key("rate", write_rate);
+    let spinner = ProgressBar::new_spinner().with_style(style).with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+// The progress of an organize run on stderr: a spinner while scanning, then a bar for the
+// move and one for hashing, each started by the first event of its step
+struct RunProgress {
+    scanning: ProgressBar,
+    moving: OnceLock<ProgressBar>,
+    hashing: OnceLock<ProgressBar>,
+    copy: bool,
+    quiet: bool,
+}
+
+impl RunProgress {
+    fn new(copy: bool, quiet: bool) -> RunProgress {
+        RunProgress { scanning: progress_spinner(tr!("Scanning"), quiet), moving: OnceLock::new(), hashing: OnceLock::new(), copy, quiet }
+    }
+
+    // Show one event of `organize_with_progress`
+    fn show(&self, event: OrganizeEvent) {
+        match event {
+            OrganizeEvent::Resuming => self.scanning.suspend(|| say!("Resuming the scan of the interrupted run")),
+            OrganizeEvent::CheckpointOutdated => {
+                self.scanning.suspend(|| say!("The checkpoint was made with other scan settings, scanning again"))
+            }
+            OrganizeEvent::Scanned(walked) => self.scanning.set_position(walked as u64),
+            OrganizeEvent::Moved(done, total) => {
+                self.scanning.finish_and_clear();
+                let moving = self.moving.get_or_init(|| progress_bar(if self.copy { tr!("Copying") } else { tr!("Moving") }, self.quiet));
+                moving.set_length(total as u64);
+                moving.set_position(done as u64);
+            }
+            OrganizeEvent::Hashed(done, total) => {
+                self.finish_moving();
+                let hashing = self.hashing.get_or_init(|| progress_bar(tr!("Hashing"), self.quiet));
+                hashing.set_length(total as u64);
+                hashing.set_position(done as u64);
+            }
+            OrganizeEvent::FingerprintingAudio => {
+                self.finish();
+                if !self.quiet {
+                    say!("\nFingerprinting audio files...");
+                }
             }
+            OrganizeEvent::SamplingVideos => {
+                self.finish();
+                if !self.quiet {
+                    say!("\nSampling video frames...");
+                }
+            }
+        }
+    }
+
+    // Clear the scan and move progress once a later step starts
+    fn finish_moving(&self) {
+        self.scanning.finish_and_clear();
+        if let Some(moving) = self.moving.get() {
+            moving.finish_and_clear();
+        }
+    }
+
+    // Clear every progress line, at the end of the run or before printing
+    fn finish(&self) {
+        self.finish_moving();
+        if let Some(hashing) = self.hashing.get() {
+            hashing.finish_and_clear();
+        }
+    }
+}
+
+// End a run stopped by Ctrl+C once the step in progress has wound down: the journal
+// holds every move made and the checkpoints stay, so the next run resumes from here
+fn exit_interrupted(
+    organized: usize,
+    removed: usize,
+    report: &mut Report,
+    errors: &[OperationError],
+    write_report: impl Fn(&mut Report, &[OperationError]),
+) -> ! {
+    say_err!(
+        "\nInterrupted after organizing {} files and removing {} duplicates. Run again to continue where this run stopped.",
+        format_count(organized),
+        format_count(removed)
+    );
+    write_report(report, errors);
+    print_errors(errors);
+    std::process::exit(INTERRUPTED_EXIT_CODE);
+}
+
+// End-of-run summary of every per-file failure, on stderr
+fn print_errors(errors: &[OperationError]) {
+    if errors.is_empty() {
+        return;
+    }
+    say_err!("\n{} errors occurred:", format_count(errors.len()));
+    for error in errors {
+        match error.error.get_ref().and_then(|e| e.downcast_ref::<HashMismatch>()) {
+            Some(mismatch) => say_err!(
+                "  Hash {} matched {} files whose contents differ (starting with {}); split into {} groups",
+                mismatch.hash,
+                mismatch.files,
+                error.path.display(),
+                mismatch.groups
+            ),
+            None => eprintln!("  {}", error),
+        }
+    }
+}
+
+// Print a question and return the user's trimmed answer. Nothing is changed while a
+// question waits, so a closed or unreadable stdin ends the process.
+fn ask_line(question: &str) -> String {
+    print!("{}", question);
+    let mut answer = String::new();
+    // Nothing is in flight while waiting for an answer, so Ctrl+C quits right away
+    let read = io::stdout()
+        .flush()
+        .and_then(|()| exit_on_interrupt(|| io::stdin().read_line(&mut answer)));
+    match read {
+        Ok(0) => {
+            say_err!("\nInput closed, stopping without further changes.");
+            std::process::exit(1);
+        }
+        Ok(_) => answer.trim().to_string(),
+        Err(e) => {
+            say_err!("Failed to read the answer: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Ask for an optional path; an empty answer means none
+fn ask_path(question: &str) -> Option<PathBuf> {
+    let answer = ask_line(question);
+    (!answer.is_empty()).then(|| PathBuf::from(answer))
+}
+
+// Print a yes/no question and return true only when the user answers "y"
+fn ask_yes_no(question: &str) -> bool {
+    ask_line(question).to_lowercase() == "y"
+}
+
+// Ask for an optional byte count; empty or unparsable input means no limit
+fn ask_size(question: &str) -> Option<u64> {
+    let answer = ask_line(question);
+    if answer.is_empty() {
+        return None;
+    }
+    let size = answer.parse().ok();
+    if size.is_none() {
+        say!("Not a number, no limit applied.");
+    }
+    size
+}
+
+// Ask whether duplicates are deleted (permanently or recoverably) or replaced with links
+fn ask_dedup_action(root: &Path) -> DedupAction {
+    say!("\nPermanently delete, move to trash, or replace with links?");
+    say!("  1) Move to {}/ inside the directory (default)", TRASH_FOLDER);
+    say!("  2) Send to the system recycle bin");
+    say!("  3) Permanently delete");
+    say!("  4) Replace with hard links to the kept file");
+    say!("  5) Replace with relative symbolic links to the kept file");
+    say!("  6) Replace with absolute symbolic links to the kept file");
+    match ask_line(tr!("Choose 1-6: ")).as_str() {
+        "2" => DedupAction::Delete(DeleteMode::RecycleBin),
+        "3" => DedupAction::Delete(DeleteMode::Permanent),
+        "4" => DedupAction::Hardlink,
+        "5" => DedupAction::Symlink(LinkTarget::Relative),
+        "6" => DedupAction::Symlink(LinkTarget::Absolute),
+        _ => DedupAction::Delete(DeleteMode::TrashFolder(root.join(TRASH_FOLDER))),
+    }
+}
+
+// The action requested on the command line for the duplicates found, if any:
+// --dedupe-action, or its shortcuts --delete-dupes, --trash and --hardlink
+fn requested_dedup_action(cli: &Cli, root: &Path) -> Option<DedupAction> {
+    let delete_mode = || {
+        if cli.trash {
+            DeleteMode::RecycleBin
+        } else {
+            parse_delete_mode(&cli.delete_mode, root)
         }
+    };
+    match cli.dedupe_action.as_deref() {
+        Some("hardlink") => Some(DedupAction::Hardlink),
+        Some("symlink") => Some(DedupAction::Symlink(parse_link_target(&cli.symlink_target))),
+        Some(_) => Some(DedupAction::Delete(delete_mode())),
+        None if cli.hardlink => Some(DedupAction::Hardlink),
+        None if cli.delete_dupes || cli.trash => Some(DedupAction::Delete(delete_mode())),
+        None => None,
     }
 }
 
-// Compute SHA-256 hash of the file content. Returns lowercase hex string.
-fn calc_sha256(path: &Path) -> io::Result<String> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 8192];
-    loop {
-        let len = reader.read(&mut buffer)?;
-        if len == 0 { break; }
-        hasher.update(&buffer[..len]);
+// Map a --symlink-target value onto the link target
+fn parse_link_target(value: &str) -> LinkTarget {
+    match value {
+        "absolute" => LinkTarget::Absolute,
+        _ => LinkTarget::Relative,
     }
-    Ok(format!("{:x}", hasher.finalize()))
 }
 
-// Given file paths, group files with same contents (hash) as duplicates
-fn find_duplicates(paths: &[PathBuf]) -> HashMap<String, Vec<PathBuf>> {
-    let mut hash_map: HashMap<String, Vec<PathBuf>> = HashMap::new();
-    for path in paths {
-        match calc_sha256(path) {
-            Ok(hash) => {
-                hash_map.entry(hash).or_insert_with(Vec::new).push(path.clone());
+// Map a --layout value onto the layout
+fn parse_layout(name: &str) -> Layout {
+    match name {
+        "mirror" => Layout::Mirror,
+        "date" => Layout::ByDate,
+        "camera" => Layout::ByCamera,
+        "artist" => Layout::ByArtist,
+        _ => Layout::Flat,
+    }
+}
+
+// The layout chosen by --layout, --mirror or --template
+fn requested_layout(cli: &Cli) -> Layout {
+    match (&cli.template, cli.mirror) {
+        (Some(template), _) => Layout::Template(template.clone()),
+        (None, true) => Layout::Mirror,
+        (None, false) => parse_layout(&cli.layout),
+    }
+}
+
+// Map an --on-collision value onto the collision policy
+fn parse_collision_policy(name: &str) -> CollisionPolicy {
+    match name {
+        "skip" => CollisionPolicy::Skip,
+        "overwrite" => CollisionPolicy::Overwrite,
+        "hash" => CollisionPolicy::Hash,
+        "keep-newer" => CollisionPolicy::KeepNewer,
+        _ => CollisionPolicy::Suffix,
+    }
+}
+
+// Categories come from --config, or organizer.toml in the working directory if present,
+// plus those given with --category, with unmatched files handled as --unmatched says and
+// only the categories of --only, or all but those of --skip; a config that can't be loaded,
+// or an unknown category name, ends the program
+fn load_classifier(cli: &Cli) -> Classifier {
+    let config_path = cli.config.clone().or_else(|| {
+        let default = PathBuf::from(CONFIG_FILE);
+        default.is_file().then_some(default)
+    });
+    let classifier = match &config_path {
+        Some(path) => match Classifier::load(path) {
+            Ok(classifier) => {
+                say!("Using categories from {}", path.display());
+                classifier
+            }
+            Err(e) => {
+                say_err!("Failed to load config {}: {}", path.display(), e);
+                std::process::exit(1);
             }
+        },
+        None => Classifier::builtin(),
+    };
+    let classifier = cli
+        .categories
+        .iter()
+        .fold(classifier, |classifier, (name, extensions)| classifier.with_category(name, extensions))
+        .with_unmatched(parse_unmatched(&cli.unmatched));
+    let classifier = if cli.only.is_empty() { classifier.with_skipped(&cli.skip) } else { classifier.with_only(&cli.only) };
+    classifier.unwrap_or_else(|name| {
+        say_err!("Unknown category: {}", name);
+        std::process::exit(1);
+    })
+}
+
+// Map an --unmatched value onto what happens to unmatched files
+fn parse_unmatched(name: &str) -> Unmatched {
+    match name {
+        "move" => Unmatched::Move,
+        "report" => Unmatched::Report,
+        _ => Unmatched::Ignore,
+    }
+}
+
+// Ask what happens to the files no category claims
+fn ask_unmatched() -> Unmatched {
+    say!("\nWhat should happen to files that match no category?");
+    say!("  1) Leave them where they are (default)");
+    say!("  2) Leave them, but list them");
+    say!("  3) Move them into 'other'");
+    match ask_line(tr!("Choose 1-3: ")).as_str() {
+        "2" => Unmatched::Report,
+        "3" => Unmatched::Move,
+        _ => Unmatched::Ignore,
+    }
+}
+
+// Parse a --category value, NAME=EXT[,EXT...]
+fn parse_category(value: &str) -> Result<(String, Vec<String>), &'static str> {
+    let (name, extensions) = value.split_once('=').ok_or("expected NAME=EXT[,EXT...]")?;
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err("the name must be a plain folder name");
+    }
+    let extensions: Vec<String> = extensions
+        .split(',')
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty())
+        .map(str::to_string)
+        .collect();
+    if extensions.is_empty() {
+        return Err("no extensions given");
+    }
+    Ok((name.to_string(), extensions))
+}
+
+// Ask for categories to add for this run, e.g. "ebooks=epub,mobi fonts=ttf,otf";
+// invalid entries are reported and left out
+fn ask_categories(classifier: Classifier) -> Classifier {
+    let answer = ask_line(tr!("Extra categories as name=extensions, separated by spaces (e.g. ebooks=epub,mobi fonts=ttf,otf; empty for none): "));
+    let classifier = answer.split_whitespace().fold(classifier, |classifier, value| match parse_category(value) {
+        Ok((name, extensions)) => classifier.with_category(&name, &extensions),
+        Err(e) => {
+            say_err!("Invalid category {}: {}", value, i18n::tr(e));
+            classifier
+        }
+    });
+    // Categories are picked from a menu on a terminal, typed otherwise
+    let names: Vec<String> = if console::user_attended_stderr() {
+        let names: Vec<String> = classifier.categories().iter().map(|category| category.file_type.folder_name().to_string()).collect();
+        let picked = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr!("Categories to organize (space toggles, enter confirms)"))
+            .items(&names)
+            .defaults(&vec![true; names.len()])
+            .interact()
+            .unwrap_or_else(|e| {
+                say_err!("Failed to read the answer: {}", e);
+                std::process::exit(1);
+            });
+        if picked.is_empty() {
+            say!("No category selected, nothing to organize.");
+            std::process::exit(0);
+        }
+        if picked.len() == names.len() {
+            return classifier;
+        }
+        picked.into_iter().map(|index| names[index].clone()).collect()
+    } else {
+        let answer = ask_line(tr!("Categories to organize, comma-separated (e.g. image,video; empty for all): "));
+        answer.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+    };
+    if names.is_empty() {
+        return classifier;
+    }
+    match classifier.clone().with_only(&names) {
+        Ok(classifier) => classifier,
+        Err(name) => {
+            say_err!("Unknown category {}, organizing all categories", name);
+            classifier
+        }
+    }
+}
+
+// Ask for the categories of the run and how they are recognized, starting from `classifier`
+// as loaded; also returns whether files without an extension are organized into `other`
+fn ask_classifier(classifier: Classifier) -> (Classifier, bool) {
+    let classifier = ask_categories(classifier);
+    // Content sniffing can only be switched on here, never off over the config
+    let classifier = if ask_yes_no(tr!("Check every file's content and trust it over the extension (slower)? (y/n): ")) {
+        classifier.with_content_sniffing(true)
+    } else {
+        classifier
+    };
+    // Files without an extension are ignored unless the user opts in
+    let include_no_ext = ask_yes_no(tr!("Organize files without an extension into 'other'? (y/n): "));
+    // Other files no category claims are left alone unless listed or moved on request
+    (classifier.with_unmatched(ask_unmatched()), include_no_ext)
+}
+
+// What the scan skips, from the command line or asked interactively
+#[derive(Debug, Clone, Default)]
+struct FilterSettings {
+    exclude: Vec<String>,
+    include_hidden: bool,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    one_filesystem: bool,
+}
+
+impl FilterSettings {
+    // The filter options given on the command line
+    fn from_cli(cli: &Cli) -> FilterSettings {
+        FilterSettings {
+            exclude: cli.exclude.clone(),
+            include_hidden: cli.include_hidden,
+            min_size: cli.min_size,
+            max_size: cli.max_size,
+            follow_symlinks: cli.follow_symlinks,
+            max_depth: cli.max_depth.map(NonZeroUsize::get),
+            one_filesystem: cli.one_filesystem,
+        }
+    }
+}
+
+// Ask what the scan skips; symlinks are only asked about when no flag decided them
+fn ask_filter_settings(cli: &Cli) -> FilterSettings {
+    // Hidden entries and user exclusion globs are skipped by the scan and the duplicate check
+    let patterns = ask_line(tr!("Exclude glob patterns, comma-separated (e.g. node_modules,*.tmp; empty for none): "));
+    let exclude = patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    let include_hidden = ask_yes_no(tr!("Include hidden files and folders? (y/n): "));
+    // Files outside the size range are left out of classification and the duplicate check
+    let min_size = ask_size(tr!("Minimum file size in bytes (leave empty for no limit): "));
+    let max_size = ask_size(tr!("Maximum file size in bytes (leave empty for no limit): "));
+    // Symlinks are skipped unless the user opts in to following them
+    let follow_symlinks = if !cli.follow_symlinks && !cli.skip_symlinks {
+        ask_yes_no(tr!("Follow symbolic links leading outside the directory? (y/n): "))
+    } else {
+        cli.follow_symlinks
+    };
+    // How deep the scan goes, and whether it stays on the directory's filesystem
+    let max_depth = ask_size(tr!("Maximum folder depth to scan, 1 for only the top level (leave empty for no limit): "))
+        .filter(|&depth| depth > 0)
+        .map(|depth| depth as usize);
+    let one_filesystem = ask_yes_no(tr!("Stay on the directory's filesystem, skipping mounted drives and network shares? (y/n): "));
+    FilterSettings { exclude, include_hidden, min_size, max_size, follow_symlinks, max_depth, one_filesystem }
+}
+
+// The scan filter for `root`, with its ignore file; an invalid pattern ends the program
+fn build_filter(root: &Path, settings: &FilterSettings) -> ScanFilter {
+    let filter = match ScanFilter::new(&settings.exclude, settings.include_hidden) {
+        Ok(filter) => filter
+            .with_size_range(settings.min_size, settings.max_size)
+            .with_follow_symlinks(settings.follow_symlinks)
+            .with_max_depth(settings.max_depth)
+            .with_same_file_system(settings.one_filesystem),
+        Err(e) => {
+            say_err!("Invalid exclude pattern: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match filter.with_ignore_file(root) {
+        Ok(filter) => filter,
+        Err(e) => {
+            say_err!("Failed to read {}: {}", root.join(IGNORE_FILE).display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Ask how files are arranged inside their category folders
+fn ask_layout() -> Layout {
+    say!("\nHow should files be arranged inside each category folder?");
+    say!("  1) Flat (default)");
+    say!("  2) Keep their subfolders (e.g. image/2020/summer/)");
+    say!("  3) By year and month taken or modified (e.g. image/2023/07/)");
+    say!("  4) Photos by camera model (e.g. image/Canon EOS 5D/)");
+    say!("  5) Music by artist and album (e.g. audio/Artist/Album/)");
+    say!("  6) A path template (e.g. {{category}}/{{year}}/{{month}}/{{filename}})");
+    match ask_line(tr!("Choose 1-6: ")).as_str() {
+        "2" => Layout::Mirror,
+        "3" => Layout::ByDate,
+        "4" => Layout::ByCamera,
+        "5" => Layout::ByArtist,
+        "6" => match PathTemplate::parse(&ask_line(tr!("Template: "))) {
+            Ok(template) => Layout::Template(template),
             Err(e) => {
-                eprintln!("Failed to hash {}: {}", path.display(), e);
+                say_err!("Invalid template ({}), keeping files flat.", e);
+                Layout::Flat
+            }
+        },
+        _ => Layout::Flat,
+    }
+}
+
+// Ask what to do when a target name is taken
+fn ask_collision_policy() -> CollisionPolicy {
+    say!("\nWhat should happen when a file of the same name is already there?");
+    say!("  1) Add a number to the name, e.g. photo_1.jpg (default)");
+    say!("  2) Add the start of the content hash, e.g. photo_3f2a9c1e.jpg");
+    say!("  3) Skip the file");
+    say!("  4) Overwrite the existing file (it goes to {}/)", TRASH_FOLDER);
+    say!("  5) Overwrite only with a newer file, skip it otherwise");
+    match ask_line(tr!("Choose 1-5: ")).as_str() {
+        "2" => CollisionPolicy::Hash,
+        "3" => CollisionPolicy::Skip,
+        "4" => CollisionPolicy::Overwrite,
+        "5" => CollisionPolicy::KeepNewer,
+        _ => CollisionPolicy::Suffix,
+    }
+}
+
+// Ask how files are moved, over the command line's `options`; re-sorting the files already
+// in category folders is only offered when `can_reorganize`
+fn ask_move_options(options: OrganizeOptions, can_reorganize: bool) -> OrganizeOptions {
+    // Flat category folders unless subfolders or dates should be kept
+    let layout = ask_layout();
+    let on_collision = ask_collision_policy();
+    let reorganize =
+        can_reorganize && ask_yes_no(tr!("Also re-sort the files already in category folders into this layout? (y/n): "));
+    // Copying leaves the originals where they are
+    let copy = ask_yes_no(tr!("Copy the files instead of moving them (the originals stay where they are)? (y/n): "));
+    OrganizeOptions { layout, on_collision, reorganize, copy, ..options }
+}
+
+// List the files whose target name was taken and what was done with each
+fn show_collisions(collisions: &[Collision], dry_run: bool, copy: bool) {
+    for collision in collisions {
+        let (source, existing) = (collision.source.display(), collision.existing.display());
+        match (&collision.target, collision.applied) {
+            (None, _) if collision.identical => say!("Skipped {} (same as {})", source, existing),
+            (Some(_), _) if collision.identical && dry_run => say!("Would remove {} (same as {})", source, existing),
+            (Some(_), _) if collision.identical => say!("Removed {} (same as {})", source, existing),
+            (None, _) => say!("Skipped {} ({} exists)", source, existing),
+            (Some(_), CollisionPolicy::Overwrite) if dry_run => say!("Would overwrite {} with {}", existing, source),
+            (Some(_), CollisionPolicy::Overwrite) => say!("Overwrote {} with {}", existing, source),
+            (Some(target), policy) => {
+                let (target, policy) = (target.display(), policy.describe());
+                match (dry_run, copy) {
+                    (true, false) => say!("{} exists, would move {} to {} ({})", existing, source, target, policy),
+                    (false, false) => say!("{} exists, moved {} to {} ({})", existing, source, target, policy),
+                    (true, true) => say!("{} exists, would copy {} to {} ({})", existing, source, target, policy),
+                    (false, true) => say!("{} exists, copied {} to {} ({})", existing, source, target, policy),
+                }
             }
         }
     }
-    // Retain only those hashes with more than 1 file (i.e., actual duplicates)
-    hash_map.into_iter().filter(|(_, files)| files.len() > 1).collect()
 }
 
-// Print duplicate file info and return all except the first of each duplicate group for deletion
-fn show_and_list_duplicates(duplicates: &HashMap<String, Vec<PathBuf>>, category: &str) -> Vec<PathBuf> {
-    if duplicates.is_empty() {
-        println!("No duplicate {} files found.", category);
-        return Vec::new();
+// Map a --keep value onto the keep strategy
+fn parse_keep_strategy(name: &str) -> KeepStrategy {
+    match name {
+        "oldest" => KeepStrategy::Oldest,
+        "newest" => KeepStrategy::Newest,
+        "shortest" => KeepStrategy::ShortestPath,
+        "longest" => KeepStrategy::LongestPath,
+        _ => KeepStrategy::FirstFound,
     }
+}
 
-    println!("{}", Style::new().red().bold().apply_to(format!("\nDuplicate {} files found:", category)));
-    let mut total = 0usize;
-    let mut files_to_delete = Vec::new();
-    for (hash, files) in duplicates {
-        println!("  Hash: {} ({} files)", &hash, files.len());
-        // Retain only the first file
-        let mut iter = files.iter();
-        if let Some(first) = iter.next() {
-            println!("   Keep: {}", first.display());
-            for dup in iter {
-                println!("   DELETE: {}", dup.display());
-                files_to_delete.push(dup.clone());
-                total += 1;
+// The duplicate search settings given on the command line
+fn requested_dedup_options(cli: &Cli) -> DedupOptions {
+    DedupOptions {
+        algo: parse_hash_algo(&cli.hash),
+        verify: cli.verify,
+        partial_window: cli.partial_window,
+        strategy: if cli.hash_strategy == "xxh3" { HashStrategy::Xxh3 } else { HashStrategy::Partial },
+        max_threads: cli.max_threads.map(NonZeroUsize::get),
+        read_delay: Duration::from_millis(cli.read_delay_ms),
+    }
+}
+
+// Map a --delete-mode value onto the delete mode
+fn parse_delete_mode(name: &str, root: &Path) -> DeleteMode {
+    match name {
+        "recycle" => DeleteMode::RecycleBin,
+        "permanent" => DeleteMode::Permanent,
+        _ => DeleteMode::TrashFolder(root.join(TRASH_FOLDER)),
+    }
+}
+
+// Map a --hash value onto the hash algorithm
+fn parse_hash_algo(name: &str) -> HashAlgo {
+    match name {
+        "blake3" => HashAlgo::Blake3,
+        _ => HashAlgo::Sha256,
+    }
+}
+
+// Ask which hash algorithm is used to find duplicates
+fn ask_hash_algo() -> HashAlgo {
+    parse_hash_algo(&ask_line(tr!("Hash algorithm, sha256 (default) or blake3 (faster): ")).to_lowercase())
+}
+
+// Perceptual hash named on the command line; dhash unless phash is asked for
+fn parse_perceptual_hash(name: &str) -> PerceptualHash {
+    match name {
+        "phash" => PerceptualHash::PHash,
+        _ => PerceptualHash::DHash,
+    }
+}
+
+// Ask which perceptual hash images are compared by
+fn ask_perceptual_hash() -> PerceptualHash {
+    parse_perceptual_hash(&ask_line(tr!("Perceptual hash, dhash (default, faster) or phash (also matches edited brightness): ")).to_lowercase())
+}
+
+// Ask how far apart two image hashes may be; empty or invalid input keeps the default
+fn ask_max_distance() -> u32 {
+    let answer = ask_line(&tr!(
+        "Maximum perceptual hash distance in bits, 0-64 (default {}): ",
+        DEFAULT_MAX_DISTANCE
+    ));
+    answer.parse().unwrap_or(DEFAULT_MAX_DISTANCE)
+}
+
+// Ask which file of each duplicate group should be kept
+fn ask_keep_strategy() -> KeepStrategy {
+    say!("\nWhich file of each duplicate group should be kept?");
+    say!("  1) First found (default)");
+    say!("  2) Oldest (by modification time)");
+    say!("  3) Newest (by modification time)");
+    say!("  4) Shortest path");
+    say!("  5) Longest path");
+    match ask_line(tr!("Choose 1-5: ")).as_str() {
+        "2" => KeepStrategy::Oldest,
+        "3" => KeepStrategy::Newest,
+        "4" => KeepStrategy::ShortestPath,
+        "5" => KeepStrategy::LongestPath,
+        _ => KeepStrategy::FirstFound,
+    }
+}
+
+// Ask how duplicates are looked for, over the command line's `options`; a reference folder
+// inside the `organized` folders is refused and asked for again
+fn ask_dedupe_options(cli: &Cli, options: OrganizeOptions, organized: &[PathBuf]) -> OrganizeOptions {
+    let keep_strategy = ask_keep_strategy();
+    let preferred_dirs = ask_path(tr!("Folder whose copies are always kept (empty for none): ")).into_iter().collect();
+    // Files in a reference folder are only compared against, never removed
+    let reference_dirs = loop {
+        let answer = ask_line(tr!("Reference folder whose files are never removed, only files also in it count as duplicates (empty for none): "));
+        if answer.is_empty() {
+            break Vec::new();
+        }
+        match reference_dir(Path::new(&answer), organized) {
+            Ok(path) => break vec![path],
+            Err(message) => eprintln!("{}", message),
+        }
+    };
+    let algo = ask_hash_algo();
+    let verify = ask_yes_no(tr!("Verify duplicates byte for byte (slower, never trusts the hash alone)? (y/n): "));
+    // By default duplicates are only looked for inside each category folder
+    let global_dedup = ask_yes_no(tr!("Compare files across all categories instead of within each category folder? (y/n): "));
+    // Copies outside the category folders, e.g. left in an uncategorized folder, are only
+    // found when the whole destination is compared
+    let whole_root_dedup = global_dedup
+        && ask_yes_no(tr!("Also compare the files outside the category folders, classified or not? (y/n): "));
+    let duplicate_dirs = ask_yes_no(tr!("Look for whole folders duplicated elsewhere, e.g. in backups? (y/n): "));
+    // Near-duplicates are only listed for review, next to the exact duplicate groups
+    let (perceptual_hash, similar_images) =
+        if ask_yes_no(tr!("Also look for images that look alike, e.g. resized or re-compressed copies (reported only)? (y/n): ")) {
+            (ask_perceptual_hash(), Some(ask_max_distance()))
+        } else {
+            (options.perceptual_hash, None)
+        };
+    let similar_audio = ask_yes_no(tr!("Also look for the same songs in other encodings, e.g. MP3 and FLAC (reported only)? (y/n): "))
+        .then_some(cli.max_bit_error);
+    let similar_videos =
+        ask_yes_no(tr!("Also look for the same videos at other bitrates or in other containers, using ffmpeg (reported only)? (y/n): "))
+            .then_some(cli.max_frame_distance);
+    OrganizeOptions {
+        keep_strategy,
+        preferred_dirs,
+        reference_dirs,
+        dedup_options: DedupOptions { algo, verify, ..options.dedup_options },
+        global_dedup,
+        whole_root_dedup,
+        duplicate_dirs,
+        similar_images,
+        perceptual_hash,
+        similar_audio,
+        similar_videos,
+        ..options
+    }
+}
+
+// The run as the command line's flags describe it, but for the directories: the sources
+// and reference folders are checked and filled in by the caller
+fn requested_options(cli: &Cli, classifier: Classifier, filter: ScanFilter) -> OrganizeOptions {
+    OrganizeOptions {
+        classifier,
+        filter,
+        include_no_ext: cli.include_no_ext,
+        move_files: cli.move_files || cli.copy,
+        layout: requested_layout(cli),
+        on_collision: parse_collision_policy(&cli.on_collision),
+        remove_identical: cli.remove_identical,
+        reorganize: cli.reorganize,
+        copy: cli.copy,
+        move_threads: cli.move_threads.map(NonZeroUsize::get),
+        remove_empty_dirs: cli.remove_empty_dirs,
+        merge: cli.merge,
+        dry_run: cli.dry_run,
+        dedup_options: requested_dedup_options(cli),
+        global_dedup: cli.global_dedup,
+        whole_root_dedup: cli.whole_root,
+        duplicate_dirs: cli.duplicate_dirs,
+        similar_images: cli.similar_images.then_some(cli.max_distance),
+        perceptual_hash: parse_perceptual_hash(&cli.perceptual_hash),
+        similar_audio: cli.similar_audio.then_some(cli.max_bit_error),
+        similar_videos: cli.similar_videos.then_some(cli.max_frame_distance),
+        keep_strategy: parse_keep_strategy(&cli.keep),
+        preferred_dirs: cli.prefer_dir.clone(),
+        hash_cache: cli.hash_cache,
+        ..OrganizeOptions::default()
+    }
+}
+
+// Command line arguments. Without any arguments the organizer runs interactively;
+// with `--dir` every decision is taken from the flags instead of stdin prompts.
+#[derive(Parser, Debug)]
+#[command(name = "organizer", about = "Classify, move and deduplicate files by type.")]
+struct Cli {
+    /// Directory to organize; enables non-interactive mode
+    #[arg(long, value_name = "PATH")]
+    dir: Option<PathBuf>,
+    /// Another directory whose files are organized along with --dir (repeatable); the first one stands for --dir when it is missing
+    #[arg(long = "source", value_name = "PATH")]
+    sources: Vec<PathBuf>,
+    /// Pick the directories to organize from the subfolders of this one, when asked interactively; the first picked is the directory to organize
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["dir", "sources"])]
+    base_path: Option<PathBuf>,
+    /// Create the category folders in this directory instead of the organized one (created if missing)
+    #[arg(long, value_name = "PATH")]
+    dest: Option<PathBuf>,
+    /// Also organize files without an extension, into the `other` folder
+    #[arg(long, global = true)]
+    include_no_ext: bool,
+    /// What happens to files no category claims (unknown or no extension): moved into `other`, listed on screen and in the report, or left alone
+    #[arg(long, global = true, value_name = "MODE", default_value = "ignore", value_parser = ["move", "report", "ignore"])]
+    unmatched: String,
+    /// When category folders exist, only move files not already sorted or present there
+    #[arg(long)]
+    merge: bool,
+    /// Only print what would be moved, deleted or linked; touch nothing
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Write a JSON report of the run (files scanned, categories, moves, duplicate groups, near-duplicates, bytes reclaimed, errors) to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    report: Option<PathBuf>,
+    /// Export the duplicate groups to this CSV file (hash, size, path, mtime, keep/delete decision)
+    #[arg(long, global = true, value_name = "PATH")]
+    duplicates_csv: Option<PathBuf>,
+    /// Write the near-duplicate pairs (--similar-images, --similar-audio, --similar-videos) to this JSON file for review
+    #[arg(long, global = true, value_name = "PATH")]
+    review_report: Option<PathBuf>,
+    /// Move files into their category folders
+    #[arg(long = "move")]
+    move_files: bool,
+    /// Copy files into their category folders instead of moving them; the originals stay where they are (implies --move)
+    #[arg(long, conflicts_with_all = ["remove_identical", "remove_empty_dirs"])]
+    copy: bool,
+    /// How files are arranged in their category folder: flat, their source subfolders, year/month, photo camera, or music artist/album
+    #[arg(long, global = true, value_name = "LAYOUT", default_value = "flat", value_parser = ["flat", "mirror", "date", "camera", "artist"])]
+    layout: String,
+    /// Recreate each file's subfolders below its category folder, i.e. <dest>/<category>/<path in its source>; short for --layout mirror
+    #[arg(long, global = true, visible_alias = "preserve-structure", conflicts_with = "layout")]
+    mirror: bool,
+    /// Destination path template, e.g. "{category}/{year}/{month}/{filename}"; without {category} it starts in the category folder
+    #[arg(long, global = true, value_name = "TEMPLATE", conflicts_with_all = ["layout", "mirror"])]
+    template: Option<PathTemplate>,
+    /// What to do when a target name is taken: skip the file, overwrite (the old file goes to the trash folder), add a numeric suffix, add the start of the content hash, or overwrite only with a newer file
+    #[arg(long, value_name = "POLICY", default_value = "suffix", value_parser = ["skip", "overwrite", "suffix", "hash", "keep-newer"])]
+    on_collision: String,
+    /// When a file's target already holds the same content, send the file to the trash folder instead of leaving it in place
+    #[arg(long)]
+    remove_identical: bool,
+    /// Also move files already inside their category folder when the layout puts them elsewhere (by default they stay where they are)
+    #[arg(long, conflicts_with = "merge")]
+    reorganize: bool,
+    /// Move with at most this many threads (default: one per CPU); 1 moves one file at a time
+    #[arg(long, value_name = "N")]
+    move_threads: Option<NonZeroUsize>,
+    /// After moving, remove source folders that were left empty
+    #[arg(long)]
+    remove_empty_dirs: bool,
+    /// Check the category folders for duplicate files
+    #[arg(long, visible_alias = "dedupe")]
+    dedup: bool,
+    /// Remove the duplicates found, as chosen by --delete-mode (implies --dedup)
+    #[arg(long, global = true)]
+    delete_dupes: bool,
+    /// Send the duplicates found to the system recycle bin; short for --delete-dupes --delete-mode recycle
+    #[arg(long, global = true, conflicts_with_all = ["delete_mode", "hardlink"])]
+    trash: bool,
+    /// Where removed duplicates go: .duplicates_trash/, the recycle bin, or nowhere
+    #[arg(long, global = true, value_name = "MODE", default_value = "trash", value_parser = ["trash", "recycle", "permanent"])]
+    delete_mode: String,
+    /// Which file of each duplicate group is kept
+    #[arg(long, global = true, value_name = "STRATEGY", default_value = "first", value_parser = ["first", "oldest", "newest", "shortest", "longest"])]
+    keep: String,
+    /// Decide on every duplicate group in turn (keep, pick, skip or open) instead of all at once; needs a terminal
+    #[arg(long)]
+    review: bool,
+    /// Always keep the copy inside this folder, whatever --keep says (repeatable, first match wins)
+    #[arg(long, global = true, value_name = "DIR")]
+    prefer_dir: Vec<PathBuf>,
+    /// Folder whose files are never removed; only files whose content is also in it count as
+    /// duplicates, e.g. to clear out of Downloads what the archive holds (repeatable, implies --dedup)
+    #[arg(long, global = true, value_name = "DIR")]
+    reference: Vec<PathBuf>,
+    /// Replace the duplicates found with hard links to the kept file (implies --dedup)
+    #[arg(long, global = true, conflicts_with = "delete_dupes")]
+    hardlink: bool,
+    /// What happens to the duplicates found (implies --dedup); the same as --delete-dupes or --hardlink
+    #[arg(long, global = true, value_name = "ACTION", value_parser = ["delete", "hardlink", "symlink"], conflicts_with_all = ["delete_dupes", "hardlink"])]
+    dedupe_action: Option<String>,
+    /// Whether symbolic links to kept files are relative to the duplicate's folder or absolute
+    #[arg(long, global = true, value_name = "TARGET", default_value = "relative", value_parser = ["relative", "absolute"])]
+    symlink_target: String,
+    /// Hash algorithm used to find duplicates; blake3 is several times faster on large files
+    #[arg(long, global = true, value_name = "ALGO", default_value = "sha256", value_parser = ["sha256", "blake3"])]
+    hash: String,
+    /// Compare duplicates byte for byte instead of trusting the hash alone
+    #[arg(long, global = true)]
+    verify: bool,
+    /// Keep file hashes in .organizer_hash_cache.json so later runs only hash new or changed files
+    #[arg(long, global = true)]
+    hash_cache: bool,
+    /// Bytes hashed from each end of same-size files before hashing them fully (0 = off)
+    #[arg(long, global = true, value_name = "BYTES", default_value_t = DEFAULT_PARTIAL_WINDOW)]
+    partial_window: u64,
+    /// Narrow down same-size files by their ends only, or also by a fast xxh3 of the whole file before the --hash hash
+    #[arg(long, global = true, value_name = "STRATEGY", default_value = "partial", value_parser = ["partial", "xxh3"])]
+    hash_strategy: String,
+    /// Hash with at most this many threads (default: one per CPU); 1 reads one file at a time
+    #[arg(long, global = true, value_name = "N")]
+    max_threads: Option<NonZeroUsize>,
+    /// Pause this many milliseconds before reading each file, to spare slow network storage
+    #[arg(long, global = true, value_name = "MS", default_value_t = 0)]
+    read_delay_ms: u64,
+    /// Look for duplicates across all categories (and unmoved files) in one pass (implies --dedup)
+    #[arg(long, global = true)]
+    global_dedup: bool,
+    /// Look for duplicates among every file under the destination, inside the category folders or
+    /// not and classified or not, as one set (implies --dedup)
+    #[arg(long, global = true)]
+    whole_root: bool,
+    /// Also find whole folders duplicated elsewhere (e.g. in backups) and list each as one entry instead of file by file (implies --dedup)
+    #[arg(long, global = true)]
+    duplicate_dirs: bool,
+    /// Also list images that look alike (resized, re-compressed) as near-duplicates for review; never removed (implies --dedup)
+    #[arg(long, global = true, visible_alias = "perceptual")]
+    similar_images: bool,
+    /// Perceptual hash images are compared by: dhash (fast) or phash (DCT, also matches brightness and contrast changes)
+    #[arg(long, global = true, value_name = "HASH", default_value = "dhash", value_parser = ["dhash", "phash"])]
+    perceptual_hash: String,
+    /// Largest perceptual hash difference, in bits (0-64), for images to count as alike
+    #[arg(long, global = true, value_name = "BITS", default_value_t = DEFAULT_MAX_DISTANCE)]
+    max_distance: u32,
+    /// Also list audio files holding the same recording in different encodings (e.g. MP3 and FLAC); never removed (implies --dedup)
+    #[arg(long, global = true)]
+    similar_audio: bool,
+    /// Largest share of differing fingerprint bits (0-1) for two audio files to count as the same recording
+    #[arg(long, global = true, value_name = "RATIO", default_value_t = DEFAULT_MAX_BIT_ERROR)]
+    max_bit_error: f64,
+    /// Also list videos that look alike (same video at another bitrate or in another container), using ffmpeg; never removed (implies --dedup)
+    #[arg(long, global = true)]
+    similar_videos: bool,
+    /// Largest average difference, in bits (0-64), between the sampled frames of two videos to count as alike
+    #[arg(long, global = true, value_name = "BITS", default_value_t = DEFAULT_MAX_FRAME_DISTANCE)]
+    max_frame_distance: u32,
+    /// Skip entries matching this glob (relative path or name); repeatable; a .organizerignore file in the directory adds more
+    #[arg(long, global = true, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Also scan hidden files and folders: names starting with '.', Thumbs.db, desktop.ini, and the hidden attribute on Windows
+    #[arg(long, global = true)]
+    include_hidden: bool,
+    /// Follow symbolic links that lead outside the directory (links are skipped by default)
+    #[arg(long, global = true)]
+    follow_symlinks: bool,
+    /// Skip symbolic links, the default; the question isn't asked in interactive mode
+    #[arg(long, global = true, conflicts_with = "follow_symlinks")]
+    skip_symlinks: bool,
+    /// Only scan this many folder levels deep (1: only the files directly in the directory)
+    #[arg(long, global = true, value_name = "N")]
+    max_depth: Option<NonZeroUsize>,
+    /// Don't descend into folders on another filesystem (mounted drives, network shares)
+    #[arg(long, global = true)]
+    one_filesystem: bool,
+    /// Skip files smaller than this many bytes
+    #[arg(long, global = true, value_name = "BYTES")]
+    min_size: Option<u64>,
+    /// Skip files larger than this many bytes
+    #[arg(long, global = true, value_name = "BYTES")]
+    max_size: Option<u64>,
+    /// TOML file with custom categories and extensions (default: ./organizer.toml if present)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Only handle these categories, e.g. image,video; the files of the others are left alone
+    #[arg(long, global = true, value_name = "NAMES", value_delimiter = ',', conflicts_with = "skip")]
+    only: Vec<String>,
+    /// Leave the files of these categories alone, e.g. office,archive
+    #[arg(long, global = true, value_name = "NAMES", value_delimiter = ',')]
+    skip: Vec<String>,
+    /// Add a category (or extensions to an existing one) for this run, e.g. ebooks=epub,mobi,azw3 (repeatable)
+    #[arg(long = "category", global = true, value_name = "NAME=EXTS", value_parser = parse_category)]
+    categories: Vec<(String, Vec<String>)>,
+    /// Classify by file content wherever it is recognized, not only when the extension is unknown (slower)
+    #[arg(long, global = true)]
+    sniff_content: bool,
+    /// Start over instead of resuming from the checkpoint an interrupted run left behind
+    #[arg(long)]
+    no_resume: bool,
+    /// Hide progress output (scan counter and progress bars)
+    #[arg(long, short, global = true)]
+    quiet: bool,
+    /// Answer yes to every confirmation
+    #[arg(long, global = true)]
+    yes: bool,
+    /// Language of the messages (default: from the locale in LC_ALL, LC_MESSAGES or LANG)
+    #[arg(long, global = true, value_name = "LANG", value_parser = ["en", "zh"])]
+    lang: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move every file recorded in the journal (organized or trashed) back to where it came from
+    Undo {
+        /// Directory that was organized
+        dir: PathBuf,
+    },
+    /// Find duplicates across any directories without organizing them first, e.g. a laptop folder
+    /// and its copy on a NAS; takes the filter and duplicate options, and removes the duplicates with
+    /// --dedupe-action (the trash folder and journal go in the first directory)
+    Dedupe {
+        /// Directories to compare
+        #[arg(required = true)]
+        dirs: Vec<PathBuf>,
+    },
+    /// Remove zero-byte files and folders left empty (e.g. the source folders after a move); takes the filter options
+    Clean {
+        /// Directory to clean up
+        dir: PathBuf,
+    },
+    /// Scan, classify and list duplicates, sizes and the space they take without writing anything
+    /// to the directory, not even checkpoints or the hash cache; takes the category, filter and
+    /// duplicate search options, and --report / --duplicates-csv to write elsewhere
+    Analyze {
+        /// Directory to analyze; read access is enough
+        dir: PathBuf,
+    },
+    /// Keep moving the files that appear in a directory into its category folders until interrupted;
+    /// takes the category, layout and filter options
+    Watch {
+        /// Directory to watch, e.g. ~/Downloads
+        dir: PathBuf,
+        /// Seconds a file must stay unchanged before it is moved, so files still being written are left alone
+        #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_SETTLE_TIME.as_secs())]
+        settle_secs: u64,
+    },
+}
+
+// Remove the empty files and folders under a directory, after listing them and asking
+// (unless --yes); with --dry-run only list them. Returns the process exit code.
+fn clean(cli: &Cli, dir: &Path) -> i32 {
+    let root = match fs::canonicalize(dir) {
+        Ok(root) if root.is_dir() => root,
+        _ => {
+            say_err!("Invalid directory.");
+            return 1;
+        }
+    };
+    let filter = build_filter(&root, &FilterSettings::from_cli(cli));
+    let (empty, mut errors) = find_empty(&root, &filter);
+    if empty.is_empty() {
+        say!("No empty files or folders found.");
+        print_errors(&errors);
+        return 0;
+    }
+    for path in &empty.files {
+        say!("Empty file: {}", path.display());
+    }
+    for path in &empty.dirs {
+        say!("Empty folder: {}", path.display());
+    }
+    let counts = tr!("{} empty files and {} empty folders", format_count(empty.files.len()), format_count(empty.dirs.len()));
+    if cli.dry_run {
+        say!("\nWould remove {}", counts);
+    } else if cli.yes || ask_yes_no(&tr!("\nRemove {}? (y/n): ", counts)) {
+        let (files, dirs, remove_errors) = remove_empty(&empty);
+        errors.extend(remove_errors);
+        say!("Removed {} empty files and {} empty folders", format_count(files.len()), format_count(dirs.len()));
+    } else {
+        say!("Cleanup cancelled. Nothing was removed.");
+    }
+    print_errors(&errors);
+    i32::from(!errors.is_empty())
+}
+
+// Report what organizing a directory would find, read-only: the categories with their
+// sizes, the duplicate groups and folders, near-duplicates on request and the space the
+// duplicates take. Returns the process exit code.
+fn analyze(cli: &Cli, dir: &Path) -> i32 {
+    let root = match fs::canonicalize(dir) {
+        Ok(root) if root.is_dir() => root,
+        _ => {
+            say_err!("Invalid directory.");
+            return 1;
+        }
+    };
+    let classifier = load_classifier(cli);
+    let classifier = if cli.sniff_content { classifier.with_content_sniffing(true) } else { classifier };
+    let filter_settings = FilterSettings::from_cli(cli);
+    let reference = reference_dirs(&cli.reference, &[]);
+    let options = OrganizeOptions {
+        reference_filters: reference.iter().map(|dir| build_filter(dir, &filter_settings)).collect(),
+        reference_dirs: reference,
+        ..requested_options(cli, classifier.clone(), build_filter(&root, &filter_settings))
+    };
+    let scanning = progress_spinner(tr!("Analyzing"), cli.quiet);
+    let result = organize::analyze(&root, options.clone());
+    scanning.finish_and_clear();
+
+    let mut report = Report { dry_run: true, ..Report::default() };
+    print_scan_links(&result.scan);
+    show_scan(&result.scan, &result.sizes, &classifier, &mut report);
+    show_found_duplicates(&result, &options, &mut report);
+    report.reclaimable_bytes = result.reclaimable_bytes;
+    let errors = result.errors;
+
+    if let Some(path) = &cli.duplicates_csv {
+        match write_duplicates_csv(&result.duplicates, path) {
+            Ok(()) => say!("Duplicate groups exported to {}", path.display()),
+            Err(e) => say_err!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = &cli.review_report {
+        match report.write_near_duplicates(path) {
+            Ok(()) => say!("Near-duplicates written to {}", path.display()),
+            Err(e) => say_err!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = &cli.report {
+        report.add_errors(&errors);
+        match report.write(path) {
+            Ok(()) => say!("Report written to {}", path.display()),
+            Err(e) => say_err!("Failed to write report {}: {}", path.display(), e),
+        }
+    }
+    print_errors(&errors);
+    i32::from(!errors.is_empty())
+}
+
+// Find the duplicates across several directories as one set, list them and remove them
+// as the dedupe flags say, after asking (unless --yes); with --dry-run only list what would
+// happen. The first directory holds the trash folder, journal and hash cache. Returns
+// the process exit code.
+fn dedupe(cli: &Cli, dirs: &[PathBuf]) -> i32 {
+    let mut roots = Vec::new();
+    for dir in dirs {
+        match fs::canonicalize(dir) {
+            Ok(root) if root.is_dir() => roots.push(root),
+            _ => {
+                say_err!("Invalid directory: {}", dir.display());
+                return 1;
+            }
+        }
+    }
+    let root = roots[0].clone();
+    if let Err(e) = interrupt::install() {
+        say_err!("Failed to install the Ctrl+C handler: {}", e);
+    }
+    let classifier = load_classifier(cli);
+    let classifier = if cli.sniff_content { classifier.with_content_sniffing(true) } else { classifier };
+    let filter_settings = FilterSettings::from_cli(cli);
+    let filters: Vec<ScanFilter> = roots.iter().map(|dir| build_filter(dir, &filter_settings)).collect();
+    let mut report = Report { dry_run: cli.dry_run, ..Report::default() };
+    let write_report = |report: &mut Report, errors: &[OperationError]| {
+        if let Some(path) = &cli.report {
+            report.add_errors(errors);
+            match report.write(path) {
+                Ok(()) => say!("Report written to {}", path.display()),
+                Err(e) => say_err!("Failed to write report {}: {}", path.display(), e),
+            }
+        }
+    };
+
+    let mut scope = directories_scope(&roots, &filters, &classifier);
+    report.files_scanned = scope.files.len();
+    let reference = reference_dirs(&cli.reference, &[]);
+    let reference_filters: Vec<ScanFilter> = reference.iter().map(|dir| build_filter(dir, &filter_settings)).collect();
+    add_reference_files(std::slice::from_mut(&mut scope), &reference, &reference_filters, &classifier);
+    let mut errors = Vec::new();
+    let cache = cli.hash_cache.then(|| {
+        let (cache, error) = load_hash_cache(&root);
+        errors.extend(error);
+        cache
+    });
+    let progress = progress_bar(tr!("Hashing"), cli.quiet);
+    let on_hashed = |done: usize, total: usize| {
+        progress.set_length(total as u64);
+        progress.set_position(done as u64);
+    };
+    let (duplicates, find_errors) = find_scope_duplicates(&scope, requested_dedup_options(cli), cache.as_ref(), on_hashed);
+    progress.finish_and_clear();
+    errors.extend(find_errors);
+    if let Some(cache) = cache.filter(|_| !cli.dry_run) {
+        if let Err(e) = cache.save(&root) {
+            errors.push(OperationError::new(Operation::HashCache, &root.join(HASH_CACHE_FILE), e));
+        }
+    }
+    if is_interrupted() {
+        exit_interrupted(0, 0, &mut report, &errors, write_report);
+    }
+    let keep_strategy = parse_keep_strategy(&cli.keep);
+    let groups = if reference.is_empty() {
+        duplicate_groups(&duplicates, &scope.label, keep_strategy, &cli.prefer_dir)
+    } else {
+        reference_groups(&duplicates, &scope.label, keep_strategy, &cli.prefer_dir, &reference)
+    };
+    let (pairs, reclaimable, size_errors) = show_and_list_duplicates(&groups, &scope.label, &mut report);
+    errors.extend(size_errors);
+    report.reclaimable_bytes = reclaimable;
+    if let Some(path) = &cli.duplicates_csv {
+        match write_duplicates_csv(&groups, path) {
+            Ok(()) => say!("Duplicate groups exported to {}", path.display()),
+            Err(e) => say_err!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+
+    if pairs.is_empty() {
+        say!("\nNo duplicate files detected!");
+    } else if let Some(action) = requested_dedup_action(cli, &root) {
+        if cli.dry_run {
+            println!();
+            print_planned_removals(&pairs, &action);
+            report.removed_duplicates = pairs.len();
+            report.reclaimed_bytes = reclaimable;
+        } else if cli.yes || ask_yes_no(&dedup_question(&action, pairs.len(), reclaimable)) {
+            let (removed, reclaimed, remove_errors) = remove_duplicates(&action, &pairs, &root, &[]);
+            errors.extend(remove_errors);
+            report.removed_duplicates = removed.len();
+            report.reclaimed_bytes = reclaimed;
+            if is_interrupted() {
+                exit_interrupted(0, removed.len(), &mut report, &errors, write_report);
             }
+        } else {
+            say!("Deletion cancelled. No files were removed.");
+        }
+    } else {
+        say!("\nDuplicates listed only, pass --dedupe-action (or --delete-dupes, --hardlink) to remove them.");
+    }
+    write_report(&mut report, &errors);
+    print_errors(&errors);
+    i32::from(!errors.is_empty())
+}
+
+// Watch a directory and organize the files arriving in it. Runs until interrupted; returns
+// the process exit code when watching can't start.
+fn watch(cli: &Cli, dir: &Path, settle_secs: u64) -> i32 {
+    let root = match fs::canonicalize(dir) {
+        Ok(root) if root.is_dir() => root,
+        _ => {
+            say_err!("Invalid directory.");
+            return 1;
+        }
+    };
+    let classifier = load_classifier(cli);
+    let classifier = if cli.sniff_content { classifier.with_content_sniffing(true) } else { classifier };
+    let filter = build_filter(&root, &FilterSettings::from_cli(cli));
+    let layout = requested_layout(cli);
+    let settle = Duration::from_secs(settle_secs);
+    let result = watch_and_organize(&root, cli.include_no_ext, &filter, &classifier, &layout, settle, |event| match event {
+        WatchEvent::Watching(root) => say!("Watching {} for new files (Ctrl+C to stop)", root.display()),
+        WatchEvent::Moved(src, dst) => say!("Moved {} -> {}", src.display(), dst.display()),
+        WatchEvent::Failed(error) => eprintln!("{}", error),
+        WatchEvent::WatchFailed(e) => say_err!("Watch error: {}", e),
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            say_err!("Failed to watch {}: {}", root.display(), e);
+            1
         }
     }
-    println!("Total duplicate {} files to delete: {}", category, total);
-    files_to_delete
 }
 
-// Delete files in filesystem, print status
-fn delete_files(paths: &[PathBuf]) {
-    for path in paths {
-        match fs::remove_file(path) {
-            Ok(()) => println!("Deleted {}", path.display()),
-            Err(e) => eprintln!("Failed to delete {}: {}", path.display(), e),
+// The subfolders of `base` in path order, picked from a menu; the first one picked is the
+// directory to organize. No subfolders, or none picked, ends the process.
+fn pick_source_dirs(base: &Path) -> Vec<PathBuf> {
+    let mut subdirs: Vec<PathBuf> = match fs::read_dir(base) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect(),
+        Err(e) => {
+            say_err!("Failed to read {}: {}", base.display(), e);
+            std::process::exit(1);
         }
+    };
+    subdirs.sort();
+    if subdirs.is_empty() {
+        say!("{} has no subfolders to organize.", base.display());
+        std::process::exit(0);
     }
+    let labels: Vec<String> = subdirs.iter().map(|dir| dir.display().to_string()).collect();
+    let picked = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr!("Directories to organize, the first one holds the category folders (space toggles, enter confirms)"))
+        .items(&labels)
+        .interact()
+        .unwrap_or_else(|e| {
+            say_err!("Failed to read the answer: {}", e);
+            std::process::exit(1);
+        });
+    if picked.is_empty() {
+        say!("No directory selected, nothing to organize.");
+        std::process::exit(0);
+    }
+    picked.into_iter().map(|index| subdirs[index].clone()).collect()
 }
 
-// Main process flow: classify, move, deduplicate, and (optionally) delete duplicates
+// A reference folder as an absolute path. A folder that doesn't exist is refused, and so
+// is one inside the `organized` folders, whose files would be moved; the message says why.
+fn reference_dir(dir: &Path, organized: &[PathBuf]) -> Result<PathBuf, String> {
+    let path = match fs::canonicalize(dir) {
+        Ok(path) if path.is_dir() => path,
+        _ => return Err(tr!("Invalid directory: {}", dir.display())),
+    };
+    // The organized folders may be given as typed, relative or through a link
+    let inside = organized
+        .iter()
+        .any(|folder| path.starts_with(fs::canonicalize(folder).unwrap_or_else(|_| folder.clone())));
+    if inside {
+        return Err(tr!("Reference folder {} lies inside a folder being organized, its files would be moved", path.display()));
+    }
+    Ok(path)
+}
+
+// The reference folders given, as absolute paths; one refused by `reference_dir` ends the process
+fn reference_dirs(dirs: &[PathBuf], organized: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter()
+        .map(|dir| {
+            reference_dir(dir, organized).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            })
+        })
+        .collect()
+}
+
+// The source directories to scan, in the order given. A source inside another one is
+// dropped, as its files are scanned anyway; a source that doesn't exist, or that holds
+// the destination `dest` without being it, ends the process.
+fn source_dirs(sources: &[PathBuf], dest: &Path) -> Vec<PathBuf> {
+    let canonical = |dir: &Path| match fs::canonicalize(dir) {
+        Ok(path) if path.is_dir() => path,
+        _ => {
+            say_err!("Invalid directory: {}", dir.display());
+            std::process::exit(1);
+        }
+    };
+    // The destination may not exist yet
+    let canonical_dest = fs::canonicalize(dest).or_else(|_| std::path::absolute(dest)).unwrap_or(dest.to_path_buf());
+    let mut kept: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for source in sources {
+        let canonical_source = canonical(source);
+        if canonical_dest.starts_with(&canonical_source) && canonical_dest != canonical_source {
+            say_err!(
+                "Source {} holds the destination {}; organize it in place or choose a destination outside it.",
+                source.display(),
+                dest.display()
+            );
+            std::process::exit(1);
+        }
+        if let Some((outer, _)) = kept.iter().find(|(_, dir)| canonical_source.starts_with(dir)) {
+            say!("Skipping source {}: it lies inside {}", source.display(), outer.display());
+            continue;
+        }
+        kept.retain(|(kept_source, dir)| {
+            let inside = dir.starts_with(&canonical_source);
+            if inside {
+                say!("Skipping source {}: it lies inside {}", kept_source.display(), source.display());
+            }
+            !inside
+        });
+        kept.push((source.clone(), canonical_source));
+    }
+    kept.into_iter().map(|(source, _)| source).collect()
+}
+
+// The directories to organize and the destination, if another one: --dir (or the first
+// --source) followed by the other sources, or asked on stdin
+fn requested_sources(cli: &Cli) -> (Vec<PathBuf>, Option<PathBuf>) {
+    match (&cli.dir, cli.sources.split_first()) {
+        (Some(dir), _) => ([vec![dir.clone()], cli.sources.clone()].concat(), cli.dest.clone()),
+        (None, Some(_)) => (cli.sources.clone(), cli.dest.clone()),
+        (None, None) => {
+            let sources = match &cli.base_path {
+                Some(base) => pick_source_dirs(base),
+                None => {
+                    let dir = PathBuf::from(ask_line(tr!("Please input the directory to organize: ")));
+                    let others = ask_line(tr!("Other directories whose files go with it, comma-separated (empty for none): "));
+                    let others = others.split(',').map(str::trim).filter(|s| !s.is_empty()).map(PathBuf::from);
+                    std::iter::once(dir).chain(others).collect()
+                }
+            };
+            let dest = ask_path(tr!("Directory to create the category folders in (leave empty to organize in place): "));
+            (sources, dest)
+        }
+    }
+}
+
+// Main process flow: ask for (or read from the flags) what the run does, run it through
+// `organize_with_progress`, print what it did and (optionally) delete the duplicates found
 fn main() {
-    // Read user input for directory path
-    print!("Please input the directory to organize: ");
-    io::stdout().flush().unwrap();
+    let cli = Cli::parse();
+    i18n::set_lang(cli.lang.as_deref().and_then(Lang::parse).unwrap_or_else(Lang::from_env));
+    match &cli.command {
+        Some(Command::Undo { dir }) => std::process::exit(undo(dir)),
+        Some(Command::Clean { dir }) => std::process::exit(clean(&cli, dir)),
+        Some(Command::Analyze { dir }) => std::process::exit(analyze(&cli, dir)),
+        Some(Command::Dedupe { dirs }) => std::process::exit(dedupe(&cli, dirs)),
+        Some(Command::Watch { dir, settle_secs }) => std::process::exit(watch(&cli, dir, *settle_secs)),
+        None => {}
+    }
+    // Watching stops on Ctrl+C as before; an organize run finishes the file at hand first
+    if let Err(e) = interrupt::install() {
+        say_err!("Failed to install the Ctrl+C handler: {}", e);
+    }
+    let interactive = cli.dir.is_none() && cli.sources.is_empty();
 
-    let mut input_path = String::new();
-    io::stdin().read_line(&mut input_path).expect("Failed to read line");
-    let input_path = input_path.trim();
-    let root = Path::new(input_path);
+    let (sources, dest) = requested_sources(&cli);
+    if !sources[0].is_dir() {
+        say_err!("Invalid directory.");
+        std::process::exit(1);
+    }
+    // Category folders, the journal, the trash folder and the checkpoints all go in the destination
+    let root = dest.unwrap_or_else(|| sources[0].clone());
+    let root = root.as_path();
+    let sources = source_dirs(&sources, root);
+    // Reference folders are checked before anything is moved
+    let cli_reference = if cli.move_files {
+        reference_dirs(&cli.reference, &[sources.as_slice(), &[root.to_path_buf()]].concat())
+    } else {
+        reference_dirs(&cli.reference, &[])
+    };
 
-    if !root.is_dir() {
-        eprintln!("Invalid directory.");
-        return;
+    // Dry run previews every move and deletion without touching the filesystem
+    let dry_run = if interactive {
+        ask_yes_no(tr!("Dry run (only preview changes, touch nothing)? (y/n): "))
+    } else {
+        cli.dry_run
+    };
+    if !dry_run && !root.is_dir() {
+        if let Err(e) = fs::create_dir_all(root) {
+            say_err!("Failed to create {}: {}", root.display(), e);
+            std::process::exit(1);
+        }
     }
 
-    // Scan and classify files, report statistics
-    let (stats, file_map) = scan_and_classify_files(root);
-    print_file_stats(&stats);
+    // Optional machine-readable report, written once the run is over
+    let report_path = if interactive { ask_path(tr!("JSON report file (leave empty to skip): ")) } else { cli.report.clone() };
+    let mut report = Report { dry_run, ..Report::default() };
+    let write_report = |report: &mut Report, errors: &[OperationError]| {
+        if let Some(path) = &report_path {
+            report.add_errors(errors);
+            match report.write(path) {
+                Ok(()) => say!("Report written to {}", path.display()),
+                Err(e) => say_err!("Failed to write report {}: {}", path.display(), e),
+            }
+        }
+    };
 
-    // Prompt if files should be moved
-    print!("\nMove files to corresponding folders? (y/n): ");
-    io::stdout().flush().unwrap();
-    let mut answer = String::new();
-    io::stdin().read_line(&mut answer).expect("Failed to read line");
-    if answer.trim().to_lowercase() != "y" {
-        println!("Operation cancelled.");
-        return;
+    let classifier = load_classifier(&cli);
+    let (classifier, include_no_ext) = if interactive {
+        ask_classifier(classifier)
+    } else if cli.sniff_content {
+        (classifier.with_content_sniffing(true), cli.include_no_ext)
+    } else {
+        (classifier, cli.include_no_ext)
+    };
+    // Hidden entries, exclusion globs, sizes and depth limit what the scan and the duplicate check see
+    let filter_settings = if interactive { ask_filter_settings(&cli) } else { FilterSettings::from_cli(&cli) };
+    // The destination's filter applies to its category folders (merging, duplicates),
+    // and every source is scanned with one reading its own .organizerignore file
+    let filter = build_filter(root, &filter_settings);
+    let source_filters: Vec<ScanFilter> = sources.iter().map(|source| build_filter(source, &filter_settings)).collect();
+
+    // Runs checkpoint their scan and hashes, so an interrupted run can be resumed.
+    // Dry runs touch nothing, so they neither use nor write checkpoints.
+    let scan_settings = format!(
+        "{:?}",
+        (
+            &sources[0],
+            include_no_ext,
+            &filter_settings.exclude,
+            filter_settings.include_hidden,
+            (filter_settings.min_size, filter_settings.max_size),
+            filter_settings.follow_symlinks,
+            (filter_settings.max_depth, filter_settings.one_filesystem),
+            &classifier,
+            fs::read_to_string(sources[0].join(IGNORE_FILE)).ok()
+        )
+    );
+    let resume = !dry_run
+        && has_checkpoint(root)
+        && if interactive {
+            ask_yes_no(tr!("An interrupted run left a checkpoint here. Resume where it stopped? (y/n): "))
+        } else {
+            !cli.no_resume
+        };
+
+    // A previously organized root can be merged into, leaving out the files whose content
+    // is already there as well
+    let has_category_folders = has_category_folders(root, &classifier);
+    let merge = has_category_folders
+        && if interactive {
+            ask_yes_no(tr!("Existing category folders found. Merge new files into them (skip sorted and already-present files)? (y/n): "))
+        } else {
+            cli.merge
+        };
+    let mut options = OrganizeOptions {
+        include_no_ext,
+        sources: sources.clone(),
+        source_filters,
+        merge,
+        // A non-interactive --move never previews anything, so files are moved while the
+        // tree is walked instead of being collected first, keeping memory flat on huge trees
+        stream: !interactive && report_path.is_none(),
+        checkpoint: Some(scan_settings),
+        resume,
+        dry_run,
+        reference_dirs: cli_reference,
+        ..requested_options(&cli, classifier.clone(), filter)
+    };
+    if interactive {
+        options.move_files = ask_yes_no(tr!("\nMove files to corresponding folders? (y/n): "));
+        if options.move_files {
+            options = ask_move_options(options, has_category_folders && !merge);
+            // Folders emptied by the move are only cleaned up on request
+            options.remove_empty_dirs = !dry_run
+                && !options.copy
+                && ask_yes_no(tr!("Remove source folders left empty by the move? (y/n): "));
+            options.dedup = ask_yes_no(tr!("\nCheck and remove duplicate files? (y/n): "));
+        }
+        if options.dedup {
+            // Like on the command line, a reference folder inside the organized folders is refused
+            let organized = [sources.as_slice(), &[root.to_path_buf()]].concat();
+            options = ask_dedupe_options(&cli, options, &organized);
+        }
+    } else {
+        options.dedup = cli.dedup
+            || requested_dedup_action(&cli, root).is_some()
+            || cli.global_dedup
+            || cli.whole_root
+            || !cli.reference.is_empty()
+            || cli.duplicate_dirs
+            || cli.similar_images
+            || cli.similar_audio
+            || cli.similar_videos;
     }
+    options.reference_filters = options.reference_dirs.iter().map(|dir| build_filter(dir, &filter_settings)).collect();
+    let (moving, copy) = (options.move_files || options.copy, options.copy);
 
-    move_files(&file_map, root);
-    println!("File organization completed!");
+    // Scan and classify, move, remove the emptied folders and look for duplicates.
+    // Progress is shown on stderr while the steps run, if it is a terminal.
+    let progress = RunProgress::new(copy, cli.quiet);
+    let mut result = organize_with_progress(root, options.clone(), |event| progress.show(event));
+    progress.finish();
+    // Per-file failures of every stage, reported together at the end of the run
+    let mut errors = std::mem::take(&mut result.errors);
 
-    // Prompt if duplicate search and removal is desired
-    print!("\nCheck and remove duplicate files? (y/n): ");
-    io::stdout().flush().unwrap();
-    let mut answer2 = String::new();
-    io::stdin().read_line(&mut answer2).expect("Failed to read line");
-    if answer2.trim().to_lowercase() != "y" {
-        println!("Duplicate removal skipped.");
+    print_scan_links(&result.scan);
+    if options.merge {
+        for (path, same) in &result.already_present {
+            say!("Already present: {} (same as {})", path.display(), same.display());
+        }
+        say!("Skipped {} files already organized or present.", format_count(result.merged));
+    }
+    show_scan(&result.scan, &result.sizes, &classifier, &mut report);
+    // Files left out by merging were scanned too
+    report.files_scanned += result.merged;
+
+    // Nothing is copied onto a destination without room for it all
+    if let Some(shortage) = &result.space_shortage {
+        say_err!(
+            "Not enough space on {}: {} needed, {} available",
+            shortage.dest.display(),
+            format_bytes(shortage.required),
+            format_bytes(shortage.available)
+        );
+        if !dry_run {
+            std::process::exit(1);
+        }
+    }
+    report.add_moves(&result.moves, result.moved_bytes);
+    report.add_collisions(&result.collisions);
+    if dry_run {
+        for (src, dst) in &result.moves {
+            if copy {
+                say!("Would copy {} -> {}", src.display(), dst.display());
+            } else {
+                say!("Would move {} -> {}", src.display(), dst.display());
+            }
+        }
+    }
+    show_collisions(&result.collisions, dry_run, copy);
+    let moved = result.moved;
+    if result.interrupted {
+        exit_interrupted(moved, 0, &mut report, &errors, write_report);
+    }
+    if !moving && interactive {
+        say!("Operation cancelled.");
+        write_report(&mut report, &errors);
+        print_errors(&errors);
         return;
     }
+    // Without --move the non-interactive run goes straight to the duplicate check
+    if !moving {
+        say!("Moving skipped.");
+    } else if dry_run && copy {
+        say!("Would copy {} across {} files", format_bytes(result.moved_bytes), format_count(moved));
+    } else if dry_run {
+        say!("Would move {} across {} files", format_bytes(result.moved_bytes), format_count(moved));
+    } else if copy {
+        say!("Copied {} across {} files", format_bytes(result.moved_bytes), format_count(moved));
+        say!("File organization completed!");
+    } else {
+        say!("Moved {} across {} files", format_bytes(result.moved_bytes), format_count(moved));
+        say!("File organization completed!");
+        if options.remove_empty_dirs {
+            for path in &result.removed_dirs {
+                say!("Removed empty folder {}", path.display());
+            }
+            say!("Removed {} empty folders", format_count(result.removed_dirs.len()));
+        }
+    }
 
-    // For every file category, collect the files under its folder and compute duplicates
-    let type_folder_map = [
-        (FileType::Image, "image", "Image"),
-        (FileType::Audio, "audio", "Audio"),
-        (FileType::Video, "video", "Video"),
-        (FileType::Office, "office", "Office"),
-    ];
+    if !options.dedup {
+        say!("Duplicate removal skipped.");
+        if dry_run && copy {
+            say!("\nWould copy {} files, would delete 0 files", moved);
+        } else if dry_run {
+            say!("\nWould move {} files, would delete 0 files", moved);
+        }
+        write_report(&mut report, &errors);
+        print_errors(&errors);
+        return;
+    }
 
-    let mut all_files_to_delete = Vec::new();
-    for (file_type, folder_name, display_name) in &type_folder_map {
-        let folder = root.join(folder_name);
-        if !folder.is_dir() {
-            continue;
+    let (mut all_files_to_delete, mut reclaimable) = show_found_duplicates(&result, &options, &mut report);
+    let mut all_groups = result.duplicates;
+    let trees = result.duplicate_trees;
+    let review_path = if interactive && report.has_near_duplicates() {
+        ask_path(tr!("JSON file to write the near-duplicates to for review (leave empty to skip): "))
+    } else {
+        cli.review_report.clone()
+    };
+    if let Some(path) = &review_path {
+        match report.write_near_duplicates(path) {
+            Ok(()) => say!("Near-duplicates written to {}", path.display()),
+            Err(e) => say_err!("Failed to write {}: {}", path.display(), e),
         }
-        // Recursively gather all files in category folder
-        let files: Vec<_> = WalkDir::new(&folder)
-            .min_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .map(|e| e.into_path())
+    }
+
+    // Instead of all or nothing, every group can be decided on its own
+    let review = !all_groups.is_empty()
+        && if interactive {
+            ask_yes_no(tr!("Review each duplicate group before removing anything? (y/n): "))
+        } else {
+            cli.review
+        };
+    if review {
+        let reviewed = match review_duplicate_groups(&all_groups) {
+            Ok(reviewed) => reviewed,
+            Err(e) => {
+                say_err!("Review failed, no duplicates will be removed: {}", e);
+                Vec::new()
+            }
+        };
+        // Duplicate folders aren't reviewed, they stay as listed
+        all_files_to_delete = reviewed
+            .iter()
+            .flat_map(|group| group.duplicates.iter().map(|dup| (dup.clone(), group.kept.clone())))
+            .chain(trees.iter().flat_map(|tree| tree.pairs.iter().cloned()))
             .collect();
+        let (bytes, size_errors) = reclaimable_bytes(&reviewed);
+        errors.extend(size_errors);
+        reclaimable = bytes + tree_reclaimable_bytes(&trees);
+        if !all_files_to_delete.is_empty() {
+            say!(
+                "\nAfter review: {} files to remove, reclaiming {}",
+                format_count(all_files_to_delete.len()),
+                format_bytes(reclaimable)
+            );
+        }
+        all_groups = reviewed;
+    }
 
-        // Compute duplicates by content
-        let duplicates = find_duplicates(&files);
-        // List and collect files to delete
-        let files_to_delete = show_and_list_duplicates(&duplicates, display_name);
-        all_files_to_delete.extend(files_to_delete);
+    // The groups, with what would be kept and deleted, can be checked in a spreadsheet
+    // before confirming anything
+    let csv_path = if interactive && !all_groups.is_empty() {
+        ask_path(tr!("CSV file to export the duplicate groups to (leave empty to skip): "))
+    } else {
+        cli.duplicates_csv.clone()
+    };
+    if let Some(path) = &csv_path {
+        match write_duplicates_csv(&all_groups, path) {
+            Ok(()) => say!("Duplicate groups exported to {}", path.display()),
+            Err(e) => say_err!("Failed to write {}: {}", path.display(), e),
+        }
     }
 
+    report.reclaimable_bytes = reclaimable;
+
+    let mut deleted = 0usize;
+    let mut linked = 0usize;
     if all_files_to_delete.is_empty() {
-        println!("\nNo duplicate files detected!");
-    } else {
-        // Confirm deletion with user
-        print!("\nDo you want to delete all duplicate files listed above? (y/n): ");
-        io::stdout().flush().unwrap();
-        let mut answer3 = String::new();
-        io::stdin().read_line(&mut answer3).expect("Failed to read line");
-        if answer3.trim().to_lowercase() == "y" {
-            delete_files(&all_files_to_delete);
-            println!("Duplicate files deleted!");
+        say!("\nNo duplicate files detected!");
+    } else if let Some(action) =
+        if interactive { Some(ask_dedup_action(root)) } else { requested_dedup_action(&cli, root) }
+    {
+        let paths: Vec<PathBuf> = all_files_to_delete.iter().map(|(dup, _)| dup.clone()).collect();
+        if dry_run {
+            println!();
+            print_planned_removals(&all_files_to_delete, &action);
+            match &action {
+                DedupAction::Delete(_) => deleted = paths.len(),
+                DedupAction::Hardlink | DedupAction::Symlink(_) => linked = paths.len(),
+            }
+            report.removed_duplicates = paths.len();
+            report.reclaimed_bytes = reclaimable;
+        } else if cli.yes || ask_yes_no(&dedup_question(&action, paths.len(), reclaimable)) {
+            let (removed, reclaimed, remove_errors) = remove_duplicates(&action, &all_files_to_delete, root, &trees);
+            errors.extend(remove_errors);
+            report.removed_duplicates = removed.len();
+            report.reclaimed_bytes = reclaimed;
         } else {
-            println!("Deletion cancelled. No files were removed.");
+            say!("Deletion cancelled. No files were removed.");
         }
+    } else {
+        say!("\nDuplicates listed only, pass --dedupe-action (or --delete-dupes, --hardlink) to remove them.");
+    }
+    if is_interrupted() {
+        exit_interrupted(moved, report.removed_duplicates, &mut report, &errors, write_report);
+    }
+
+    if dry_run && copy {
+        say!("\nWould copy {} files, would delete {} files, would link {} files", moved, deleted, linked);
+    } else if dry_run {
+        say!("\nWould move {} files, would delete {} files, would link {} files", moved, deleted, linked);
     }
+    write_report(&mut report, &errors);
+    print_errors(&errors);
 }
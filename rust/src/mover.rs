@@ -0,0 +1,1143 @@
+/*
+File: mover.rs
+
+Moving classified files into their category folders under the organized root (the
+scanned folder itself, or a separate destination), and the file helpers behind it:
+unique target names, moves across filesystems that check the copy and keep
+modification time and permissions, cleanup of the folders left empty, and the
+merge-mode filter that leaves already organized files alone.
+When a target name is taken, the mover's `CollisionPolicy` decides: skip the file,
+overwrite the existing one (which goes to the trash folder), or pick another name with
+a numeric suffix or a content hash; keep-newer overwrites only with a newer file.
+A file whose target already holds the same content is never moved next to it under
+another name: it stays where it is, or goes to the trash folder with `with_remove_identical`.
+Every clash is recorded as a `Collision` naming the policy applied.
+Inside a category folder files are laid out flat, mirroring their source subfolders,
+by month, by photo camera or music artist and album, or as a destination path
+template says (`Layout`); a category's own template takes precedence.
+Files already anywhere inside their category folder are left alone, so running again
+(even with another layout) moves nothing; `with_reorganize` re-sorts them instead.
+Every move is journaled (see `journal`) so it can be undone. With `with_copy` files are
+copied instead, leaving the originals in place; copies aren't journaled.
+A symlinked file is moved as a link, never its target, and files found below a
+followed folder link are left where they are (they can still be copied).
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::cell::OnceCell;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use chrono::{DateTime, Datelike, Local};
+use filetime::FileTime;
+use rayon::prelude::*;
+use crate::classify::{normalized_extension, split_extension_os, Classifier, FileType};
+use crate::dedupe::{calc_hash, collect_files, create_file_symlink, group_by_size, same_content, HashAlgo};
+use crate::error::{Operation, OperationError};
+use crate::exif::{read_photo_info, PhotoInfo};
+use crate::filter::{is_reached_through_link, ScanFilter};
+use crate::interrupt::is_interrupted;
+use crate::journal::{Journal, JOURNAL_FILE};
+use crate::tags::{read_audio_tags, AudioTags};
+use crate::template::{folder_component, Field, PathTemplate};
+use crate::scan::{empty_stats, scan_streaming, ScanEvent, SizeStats, TRASH_FOLDER};
+
+// Returns a file name (with numeric suffix if needed) that does not exist in dest_folder
+// and is not already claimed in `reserved` (targets planned earlier in a dry run)
+// The suffix goes before the whole extension, so "a.tar.gz" becomes "a_1.tar.gz".
+// Names are handled as `OsStr`, so names that are not valid UTF-8 keep their bytes.
+pub fn get_non_duplicate_name(dest_folder: &Path, file_name: &OsStr, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let (stem, ext) = split_extension_os(file_name);
+    let mut counter = 1;
+    let mut candidate = dest_folder.join(file_name);
+    while candidate.exists() || reserved.contains(&candidate) {
+        let mut new_name = stem.clone();
+        new_name.push(format!("_{}", counter));
+        if !ext.is_empty() {
+            new_name.push(".");
+            new_name.push(&ext);
+        }
+        candidate = dest_folder.join(&new_name);
+        counter += 1;
+    }
+    candidate
+}
+
+// Hex digits of the content hash put in a name by `CollisionPolicy::Hash`
+const NAME_HASH_LEN: usize = 8;
+
+// Returns "stem_<start of the content hash>.ext" in dest_folder for `file_path`, None
+// when that name is taken too (by all odds, by a file with the same content)
+fn hashed_name(dest_folder: &Path, file_name: &OsStr, file_path: &Path, reserved: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let hash = calc_hash(file_path, HashAlgo::Blake3).ok()?;
+    let (mut new_name, ext) = split_extension_os(file_name);
+    new_name.push(format!("_{}", &hash[..NAME_HASH_LEN]));
+    if !ext.is_empty() {
+        new_name.push(".");
+        new_name.push(&ext);
+    }
+    let candidate = dest_folder.join(new_name);
+    (!candidate.exists() && !reserved.contains(&candidate)).then_some(candidate)
+}
+
+// Whether `file` was modified after `other`; false when either time can't be read
+fn is_newer(file: &Path, other: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    matches!((modified(file), modified(other)), (Some(a), Some(b)) if a > b)
+}
+
+// What to do when the target name of a move is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    // Leave the file where it is
+    Skip,
+    // Replace the existing file, which is moved to the trash folder (and back by undo)
+    Overwrite,
+    // Append a numeric suffix, "a_1.jpg" (see `get_non_duplicate_name`)
+    #[default]
+    Suffix,
+    // Append the start of the file's content hash, "a_3f2a9c1e.jpg"
+    Hash,
+    // Overwrite when the file is newer than the existing one, skip it otherwise
+    KeepNewer,
+}
+
+impl CollisionPolicy {
+    // Name of the policy, as given on the command line
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CollisionPolicy::Skip => "skip",
+            CollisionPolicy::Overwrite => "overwrite",
+            CollisionPolicy::Suffix => "suffix",
+            CollisionPolicy::Hash => "hash",
+            CollisionPolicy::KeepNewer => "keep-newer",
+        }
+    }
+}
+
+// A file whose target name was taken, and what was done about it
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub source: PathBuf,
+    // The file (or planned target) already holding the name
+    pub existing: PathBuf,
+    // Policy applied: keep-newer comes out as overwrite or skip, and a name clashing
+    // with another file of the same run (or a taken hashed name) gets a suffix
+    pub applied: CollisionPolicy,
+    // Where the file went, None when it was skipped
+    pub target: Option<PathBuf>,
+    // The existing file has the same content, so the file was skipped whatever the
+    // policy; `target` is its place in the trash folder when it was removed
+    pub identical: bool,
+}
+
+// Move a file. If rename fails due to cross-device, fall back to copy and delete,
+// as `SafeMove::default()` does: the copy must have the source's size before the
+// source is removed.
+pub fn move_file_support_cross_partition(src: &Path, dst: &Path) -> io::Result<Option<io::Error>> {
+    SafeMove::default().move_file(src, dst)
+}
+
+// How `SafeMove` checks a copy before removing its source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyCheck {
+    // The copy has the source's size
+    #[default]
+    Size,
+    // The copy has the source's content (both are hashed, so the copy is read back)
+    Content,
+}
+
+// Suffix of the temporary file a copy is written to before it is renamed into place
+pub const PART_SUFFIX: &str = ".part";
+
+// Temporary name next to dst for a copy in progress: hidden, so scans skip it
+fn part_path(dst: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(dst.file_name().unwrap_or_default());
+    name.push(PART_SUFFIX);
+    dst.with_file_name(name)
+}
+
+// Copy src to dst through a temporary `.part` file in dst's folder that is renamed into
+// place once complete and accepted by `check`, so an interrupted copy never leaves a
+// truncated file under the final name. The temporary file is removed on failure.
+// Returns the bytes copied.
+fn copy_via_part(src: &Path, dst: &Path, check: impl FnOnce(&Path) -> io::Result<()>) -> io::Result<u64> {
+    let part = part_path(dst);
+    let result = fs::copy(src, &part).and_then(|bytes| {
+        check(&part)?;
+        fs::rename(&part, dst)?;
+        Ok(bytes)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+// Copy src to dst without ever leaving a partial dst behind (see `copy_via_part`)
+pub fn copy_atomically(src: &Path, dst: &Path) -> io::Result<u64> {
+    copy_via_part(src, dst, |_| Ok(()))
+}
+
+// A move that survives crossing filesystems: a plain rename where possible, otherwise an
+// atomic copy (see `copy_atomically`) that is checked as `check` says before the source
+// is removed. A copy that fails the check is removed again and the source kept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SafeMove {
+    pub check: CopyCheck,
+}
+
+impl SafeMove {
+    pub fn new(check: CopyCheck) -> SafeMove {
+        SafeMove { check }
+    }
+
+    // Move src to dst. On the copy fallback the source modification time and permissions
+    // are re-applied to the destination; failing to do so does not fail the move, the
+    // error is returned as `Ok(Some(error))` instead. A symbolic link is moved as a link
+    // (see `move_link`), whatever it points to.
+    pub fn move_file(&self, src: &Path, dst: &Path) -> io::Result<Option<io::Error>> {
+        if fs::symlink_metadata(src)?.file_type().is_symlink() {
+            return move_link(src, dst).map(|()| None);
+        }
+        match fs::rename(src, dst) {
+            Ok(()) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => self.copy_and_remove(src, dst),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Copy src to dst atomically, checked like the copy of a move and with the source
+    // modification time and permissions; failing to re-apply those is returned as
+    // `Ok(Some(error))`. The source is left alone.
+    pub fn copy_file(&self, src: &Path, dst: &Path) -> io::Result<Option<io::Error>> {
+        let metadata = fs::metadata(src)?;
+        let mut restored = Ok(());
+        copy_via_part(src, dst, |part| {
+            self.verify(src, &metadata, part)?;
+            restored = restore_metadata(&metadata, part);
+            Ok(())
+        })?;
+        Ok(restored.err())
+    }
+
+    fn copy_and_remove(&self, src: &Path, dst: &Path) -> io::Result<Option<io::Error>> {
+        let restore_error = self.copy_file(src, dst)?;
+        fs::remove_file(src)?;
+        Ok(restore_error)
+    }
+
+    // Check the copy of src (whose metadata was read before copying)
+    fn verify(&self, src: &Path, metadata: &fs::Metadata, copy: &Path) -> io::Result<()> {
+        let same = match self.check {
+            CopyCheck::Size => fs::metadata(copy)?.len() == metadata.len(),
+            CopyCheck::Content => same_content(src, copy)?,
+        };
+        if same {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "the copy differs from the source, which was kept"))
+        }
+    }
+}
+
+// Move a symbolic link itself, never its target: the link is made again at dst and the
+// old one removed, which works across filesystems too. A relative link is made to point
+// at the full path of its target, so moving it into another folder doesn't break it.
+fn move_link(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+    let target = match src.parent() {
+        Some(folder) if target.is_relative() => fs::canonicalize(src).or_else(|_| std::path::absolute(folder.join(target)))?,
+        _ => target,
+    };
+    create_file_symlink(&target, dst)?;
+    if let Err(e) = fs::remove_file(src) {
+        let _ = fs::remove_file(dst);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Re-apply the source's modification time and permissions to a copied file
+fn restore_metadata(metadata: &fs::Metadata, dst: &Path) -> io::Result<()> {
+    let mtime = FileTime::from_last_modification_time(metadata);
+    filetime::set_file_mtime(dst, mtime)?;
+    fs::set_permissions(dst, metadata.permissions())
+}
+
+// How files are arranged inside their category folder
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Layout {
+    // Every file directly in the category folder
+    #[default]
+    Flat,
+    // Each file keeps its subfolders relative to the root (or the source folder it was
+    // found in) below the category folder (`2020/summer/pic.jpg` -> `image/2020/summer/pic.jpg`)
+    Mirror,
+    // Year and month a photo was taken (from its Exif data), or else of the file's
+    // modification time in local time (`image/2023/07/pic.jpg`)
+    ByDate,
+    // Camera model of photos, from their Exif data (`image/Canon EOS 5D/pic.jpg`); other
+    // files, and photos that don't name a camera, stay flat
+    ByCamera,
+    // Artist and album of audio files, from their tags (`audio/Artist/Album/song.mp3`);
+    // other files, and audio files without an artist tag, stay flat
+    ByArtist,
+    // Destination path template for every category (`{category}/{year}/{month}/{filename}`)
+    Template(PathTemplate),
+}
+
+// Local date a file was last modified
+fn modification_date(path: &Path) -> Option<(i32, u32, u32)> {
+    let modified: DateTime<Local> = fs::metadata(path).and_then(|m| m.modified()).ok()?.into();
+    Some((modified.year(), modified.month(), modified.day()))
+}
+
+// Date a photo was taken, or else the file was last modified
+fn file_date(file_type: &FileType, file_path: &Path) -> Option<(i32, u32, u32)> {
+    (*file_type == FileType::Image)
+        .then(|| read_photo_info(file_path).ok()?.taken)
+        .flatten()
+        .or_else(|| modification_date(file_path))
+}
+
+// How `move_files` and `scan_and_move_files` move the files
+#[derive(Debug, Clone, Default)]
+pub struct MoveOptions {
+    // How files are arranged inside their category folder
+    pub layout: Layout,
+    // Plan the moves without touching anything
+    pub dry_run: bool,
+    // Number of threads moving files; None uses one per CPU, 1 moves one file at a time
+    pub max_threads: Option<usize>,
+    // What to do when a target name is taken
+    pub on_collision: CollisionPolicy,
+    // Send files whose content is already at their target to the trash folder instead
+    // of leaving them in place
+    pub remove_identical: bool,
+    // Also move files already inside their category folder into the layout
+    pub reorganize: bool,
+    // Copy the files into their category folders, leaving the originals in place
+    pub copy: bool,
+}
+
+// What `move_files` did
+#[derive(Debug, Default)]
+pub struct MovedFiles {
+    // `(source, target)` of every file moved, or planned in a dry run
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    // Their total size in bytes
+    pub moved_bytes: u64,
+    // Files whose target name was taken
+    pub collisions: Vec<Collision>,
+}
+
+// Where `FileMover::plan` sends one file
+struct Plan {
+    target: PathBuf,
+    size: u64,
+    // Trash path of the existing file an overwrite replaces
+    replaced: Option<PathBuf>,
+    // The file holding the name first wanted, and the policy applied
+    collision: Option<(PathBuf, CollisionPolicy)>,
+    // The file only goes to the trash folder, its content being at the name wanted already
+    identical: bool,
+}
+
+// Moves files into their category folders (as named by `classifier`) under root_dir,
+// arranged inside each folder as `layout` says. `move_file` moves one file; `move_files`
+// and `scan_and_move_files` plan a batch of files one at a time and then move the batch
+// in parallel on rayon threads. Files already in their category folder are left alone,
+// and taken names are handled as the collision policy says. Every successful move is
+// appended to the journal in root_dir so it can be undone. In dry-run mode the moves are
+// only planned and nothing is touched.
+pub struct FileMover<'a> {
+    root_dir: &'a Path,
+    // Other folders the files come from, see `with_source_dirs`
+    source_dirs: Vec<PathBuf>,
+    classifier: &'a Classifier,
+    layout: &'a Layout,
+    dry_run: bool,
+    safe_move: SafeMove,
+    on_collision: CollisionPolicy,
+    // Send files whose content is already at their target to the trash folder
+    remove_identical: bool,
+    // Lay out again the files already inside their category folder
+    reorganize: bool,
+    // Copy the files, leaving the originals where they are
+    copy: bool,
+    // Shared by the threads of `move_files`
+    journal: Mutex<Option<Journal>>,
+    // Targets planned but not moved yet (every one, in a dry run), so names stay unique
+    planned: HashSet<PathBuf>,
+    // Folders that couldn't be created, reported once
+    failed_folders: HashSet<PathBuf>,
+    collisions: Vec<Collision>,
+    errors: Vec<OperationError>,
+}
+
+impl<'a> FileMover<'a> {
+    pub fn new(root_dir: &'a Path, classifier: &'a Classifier, layout: &'a Layout, dry_run: bool) -> FileMover<'a> {
+        let mut errors = Vec::new();
+        let journal = if dry_run {
+            None
+        } else {
+            match Journal::open(root_dir) {
+                Ok(journal) => Some(journal),
+                // Moves still happen, they just can't be undone
+                Err(e) => {
+                    errors.push(OperationError::new(Operation::Journal, &root_dir.join(JOURNAL_FILE), e));
+                    None
+                }
+            }
+        };
+        FileMover {
+            root_dir,
+            source_dirs: Vec::new(),
+            classifier,
+            layout,
+            dry_run,
+            safe_move: SafeMove::default(),
+            on_collision: CollisionPolicy::default(),
+            remove_identical: false,
+            reorganize: false,
+            copy: false,
+            journal: Mutex::new(journal),
+            planned: HashSet::new(),
+            failed_folders: HashSet::new(),
+            collisions: Vec::new(),
+            errors,
+        }
+    }
+
+    // Files may also come from these folders rather than from root_dir: the mirror
+    // layout recreates a file's subfolders relative to the one it lies in
+    pub fn with_source_dirs(mut self, source_dirs: &[PathBuf]) -> FileMover<'a> {
+        self.source_dirs = source_dirs.to_vec();
+        self
+    }
+
+    // Check copies across filesystems as `safe_move` does instead of by size only
+    pub fn with_safe_move(mut self, safe_move: SafeMove) -> FileMover<'a> {
+        self.safe_move = safe_move;
+        self
+    }
+
+    // Handle taken target names as `policy` says instead of adding a numeric suffix
+    pub fn with_collision_policy(mut self, policy: CollisionPolicy) -> FileMover<'a> {
+        self.on_collision = policy;
+        self
+    }
+
+    // Send a file whose target already holds the same content to the trash folder
+    // (journaled, so undo brings it back) instead of leaving it where it is
+    pub fn with_remove_identical(mut self, remove_identical: bool) -> FileMover<'a> {
+        self.remove_identical = remove_identical;
+        self
+    }
+
+    // Also move the files already inside their category folder when the layout puts
+    // them elsewhere, e.g. into date folders after an earlier flat run
+    pub fn with_reorganize(mut self, reorganize: bool) -> FileMover<'a> {
+        self.reorganize = reorganize;
+        self
+    }
+
+    // Copy the files into their category folders instead of moving them. A file whose
+    // target holds the same content is then always left alone, never sent to the trash.
+    pub fn with_copy(mut self, copy: bool) -> FileMover<'a> {
+        self.copy = copy;
+        self
+    }
+
+    // The innermost source folder (or root_dir) holding a folder
+    fn base_dir(&self, source_dir: &Path) -> &Path {
+        self.source_dirs
+            .iter()
+            .map(PathBuf::as_path)
+            .chain([self.root_dir])
+            .filter(|dir| source_dir.starts_with(dir))
+            .max_by_key(|dir| dir.components().count())
+            .unwrap_or(self.root_dir)
+    }
+
+    // Folder and name a file of the given category is moved to
+    fn destination(&self, file_type: &FileType, file_path: &Path, file_name: &OsStr) -> (PathBuf, OsString) {
+        let template = match self.layout {
+            Layout::Template(template) => Some(template),
+            _ => None,
+        };
+        match self.classifier.template(file_type).or(template) {
+            Some(template) => self.render_template(template, file_type, file_path, file_name),
+            None => (self.dest_dir(file_type, file_path), file_name.to_os_string()),
+        }
+    }
+
+    // Folder a file of the given category belongs in, for the layouts that keep file names
+    fn dest_dir(&self, file_type: &FileType, file_path: &Path) -> PathBuf {
+        let dest_folder = self.root_dir.join(self.classifier.folder_name(file_type));
+        match self.layout {
+            Layout::Flat | Layout::Template(_) => dest_folder,
+            Layout::Mirror => {
+                // A file already inside the category folder keeps its place there
+                let source_dir = file_path.parent().unwrap_or(self.root_dir);
+                let base = if source_dir.starts_with(&dest_folder) { &dest_folder } else { self.base_dir(source_dir) };
+                dest_folder.join(source_dir.strip_prefix(base).unwrap_or(Path::new("")))
+            }
+            // Files whose date can't be read stay flat
+            Layout::ByDate => match file_date(file_type, file_path) {
+                Some((year, month, _)) => dest_folder.join(format!("{:04}", year)).join(format!("{:02}", month)),
+                None => dest_folder,
+            },
+            Layout::ByCamera => {
+                let camera = (*file_type == FileType::Image)
+                    .then(|| read_photo_info(file_path).ok()?.camera)
+                    .flatten()
+                    .map(|camera| folder_component(&camera))
+                    .filter(|camera| !camera.is_empty());
+                match camera {
+                    Some(camera) => dest_folder.join(camera),
+                    None => dest_folder,
+                }
+            }
+            Layout::ByArtist => {
+                let Some(tags) = (*file_type == FileType::Audio).then(|| read_audio_tags(file_path).ok()).flatten() else {
+                    return dest_folder;
+                };
+                let Some(artist) = tags.filing_artist().map(folder_component).filter(|artist| !artist.is_empty()) else {
+                    return dest_folder;
+                };
+                let artist_folder = dest_folder.join(artist);
+                match tags.album.as_deref().map(folder_component).filter(|album| !album.is_empty()) {
+                    Some(album) => artist_folder.join(album),
+                    None => artist_folder,
+                }
+            }
+        }
+    }
+
+    // Render a destination path template for one file; the metadata behind the
+    // placeholders is read at most once, and only when the template uses it
+    fn render_template(&self, template: &PathTemplate, file_type: &FileType, file_path: &Path, file_name: &OsStr) -> (PathBuf, OsString) {
+        let photo: OnceCell<Option<PhotoInfo>> = OnceCell::new();
+        let photo = || photo.get_or_init(|| (*file_type == FileType::Image).then(|| read_photo_info(file_path).ok()).flatten());
+        let tags: OnceCell<Option<AudioTags>> = OnceCell::new();
+        let tags = || tags.get_or_init(|| (*file_type == FileType::Audio).then(|| read_audio_tags(file_path).ok()).flatten());
+        let date = OnceCell::new();
+        let date = || *date.get_or_init(|| file_date(file_type, file_path));
+        let name = file_name.to_string_lossy();
+        let (folders, target_name) = template.render(file_name, |field| match field {
+            Field::Category => Some(self.classifier.folder_name(file_type).to_string()),
+            Field::Year => date().map(|(year, _, _)| format!("{:04}", year)),
+            Field::Month => date().map(|(_, month, _)| format!("{:02}", month)),
+            Field::Day => date().map(|(_, _, day)| format!("{:02}", day)),
+            Field::Ext => Some(normalized_extension(&name)).filter(|ext| !ext.is_empty()),
+            Field::Filename => Some(name.to_string()),
+            Field::Stem => Some(split_extension_os(file_name).0.to_string_lossy().into_owned()),
+            Field::Camera => photo().as_ref()?.camera.clone(),
+            Field::Artist => tags().as_ref()?.filing_artist().map(str::to_string),
+            Field::Album => tags().as_ref()?.album.clone(),
+        });
+        let base = if template.is_relative_to_root() {
+            self.root_dir.to_path_buf()
+        } else {
+            self.root_dir.join(self.classifier.folder_name(file_type))
+        };
+        (base.join(folders), target_name)
+    }
+
+    // Whether a file lies inside its category folder, where it stays unless reorganizing
+    fn is_sorted(&self, file_type: &FileType, file_path: &Path) -> bool {
+        !self.reorganize && file_path.starts_with(self.root_dir.join(self.classifier.folder_name(file_type)))
+    }
+
+    // Whether a file of the given category already sits where it would be moved to: inside
+    // its category folder, or else in its destination folder. Only the folder counts, so a
+    // file renamed by a template and given a numeric suffix on a clash isn't moved again
+    // on the next run.
+    pub fn is_in_place(&self, file_type: &FileType, file_path: &Path) -> bool {
+        let file_name = file_path.file_name().unwrap_or_default();
+        self.is_sorted(file_type, file_path)
+            || file_path.parent().unwrap_or(self.root_dir) == self.destination(file_type, file_path, file_name).0
+    }
+
+    // Move one file of the given category. Returns the target and the file's size when the
+    // file was moved (or would be), None when it is already in place, skipped on a name
+    // clash or failed to move.
+    pub fn move_file(&mut self, file_type: &FileType, file_path: &Path) -> Option<(PathBuf, u64)> {
+        let plan = self.plan(file_type, file_path)?;
+        if self.dry_run {
+            return self.planned_move(file_path, plan);
+        }
+        // Once moved the targets exist, so they need no reservation
+        self.planned.remove(&plan.target);
+        if let Some(trashed) = &plan.replaced {
+            self.planned.remove(trashed);
+        }
+        let (moved, errors) = self.execute(file_path, &plan);
+        self.errors.extend(errors);
+        if !moved {
+            return None;
+        }
+        self.planned_move(file_path, plan)
+    }
+
+    // Pick the target of one file and create its folder. Returns None when the file is
+    // already in place, skipped on a name clash or its folder can't be created. The
+    // target is reserved in `planned`, so files planned together get distinct names.
+    fn plan(&mut self, file_type: &FileType, file_path: &Path) -> Option<Plan> {
+        let Some(file_name) = file_path.file_name() else {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "path has no file name");
+            self.errors.push(OperationError::new(Operation::Move, file_path, error));
+            return None;
+        };
+        if self.is_sorted(file_type, file_path) {
+            return None;
+        }
+        // Moving a file found through a link to a folder would take it out of the link's target
+        if !self.copy && is_reached_through_link(file_path, self.base_dir(file_path.parent().unwrap_or(self.root_dir))) {
+            return None;
+        }
+        let (dest_dir, target_name) = self.destination(file_type, file_path, file_name);
+        if file_path.parent().unwrap_or(self.root_dir) == dest_dir || self.failed_folders.contains(&dest_dir) {
+            return None;
+        }
+        let wanted = dest_dir.join(&target_name);
+        let taken = self.planned.contains(&wanted) || wanted.exists();
+        // Moving a second copy of a file next to it would only add "photo_1.jpg"
+        if taken && !self.planned.contains(&wanted) && wanted.is_file() && same_content(file_path, &wanted).unwrap_or(false) {
+            return self.plan_identical(file_path, file_name, wanted);
+        }
+        let applied = match self.on_collision {
+            _ if !taken => None,
+            // Only a file on disk is replaced; a target planned for another file of
+            // this run (or a folder) makes way for a suffixed name instead
+            CollisionPolicy::Overwrite | CollisionPolicy::KeepNewer if self.planned.contains(&wanted) || !wanted.is_file() => {
+                Some(CollisionPolicy::Suffix)
+            }
+            CollisionPolicy::KeepNewer if is_newer(file_path, &wanted) => Some(CollisionPolicy::Overwrite),
+            CollisionPolicy::KeepNewer => Some(CollisionPolicy::Skip),
+            policy => Some(policy),
+        };
+        if applied == Some(CollisionPolicy::Skip) {
+            let collision =
+                Collision { source: file_path.to_path_buf(), existing: wanted, applied: CollisionPolicy::Skip, target: None, identical: false };
+            self.collisions.push(collision);
+            return None;
+        }
+        if !self.dry_run && !dest_dir.exists() {
+            if let Err(e) = fs::create_dir_all(&dest_dir) {
+                self.errors.push(OperationError::new(Operation::CreateFolder, &dest_dir, e));
+                self.failed_folders.insert(dest_dir);
+                return None;
+            }
+        }
+        let (target, replaced, applied) = match applied {
+            Some(CollisionPolicy::Overwrite) => {
+                let trash_folder = self.root_dir.join(TRASH_FOLDER);
+                let trashed = get_non_duplicate_name(&trash_folder, &target_name, &self.planned);
+                self.planned.insert(trashed.clone());
+                (wanted.clone(), Some(trashed), applied)
+            }
+            Some(CollisionPolicy::Hash) => match hashed_name(&dest_dir, &target_name, file_path, &self.planned) {
+                Some(target) => (target, None, applied),
+                None => (get_non_duplicate_name(&dest_dir, &target_name, &self.planned), None, Some(CollisionPolicy::Suffix)),
+            },
+            _ => (get_non_duplicate_name(&dest_dir, &target_name, &self.planned), None, applied),
+        };
+        // Size must be read before the move, the source is gone afterwards
+        let size = fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
+        self.planned.insert(target.clone());
+        Some(Plan { target, size, replaced, collision: applied.map(|applied| (wanted, applied)), identical: false })
+    }
+
+    // Skip a file whose content is already at `existing`: leave it alone, or plan its
+    // move to the trash folder when identical files are removed
+    fn plan_identical(&mut self, file_path: &Path, file_name: &OsStr, existing: PathBuf) -> Option<Plan> {
+        if !self.remove_identical || self.copy {
+            let collision =
+                Collision { source: file_path.to_path_buf(), existing, applied: CollisionPolicy::Skip, target: None, identical: true };
+            self.collisions.push(collision);
+            return None;
+        }
+        let trash_folder = self.root_dir.join(TRASH_FOLDER);
+        if !self.dry_run {
+            if let Err(e) = fs::create_dir_all(&trash_folder) {
+                self.errors.push(OperationError::new(Operation::CreateFolder, &trash_folder, e));
+                return None;
+            }
+        }
+        let target = get_non_duplicate_name(&trash_folder, file_name, &self.planned);
+        let size = fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
+        self.planned.insert(target.clone());
+        Some(Plan { target, size, replaced: None, collision: Some((existing, CollisionPolicy::Skip)), identical: true })
+    }
+
+    // Record the name clash of a file moved (or planned in a dry run). Returns its target
+    // and size, None for an identical file sent to the trash folder, which isn't a move.
+    fn planned_move(&mut self, file_path: &Path, plan: Plan) -> Option<(PathBuf, u64)> {
+        if let Some((existing, applied)) = plan.collision {
+            let collision = Collision {
+                source: file_path.to_path_buf(),
+                existing,
+                applied,
+                target: Some(plan.target.clone()),
+                identical: plan.identical,
+            };
+            self.collisions.push(collision);
+        }
+        (!plan.identical).then_some((plan.target, plan.size))
+    }
+
+    // Move (or copy) a planned file and journal the move; several threads may run it at once.
+    // A file being overwritten goes to the trash folder first, and back if the move fails.
+    // Returns whether the file was moved, and the failures.
+    fn execute(&self, file_path: &Path, plan: &Plan) -> (bool, Vec<OperationError>) {
+        let mut errors = Vec::new();
+        if let Some(trashed) = &plan.replaced {
+            let trash_folder = trashed.parent().unwrap_or(self.root_dir);
+            match fs::create_dir_all(trash_folder).and_then(|()| self.safe_move.move_file(&plan.target, trashed)) {
+                Ok(Some(e)) => errors.push(OperationError::new(Operation::PreserveMetadata, trashed, e)),
+                Ok(None) => {}
+                Err(e) => {
+                    errors.push(OperationError::new(Operation::Move, &plan.target, e));
+                    return (false, errors);
+                }
+            }
+        }
+        let (result, operation) = if self.copy {
+            (self.safe_move.copy_file(file_path, &plan.target), Operation::Copy)
+        } else {
+            (self.safe_move.move_file(file_path, &plan.target), Operation::Move)
+        };
+        match result {
+            Ok(Some(e)) => errors.push(OperationError::new(Operation::PreserveMetadata, &plan.target, e)),
+            Ok(None) => {}
+            Err(e) => {
+                errors.push(OperationError::new(operation, file_path, e));
+                if let Some(trashed) = &plan.replaced {
+                    if let Err(e) = self.safe_move.move_file(trashed, &plan.target) {
+                        errors.push(OperationError::new(Operation::Move, trashed, e));
+                    }
+                }
+                return (false, errors);
+            }
+        }
+        // The overwritten file's trip to the trash is journaled first, so undo brings it
+        // back after the file that replaced it has left; a copy leaves nothing to bring back
+        let moves = plan
+            .replaced
+            .iter()
+            .map(|trashed| (plan.target.as_path(), trashed.as_path()))
+            .chain((!self.copy).then_some((file_path, plan.target.as_path())));
+        if let Some(journal) = self.journal.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            for (src, dst) in moves {
+                if let Err(e) = journal.record(src, dst) {
+                    errors.push(OperationError::new(Operation::Journal, src, e));
+                }
+            }
+        }
+        (true, errors)
+    }
+
+    // A mover set up as `options` says
+    fn with_options(root_dir: &'a Path, classifier: &'a Classifier, source_dirs: &[PathBuf], options: &'a MoveOptions) -> FileMover<'a> {
+        FileMover::new(root_dir, classifier, &options.layout, options.dry_run)
+            .with_source_dirs(source_dirs)
+            .with_collision_policy(options.on_collision)
+            .with_remove_identical(options.remove_identical)
+            .with_reorganize(options.reorganize)
+            .with_copy(options.copy)
+    }
+
+    // The name clashes since the last call, for a mover that keeps running
+    pub fn take_collisions(&mut self) -> Vec<Collision> {
+        std::mem::take(&mut self.collisions)
+    }
+
+    // The failures since the last call, for a mover that keeps running
+    pub fn take_errors(&mut self) -> Vec<OperationError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    // The failures of every file handled
+    pub fn finish(self) -> Vec<OperationError> {
+        self.errors
+    }
+}
+
+// Files planned and then moved together; targets stay reserved until their batch is done
+const MOVE_BATCH: usize = 1024;
+
+// Thread pool of `max_threads` threads; None when the global pool (one thread per CPU) will do
+fn move_pool(max_threads: Option<usize>) -> Option<rayon::ThreadPool> {
+    max_threads.and_then(|threads| rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok())
+}
+
+// Plan a batch of files one at a time, then move them on `pool` (or the global pool)
+// in parallel; failures and name clashes are kept in the mover. After an interrupt the
+// files already being moved are finished and no others are started. Returns the target
+// and size of each file, in order, None for those not moved.
+fn move_batch(mover: &mut FileMover, batch: &[(&FileType, &Path)], pool: Option<&rayon::ThreadPool>) -> Vec<Option<(PathBuf, u64)>> {
+    let planned: Vec<Option<Plan>> =
+        batch.iter().map(|(file_type, path)| if is_interrupted() { None } else { mover.plan(file_type, path) }).collect();
+    if mover.dry_run {
+        return batch.iter().zip(planned).map(|((_, path), plan)| mover.planned_move(path, plan?)).collect();
+    }
+    let outcomes: Vec<(bool, Vec<OperationError>)> = {
+        let mover = &*mover;
+        let run = || {
+            batch
+                .par_iter()
+                .zip(&planned)
+                .map(|((_, path), plan)| match plan {
+                    Some(plan) if !is_interrupted() => mover.execute(path, plan),
+                    _ => (false, Vec::new()),
+                })
+                .collect()
+        };
+        match pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+    };
+    mover.planned.clear();
+    batch
+        .iter()
+        .zip(planned)
+        .zip(outcomes)
+        .map(|(((_, path), plan), (moved, errors))| {
+            mover.errors.extend(errors);
+            mover.planned_move(path, plan.filter(|_| moved)?)
+        })
+        .collect()
+}
+
+// The files of a scan that `move_files` with the same options would move: all but those
+// already in place
+pub fn files_to_move<'a>(
+    file_map: &'a HashMap<FileType, Vec<PathBuf>>,
+    root_dir: &Path,
+    source_dirs: &[PathBuf],
+    classifier: &Classifier,
+    options: &MoveOptions,
+) -> Vec<&'a Path> {
+    let options = MoveOptions { dry_run: true, ..options.clone() };
+    let mover = FileMover::with_options(root_dir, classifier, source_dirs, &options);
+    file_map
+        .iter()
+        .flat_map(|(file_type, paths)| paths.iter().map(move |path| (file_type, path.as_path())))
+        .filter(|(file_type, path)| !mover.is_in_place(file_type, path))
+        .map(|(_, path)| path)
+        .collect()
+}
+
+// Move all files of a scan into their category folders under root_dir, as `FileMover` does.
+// Files scanned from other folders than root_dir come from `source_dirs` (see
+// `FileMover::with_source_dirs`); pass none when root_dir was the only one scanned.
+// Files are planned one at a time and moved by up to `options.max_threads` threads at once
+// (None: one per CPU, 1: one file at a time), which pays off with many small files or
+// slow storage. Taken target names are handled as `options.on_collision` says; a file whose
+// target holds the same content is left alone, or sent to the trash folder with `remove_identical`.
+// Files already inside their category folder are only moved with `reorganize`. With
+// `copy` the files are copied instead (see `FileMover::with_copy`).
+// `on_moved` receives the number of files handled so far and the total.
+// Returns what was moved (or would be moved), the name clashes met, and the failures; a
+// file that fails to move is left where it is.
+pub fn move_files(
+    file_map: &HashMap<FileType, Vec<PathBuf>>,
+    root_dir: &Path,
+    source_dirs: &[PathBuf],
+    classifier: &Classifier,
+    options: &MoveOptions,
+    mut on_moved: impl FnMut(usize, usize),
+) -> (MovedFiles, Vec<OperationError>) {
+    let files: Vec<(&FileType, &Path)> = classifier
+        .categories()
+        .iter()
+        .filter_map(|category| Some((&category.file_type, file_map.get(&category.file_type)?)))
+        .flat_map(|(file_type, paths)| paths.iter().map(move |path| (file_type, path.as_path())))
+        .collect();
+    let total = files.len();
+    let mut done = 0usize;
+    let mut mover = FileMover::with_options(root_dir, classifier, source_dirs, options);
+    let pool = move_pool(options.max_threads);
+    let mut moves = Vec::new();
+    let mut moved_bytes = 0u64;
+    for batch in files.chunks(MOVE_BATCH) {
+        if is_interrupted() {
+            break;
+        }
+        for ((_, path), moved) in batch.iter().zip(move_batch(&mut mover, batch, pool.as_ref())) {
+            done += 1;
+            on_moved(done, total);
+            if let Some((target_path, size)) = moved {
+                moves.push((path.to_path_buf(), target_path));
+                moved_bytes += size;
+            }
+        }
+    }
+    let collisions = mover.take_collisions();
+    (MovedFiles { moves, moved_bytes, collisions }, mover.finish())
+}
+
+// Files handled by `scan_and_move_files`
+#[derive(Debug, Default)]
+pub struct StreamedMoves {
+    // Files found per category, not counting those already in place
+    pub stats: HashMap<FileType, usize>,
+    // Sizes of those files
+    pub sizes: SizeStats,
+    pub moved: usize,
+    pub moved_bytes: u64,
+    // Files whose target name was taken
+    pub collisions: Vec<Collision>,
+}
+
+// Scan source_dir and move every file into its category folder under root_dir (which
+// may be source_dir itself) as soon as it is found (see `scan_streaming`), in batches
+// moved as `options` says, like `move_files` does. Neither the scan nor the move holds
+// the list of files: memory stays flat on trees of millions of files, except for the
+// names planned in a dry run and the name clashes. Files already in place are not
+// counted, since a file moved ahead of the walk is found again in its category folder.
+// `on_event` sees every scan event, a file before it is moved. Returns the counts and
+// the failures.
+pub fn scan_and_move_files(
+    source_dir: &Path,
+    root_dir: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    options: &MoveOptions,
+    mut on_event: impl FnMut(ScanEvent),
+) -> (StreamedMoves, Vec<OperationError>) {
+    let mut mover = FileMover::with_options(root_dir, classifier, &[source_dir.to_path_buf()], options);
+    let pool = move_pool(options.max_threads);
+    let mut result = StreamedMoves { stats: empty_stats(classifier, include_no_ext), ..StreamedMoves::default() };
+    // Files found and not moved yet
+    let mut batch: Vec<(FileType, PathBuf)> = Vec::new();
+    let move_found = |mover: &mut FileMover, batch: &mut Vec<(FileType, PathBuf)>, result: &mut StreamedMoves| {
+        let files: Vec<(&FileType, &Path)> = batch.iter().map(|(file_type, path)| (file_type, path.as_path())).collect();
+        for (_, size) in move_batch(mover, &files, pool.as_ref()).into_iter().flatten() {
+            result.moved += 1;
+            result.moved_bytes += size;
+        }
+        batch.clear();
+    };
+    scan_streaming(source_dir, include_no_ext, filter, classifier, |event| {
+        let ScanEvent::File(file_type, path) = &event else {
+            on_event(event);
+            return;
+        };
+        let (file_type, path) = (file_type.clone(), path.to_path_buf());
+        on_event(event);
+        if mover.is_in_place(&file_type, &path) {
+            return;
+        }
+        result.stats.entry(file_type.clone()).and_modify(|e| *e += 1);
+        // Read before the move, like the size of a moved file
+        if let Ok(metadata) = fs::metadata(&path) {
+            result.sizes.add(&file_type, &path, metadata.len());
+        }
+        batch.push((file_type, path));
+        if batch.len() == MOVE_BATCH {
+            move_found(&mut mover, &mut batch, &mut result);
+        }
+    });
+    move_found(&mut mover, &mut batch, &mut result);
+    result.collisions = mover.take_collisions();
+    (result, mover.finish())
+}
+
+// Remove the source folders left empty after moving, deepest first, so a folder whose
+// subfolders all became empty goes too. Category folders, the trash folder and entries
+// excluded by `filter` (or beyond its depth limit or filesystem) are never touched;
+// folders still holding a file (for example one that failed to move) are kept. Returns
+// the folders removed and the folders that couldn't be removed.
+pub fn remove_empty_dirs(root: &Path, filter: &ScanFilter, classifier: &Classifier) -> (Vec<PathBuf>, Vec<OperationError>) {
+    let protected: Vec<PathBuf> = classifier
+        .categories()
+        .iter()
+        .chain(classifier.skipped())
+        .map(|category| root.join(&category.folder))
+        .chain([root.join(TRASH_FOLDER)])
+        .collect();
+    // Walked top-down and visited in reverse, so every folder comes after its subfolders
+    let dirs: Vec<PathBuf> = filter
+        .walk(root)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !protected.iter().any(|p| e.path() == p) && !filter.is_excluded(e, root))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .collect();
+    let mut removed = Vec::new();
+    let mut errors = Vec::new();
+    for path in dirs.into_iter().rev() {
+        let is_empty = fs::read_dir(&path).map(|mut d| d.next().is_none()).unwrap_or(false);
+        if !is_empty {
+            continue;
+        }
+        match fs::remove_dir(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) => errors.push(OperationError::new(Operation::RemoveFolder, &path, e)),
+        }
+    }
+    (removed, errors)
+}
+
+// Merge mode: drop files that already sit in their category folder under root, and
+// files whose content is already present in that folder. Stats are updated to the
+// remaining counts. Returns how many files were skipped, the `(file, existing copy)`
+// pairs among them, and the existing files that couldn't be read.
+pub fn retain_unsorted_files(
+    file_map: &mut HashMap<FileType, Vec<PathBuf>>,
+    stats: &mut HashMap<FileType, usize>,
+    root: &Path,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+) -> (usize, Vec<(PathBuf, PathBuf)>, Vec<OperationError>) {
+    let mut skipped = 0usize;
+    let mut present = Vec::new();
+    let mut errors = Vec::new();
+    for (file_type, paths) in file_map.iter_mut() {
+        let folder = root.join(classifier.folder_name(file_type));
+        // Existing folder contents indexed by size, so only same-size files get hashed
+        let existing = if folder.is_dir() {
+            let (existing, read_errors) = group_by_size(&collect_files(&folder, filter, root));
+            errors.extend(read_errors);
+            existing
+        } else {
+            HashMap::new()
+        };
+        paths.retain(|path| {
+            if path.starts_with(&folder) {
+                skipped += 1;
+                return false;
+            }
+            let Ok(metadata) = fs::metadata(path) else {
+                return true;
+            };
+            let same = existing
+                .get(&metadata.len())
+                .and_then(|candidates| {
+                    candidates
+                        .iter()
+                        .find(|candidate| same_content(path, candidate).unwrap_or(false))
+                });
+            if let Some(same) = same {
+                present.push((path.clone(), same.clone()));
+                skipped += 1;
+                return false;
+            }
+            true
+        });
+        stats.insert(file_type.clone(), paths.len());
+    }
+    (skipped, present, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Move root/photo.jpg ("new") onto a taken image/photo.jpg ("old") as `policy` says
+    fn move_onto_taken_name(root: &Path, policy: CollisionPolicy) -> (MovedFiles, Vec<OperationError>) {
+        fs::create_dir(root.join("image")).unwrap();
+        fs::write(root.join("image").join("photo.jpg"), b"old").unwrap();
+        fs::write(root.join("photo.jpg"), b"new").unwrap();
+        let files = HashMap::from([(FileType::Image, vec![root.join("photo.jpg")])]);
+        let options = MoveOptions { on_collision: policy, ..MoveOptions::default() };
+        move_files(&files, root, &[], &Classifier::builtin(), &options, |_, _| {})
+    }
+
+    #[test]
+    fn suffix_moves_the_file_next_to_the_taken_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (moved, errors) = move_onto_taken_name(root, CollisionPolicy::Suffix);
+        assert!(errors.is_empty());
+        let target = root.join("image").join("photo_1.jpg");
+        assert_eq!(moved.moves, [(root.join("photo.jpg"), target.clone())]);
+        assert_eq!(moved.collisions[0].applied, CollisionPolicy::Suffix);
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read(root.join("image").join("photo.jpg")).unwrap(), b"old");
+    }
+
+    #[test]
+    fn hash_appends_the_start_of_the_content_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (moved, errors) = move_onto_taken_name(root, CollisionPolicy::Hash);
+        assert!(errors.is_empty());
+        let target = &moved.moves[0].1;
+        let name = target.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("photo_") && name.ends_with(".jpg") && name != "photo_1.jpg", "{name}");
+        assert_eq!(moved.collisions[0].applied, CollisionPolicy::Hash);
+        assert_eq!(fs::read(target).unwrap(), b"new");
+        assert_eq!(fs::read(root.join("image").join("photo.jpg")).unwrap(), b"old");
+    }
+
+    #[test]
+    fn skip_leaves_the_file_where_it_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (moved, errors) = move_onto_taken_name(root, CollisionPolicy::Skip);
+        assert!(errors.is_empty());
+        assert!(moved.moves.is_empty());
+        assert_eq!(moved.collisions[0].applied, CollisionPolicy::Skip);
+        assert_eq!(moved.collisions[0].target, None);
+        assert_eq!(fs::read(root.join("photo.jpg")).unwrap(), b"new");
+        assert_eq!(fs::read(root.join("image").join("photo.jpg")).unwrap(), b"old");
+    }
+
+    #[test]
+    fn overwrite_sends_the_existing_file_to_the_trash_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (moved, errors) = move_onto_taken_name(root, CollisionPolicy::Overwrite);
+        assert!(errors.is_empty());
+        let target = root.join("image").join("photo.jpg");
+        assert_eq!(moved.moves, [(root.join("photo.jpg"), target.clone())]);
+        assert_eq!(moved.collisions[0].applied, CollisionPolicy::Overwrite);
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read(root.join(TRASH_FOLDER).join("photo.jpg")).unwrap(), b"old");
+    }
+
+    #[test]
+    fn keep_newer_overwrites_only_an_older_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let older = FileTime::from_unix_time(1_000_000_000, 0);
+        let newer = FileTime::from_unix_time(1_500_000_000, 0);
+        fs::create_dir(root.join("image")).unwrap();
+        fs::write(root.join("image").join("photo.jpg"), b"old").unwrap();
+        filetime::set_file_mtime(root.join("image").join("photo.jpg"), older).unwrap();
+        fs::write(root.join("photo.jpg"), b"new").unwrap();
+        filetime::set_file_mtime(root.join("photo.jpg"), newer).unwrap();
+        fs::write(root.join("clip.jpg"), b"stale").unwrap();
+        filetime::set_file_mtime(root.join("clip.jpg"), older).unwrap();
+        fs::write(root.join("image").join("clip.jpg"), b"fresh").unwrap();
+        filetime::set_file_mtime(root.join("image").join("clip.jpg"), newer).unwrap();
+        let files = HashMap::from([(FileType::Image, vec![root.join("photo.jpg"), root.join("clip.jpg")])]);
+        let options = MoveOptions { on_collision: CollisionPolicy::KeepNewer, ..MoveOptions::default() };
+        let (moved, errors) = move_files(&files, root, &[], &Classifier::builtin(), &options, |_, _| {});
+        assert!(errors.is_empty());
+
+        // The newer photo replaced the old one, the stale clip stayed put
+        assert_eq!(moved.moves, [(root.join("photo.jpg"), root.join("image").join("photo.jpg"))]);
+        let applied = |source: &str| moved.collisions.iter().find(|c| c.source == root.join(source)).map(|c| c.applied);
+        assert_eq!(applied("photo.jpg"), Some(CollisionPolicy::Overwrite));
+        assert_eq!(applied("clip.jpg"), Some(CollisionPolicy::Skip));
+        assert_eq!(fs::read(root.join("image").join("photo.jpg")).unwrap(), b"new");
+        assert_eq!(fs::read(root.join(TRASH_FOLDER).join("photo.jpg")).unwrap(), b"old");
+        assert_eq!(fs::read(root.join("clip.jpg")).unwrap(), b"stale");
+        assert_eq!(fs::read(root.join("image").join("clip.jpg")).unwrap(), b"fresh");
+    }
+}
@@ -0,0 +1,865 @@
+/*
+File: organize.rs
+
+The whole organizer run as a library call: `organize` scans and classifies a
+directory, optionally moves the files into their category folders, and optionally
+looks for duplicates, returning everything it found instead of printing or prompting.
+A move that would copy more than the destination has room for isn't started.
+With `duplicate_dirs`, folders that are whole copies of another folder are reported as
+one tree each (see `trees`), and their files are left out of the file-level groups.
+Deleting or linking the duplicates is left to the caller; audio files holding the same
+recording in different encodings (`similar_audio`), and images (`similar_images`) and
+videos (`similar_videos`) that look alike are only ever reported, for review.
+`analyze` is the read-only run, for auditing a tree without write access: the same
+scan and duplicate search, with nothing moved, removed or written, not even the hash cache.
+Directories that were never organized can be compared as one set (`directories_scope`),
+and so can everything under the root, category folders and uncategorized places alike
+(`root_scope`, the `whole_root_dedup` option).
+Reference folders (`reference_dirs`) hold files that are only ever kept: their files are
+added to the comparison (`add_reference_files`) and only files whose content is also in
+one of them count as duplicates (`reference_groups`), e.g. to clear out of Downloads
+whatever the archive already holds.
+Besides root itself, the files of other source directories can be organized into root's
+category folders (`sources`), merged into an already organized root (`merge`), moved as
+the scan finds them (`stream`) and scanned with a checkpoint an interrupted run resumes
+from (`checkpoint`). `organize_with_progress` reports each step as it goes, and a run
+stopped by Ctrl+C returns after the file in progress with `interrupted` set.
+The steps are also exposed one by one (`duplicate_scopes`, `find_scope_duplicates`,
+`duplicate_groups`) for callers that need them on their own, like the dedupe subcommand.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cache::{HashCache, HASH_CACHE_FILE};
+use crate::checkpoint::{remove_checkpoint, ScanCheckpoint, CHECKPOINT_FILE, CHECKPOINT_HASHES_FILE};
+use crate::classify::{Classifier, FileType};
+use crate::dedupe::{
+    collect_files, find_duplicates_cached, order_by_keep_strategy, prefer_files_under, with_thread_limit, DedupOptions,
+    KeepStrategy,
+};
+use crate::error::{Operation, OperationError};
+use crate::filter::{is_reached_through_link, ScanFilter};
+use crate::fingerprint::{find_similar_audio, SimilarAudio};
+use crate::interrupt::is_interrupted;
+use crate::mover::{
+    files_to_move, move_files, remove_empty_dirs, retain_unsorted_files, scan_and_move_files, Collision, CollisionPolicy, Layout,
+    MoveOptions,
+};
+use crate::scan::{scan_and_classify_files, scan_resumable, ScanEvent, ScanResult, SizeStats, TRASH_FOLDER};
+use crate::similar::{find_similar_images, PerceptualHash, SimilarImage};
+use crate::space::{check_space, same_filesystem, SpaceShortage};
+use crate::trees::{find_duplicate_trees, DuplicateTree};
+use crate::video::{find_similar_videos, SimilarVideo};
+
+// What `organize` does; the default only scans, with the built-in categories
+#[derive(Debug, Clone, Default)]
+pub struct OrganizeOptions {
+    pub classifier: Classifier,
+    // Entries skipped by the scan and the duplicate search
+    pub filter: ScanFilter,
+    // Collect files without an extension as `FileType::Other`
+    pub include_no_ext: bool,
+    // Directories whose files are organized into the category folders under root, in
+    // order; none organizes root itself
+    pub sources: Vec<PathBuf>,
+    // The filter each source is walked with, by position; a source without one uses `filter`
+    pub source_filters: Vec<ScanFilter>,
+    // Move the files into their category folders
+    pub move_files: bool,
+    // How files are arranged inside their category folder when moving
+    pub layout: Layout,
+    // What to do when a target name is taken
+    pub on_collision: CollisionPolicy,
+    // Send files whose content is already at their target to the trash folder instead
+    // of leaving them in place
+    pub remove_identical: bool,
+    // Also move files already inside their category folder into the layout
+    pub reorganize: bool,
+    // Copy the files into their category folders, leaving the originals in place
+    // (implies `move_files`)
+    pub copy: bool,
+    // Number of threads moving files; None uses one per CPU
+    pub move_threads: Option<usize>,
+    // Remove the source folders left empty by the move
+    pub remove_empty_dirs: bool,
+    // Leave out the files already in their category folder, or whose content is already
+    // there, when root holds category folders
+    pub merge: bool,
+    // Move each file as soon as the scan finds it, so memory stays flat on huge trees. Only
+    // a real move of a single source on root's filesystem streams, and not when merging;
+    // the files are then not kept (`scan.files` and `moves` stay empty).
+    pub stream: bool,
+    // Checkpoint the scan of the first source and the hashes in root, so an interrupted
+    // run can be resumed (never in a dry run). The text describes the scan settings; a
+    // checkpoint made with other ones isn't resumed.
+    pub checkpoint: Option<String>,
+    // Resume from the checkpoint an interrupted run left in root instead of removing it
+    pub resume: bool,
+    // Plan the moves without touching anything
+    pub dry_run: bool,
+    // Look for duplicates after moving
+    pub dedup: bool,
+    pub dedup_options: DedupOptions,
+    // Compare across all categories (and unmoved files) instead of within each category folder
+    pub global_dedup: bool,
+    // Compare every file under root, classified or not, with each other and the unmoved
+    // files as one set; takes precedence over `global_dedup`
+    pub whole_root_dedup: bool,
+    // Also look for whole folders under root duplicated elsewhere under it
+    pub duplicate_dirs: bool,
+    // Pair images whose perceptual hashes differ in at most this many bits
+    pub similar_images: Option<u32>,
+    // Perceptual hash the images are compared by
+    pub perceptual_hash: PerceptualHash,
+    // Pair audio files whose fingerprints differ in at most this share of bits, e.g. an
+    // MP3 and a FLAC of the same song
+    pub similar_audio: Option<f64>,
+    // Pair videos whose sampled frames differ by at most this many bits on average;
+    // needs ffmpeg
+    pub similar_videos: Option<u32>,
+    // Which file of each duplicate group is kept
+    pub keep_strategy: KeepStrategy,
+    // Folders whose files are kept over the keep strategy's choice, earlier folders first
+    pub preferred_dirs: Vec<PathBuf>,
+    // Folders whose files are never removed; with any, only files whose content is also
+    // in one of them count as duplicates
+    pub reference_dirs: Vec<PathBuf>,
+    // The filter each reference folder is walked with, by position; a folder without one
+    // uses `filter`
+    pub reference_filters: Vec<ScanFilter>,
+    // Reuse the hashes of unchanged files from the hash cache in root, and update it
+    // (except in a dry run)
+    pub hash_cache: bool,
+}
+
+impl OrganizeOptions {
+    // The settings of the move, for `move_files`
+    pub fn move_options(&self) -> MoveOptions {
+        MoveOptions {
+            layout: self.layout.clone(),
+            dry_run: self.dry_run,
+            max_threads: self.move_threads,
+            on_collision: self.on_collision,
+            remove_identical: self.remove_identical,
+            reorganize: self.reorganize,
+            copy: self.copy,
+        }
+    }
+}
+
+// Everything `organize` found and did
+#[derive(Debug, Default)]
+pub struct OrganizeResult {
+    // Files as found by the scan, at their original paths
+    pub scan: ScanResult,
+    // Sizes of the files found per category, and the largest of them
+    pub sizes: SizeStats,
+    // Number of files left out by merging
+    pub merged: usize,
+    // `(file, copy in its category folder)` of the files left out by merging for their content
+    pub already_present: Vec<(PathBuf, PathBuf)>,
+    // Number of files moved, or planned in a dry run
+    pub moved: usize,
+    // `(source, target)` of every file moved, or planned in a dry run; empty for a streamed move
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    pub moved_bytes: u64,
+    // Files whose target name was taken, and the policy applied to each
+    pub collisions: Vec<Collision>,
+    // Source folders removed after the move
+    pub removed_dirs: Vec<PathBuf>,
+    pub duplicates: Vec<DuplicateGroup>,
+    // Labels of the sets of files searched for duplicates, in the order of their groups
+    pub searched: Vec<String>,
+    // Folders with identical copies; the files of the copies are in no group of `duplicates`
+    pub duplicate_trees: Vec<DuplicateTree>,
+    // Audio files holding the same recording, better quality first; never removed
+    pub similar_audio: Vec<SimilarAudio>,
+    // Images that look alike; never removed
+    pub similar_images: Vec<SimilarImage>,
+    // Videos that look alike; never removed
+    pub similar_videos: Vec<SimilarVideo>,
+    // Size of every duplicate except the kept files
+    pub reclaimable_bytes: u64,
+    // The destination lacked room for the files to be copied there; nothing was moved
+    pub space_shortage: Option<SpaceShortage>,
+    // Per-file failures of every step
+    pub errors: Vec<OperationError>,
+    // Stopped by Ctrl+C after the step in progress; the checkpoints are kept
+    pub interrupted: bool,
+}
+
+// What `organize_with_progress` reports while it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrganizeEvent {
+    // The scan picks up the checkpoint of an interrupted run
+    Resuming,
+    // The checkpoint was made with other scan settings, the scan starts over
+    CheckpointOutdated,
+    // Number of entries the scan of a source walked so far
+    Scanned(usize),
+    // Files moved so far, and the number to move
+    Moved(usize, usize),
+    // Files hashed so far in the set searched for duplicates, and its size
+    Hashed(usize, usize),
+    // The audio files are being fingerprinted
+    FingerprintingAudio,
+    // Frames of the videos are being sampled
+    SamplingVideos,
+}
+
+// One group of identical files
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    // Label of the category searched, or "classified" in global mode
+    pub category: String,
+    pub hash: String,
+    // File chosen by the keep strategy
+    pub kept: PathBuf,
+    // The other files of the group
+    pub duplicates: Vec<PathBuf>,
+}
+
+// A set of files compared with each other for duplicates
+#[derive(Debug, Clone)]
+pub struct DuplicateScope {
+    pub label: String,
+    // None for the global, cross-category scope
+    pub file_type: Option<FileType>,
+    pub files: Vec<PathBuf>,
+}
+
+// The sets of files to look for duplicates in: the contents of each category folder
+// under root together with the `unmoved` files of that category where they still are
+// (what the folder would hold after a move), or with `global` a single set of all of them.
+// Files found through a link to a folder under root are left out, they are never removed.
+pub fn duplicate_scopes(
+    root: &Path,
+    classifier: &Classifier,
+    filter: &ScanFilter,
+    unmoved: Option<&HashMap<FileType, Vec<PathBuf>>>,
+    global: bool,
+) -> Vec<DuplicateScope> {
+    let mut scopes: Vec<DuplicateScope> = classifier
+        .categories()
+        .iter()
+        .filter_map(|category| {
+            let folder = root.join(&category.folder);
+            let pending = unmoved.and_then(|unmoved| unmoved.get(&category.file_type));
+            if !folder.is_dir() && pending.is_none() {
+                return None;
+            }
+            let mut files = if folder.is_dir() { collect_files(&folder, filter, root) } else { Vec::new() };
+            files.extend(pending.into_iter().flatten().filter(|path| !is_reached_through_link(path, root)).cloned());
+            files.sort();
+            files.dedup();
+            Some(DuplicateScope {
+                label: category.file_type.label().to_string(),
+                file_type: Some(category.file_type.clone()),
+                files,
+            })
+        })
+        .collect();
+    if !global {
+        return scopes;
+    }
+    let mut files: Vec<PathBuf> = scopes.drain(..).flat_map(|scope| scope.files).collect();
+    files.sort();
+    files.dedup();
+    vec![DuplicateScope { label: "classified".to_string(), file_type: None, files }]
+}
+
+// The files under every directory of `dirs` as one set, so duplicates are found across
+// folders that were never organized, e.g. a laptop folder and its copy on a NAS. Each
+// directory is walked with its filter in `filters`; when the classifier skips
+// categories, only the files of the others take part. A file under two of the
+// directories counts once.
+pub fn directories_scope(dirs: &[PathBuf], filters: &[ScanFilter], classifier: &Classifier) -> DuplicateScope {
+    let mut files: Vec<PathBuf> = dirs
+        .iter()
+        .zip(filters)
+        .flat_map(|(dir, filter)| collect_files(dir, filter, dir))
+        .filter(|path| classifier.skipped().is_empty() || classifier.classify(path).is_some())
+        .collect();
+    files.sort();
+    files.dedup();
+    DuplicateScope { label: "scanned".to_string(), file_type: None, files }
+}
+
+// Every file under root as one set, wherever it lies and whether or not a category claims
+// it, together with the `unmoved` files of every category, so a copy left outside the
+// category folders is compared too. The trash folder, files reached through a link to a
+// folder under root and, when the classifier skips categories, the files of those are
+// left out.
+pub fn root_scope(
+    root: &Path,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    unmoved: Option<&HashMap<FileType, Vec<PathBuf>>>,
+) -> DuplicateScope {
+    let mut scope = directories_scope(&[root.to_path_buf()], std::slice::from_ref(filter), classifier);
+    let trash = root.join(TRASH_FOLDER);
+    scope.files.retain(|path| !path.starts_with(&trash));
+    let pending = unmoved.into_iter().flat_map(|unmoved| unmoved.values().flatten());
+    scope.files.extend(pending.filter(|path| !is_reached_through_link(path, root)).cloned());
+    scope.files.sort();
+    scope.files.dedup();
+    scope
+}
+
+// Add the files of the `reference` folders, walked with their filters in `filters`, to the
+// scopes they belong in: to a category's scope those classified into it, to a scope of
+// every category or file all of them
+pub fn add_reference_files(scopes: &mut [DuplicateScope], reference: &[PathBuf], filters: &[ScanFilter], classifier: &Classifier) {
+    if reference.is_empty() {
+        return;
+    }
+    let files = directories_scope(reference, filters, classifier).files;
+    for scope in scopes.iter_mut() {
+        match &scope.file_type {
+            Some(file_type) => {
+                scope.files.extend(files.iter().filter(|path| classifier.classify(path).as_ref() == Some(file_type)).cloned())
+            }
+            None => scope.files.extend(files.iter().cloned()),
+        }
+        scope.files.sort();
+        scope.files.dedup();
+    }
+}
+
+// Find the duplicates of one scope by content. Content hashes are taken from and added
+// to `cache` when one is given.
+// `on_hashed` reports hashing progress as in `find_duplicates_with_progress`.
+pub fn find_scope_duplicates(
+    scope: &DuplicateScope,
+    options: DedupOptions,
+    cache: Option<&HashCache>,
+    on_hashed: impl Fn(usize, usize) + Sync,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<OperationError>) {
+    find_duplicates_cached(&scope.files, options, cache, on_hashed)
+}
+
+// The files of one type in the scopes; in the global scope they are picked out with `classifier`
+fn scope_files_of_type(scopes: &[DuplicateScope], classifier: &Classifier, file_type: &FileType) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = scopes
+        .iter()
+        .flat_map(|scope| match &scope.file_type {
+            Some(scope_type) if scope_type == file_type => scope.files.clone(),
+            Some(_) => Vec::new(),
+            None => scope.files.iter().filter(|path| classifier.classify(path).as_ref() == Some(file_type)).cloned().collect(),
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+// Drop the files that are exact duplicates of a kept file in `groups`, so only one copy
+// of each group is compared for near-duplicates
+fn without_duplicates(mut files: Vec<PathBuf>, groups: &[DuplicateGroup]) -> Vec<PathBuf> {
+    let duplicates: HashSet<&PathBuf> = groups.iter().flat_map(|group| &group.duplicates).collect();
+    files.retain(|path| !duplicates.contains(path));
+    files
+}
+
+// Pairs of images in the scopes that look alike (see `similar::find_similar_images`),
+// leaving out the duplicates in `groups`
+pub fn find_scope_similar_images(
+    scopes: &[DuplicateScope],
+    classifier: &Classifier,
+    groups: &[DuplicateGroup],
+    hash: PerceptualHash,
+    max_distance: u32,
+    max_threads: Option<usize>,
+) -> (Vec<SimilarImage>, Vec<OperationError>) {
+    let files = without_duplicates(scope_files_of_type(scopes, classifier, &FileType::Image), groups);
+    with_thread_limit(max_threads, || find_similar_images(&files, hash, max_distance))
+}
+
+// Pairs of audio files in the scopes that hold the same recording (see
+// `fingerprint::find_similar_audio`), leaving out the duplicates in `groups`
+pub fn find_scope_similar_audio(
+    scopes: &[DuplicateScope],
+    classifier: &Classifier,
+    groups: &[DuplicateGroup],
+    max_bit_error: f64,
+    max_threads: Option<usize>,
+) -> (Vec<SimilarAudio>, Vec<OperationError>) {
+    let files = without_duplicates(scope_files_of_type(scopes, classifier, &FileType::Audio), groups);
+    with_thread_limit(max_threads, || find_similar_audio(&files, max_bit_error))
+}
+
+// Pairs of videos in the scopes that look alike (see `video::find_similar_videos`),
+// leaving out the duplicates in `groups`
+pub fn find_scope_similar_videos(
+    scopes: &[DuplicateScope],
+    classifier: &Classifier,
+    groups: &[DuplicateGroup],
+    max_distance: u32,
+    max_threads: Option<usize>,
+) -> (Vec<SimilarVideo>, Vec<OperationError>) {
+    let files = without_duplicates(scope_files_of_type(scopes, classifier, &FileType::Video), groups);
+    with_thread_limit(max_threads, || find_similar_videos(&files, max_distance))
+}
+
+// Take the files of the redundant folder copies of `trees` out of file-level duplicates,
+// dropping the groups left with a single file
+pub fn leave_out_tree_copies(duplicates: &mut HashMap<String, Vec<PathBuf>>, trees: &[DuplicateTree]) {
+    let copies: HashSet<&PathBuf> = trees.iter().flat_map(|tree| tree.pairs.iter().map(|(copy, _)| copy)).collect();
+    if copies.is_empty() {
+        return;
+    }
+    for files in duplicates.values_mut() {
+        files.retain(|path| !copies.contains(path));
+    }
+    duplicates.retain(|_, files| files.len() > 1);
+}
+
+// Drop the trees whose copies hold files of a category skipped for the run, since
+// those files are never removed
+pub fn leave_out_skipped_trees(trees: &mut Vec<DuplicateTree>, classifier: &Classifier) {
+    if classifier.skipped().is_empty() {
+        return;
+    }
+    trees.retain(|tree| !tree.pairs.iter().any(|(copy, _)| classifier.is_skipped_file(copy)));
+}
+
+// Bytes freed by removing every redundant copy of the trees
+pub fn tree_reclaimable_bytes(trees: &[DuplicateTree]) -> u64 {
+    trees.iter().map(|tree| tree.bytes * tree.duplicates.len() as u64).sum()
+}
+
+// Whether root holds the folder of any category, e.g. from an earlier run
+pub fn has_category_folders(root: &Path, classifier: &Classifier) -> bool {
+    classifier.categories().iter().any(|category| root.join(&category.folder).is_dir())
+}
+
+// Load the hash cache of `root`; an unreadable cache is reported and replaced by an empty one
+pub fn load_hash_cache(root: &Path) -> (HashCache, Option<OperationError>) {
+    match HashCache::load(root) {
+        Ok(cache) => (cache, None),
+        Err(e) => (HashCache::default(), Some(OperationError::new(Operation::HashCache, &root.join(HASH_CACHE_FILE), e))),
+    }
+}
+
+// Pick the file to keep in every group found in a scope, ordered by kept path: a file in
+// the first of `preferred_dirs` that holds one, otherwise the choice of `strategy`
+pub fn duplicate_groups(
+    duplicates: &HashMap<String, Vec<PathBuf>>,
+    category: &str,
+    strategy: KeepStrategy,
+    preferred_dirs: &[PathBuf],
+) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = duplicates
+        .iter()
+        .filter_map(|(hash, files)| {
+            let mut files = files.clone();
+            order_by_keep_strategy(&mut files, strategy);
+            prefer_files_under(&mut files, preferred_dirs);
+            let mut files = files.into_iter();
+            Some(DuplicateGroup {
+                category: category.to_string(),
+                hash: hash.clone(),
+                kept: files.next()?,
+                duplicates: files.collect(),
+            })
+        })
+        .collect();
+    groups.sort_by(|a, b| a.kept.cmp(&b.kept));
+    groups
+}
+
+// The groups of a scope searched with `reference` folders (see `add_reference_files`):
+// only those holding a file under one of the folders, with such a file kept and the files
+// outside them marked as duplicates. The group's other reference files are never removed.
+// Between several reference files, and for the order of the rest, `strategy` and
+// `preferred_dirs` decide as in `duplicate_groups`.
+pub fn reference_groups(
+    duplicates: &HashMap<String, Vec<PathBuf>>,
+    category: &str,
+    strategy: KeepStrategy,
+    preferred_dirs: &[PathBuf],
+    reference: &[PathBuf],
+) -> Vec<DuplicateGroup> {
+    let preferred: Vec<PathBuf> = reference.iter().chain(preferred_dirs).cloned().collect();
+    duplicate_groups(duplicates, category, strategy, &preferred)
+        .into_iter()
+        .filter(|group| is_under_any(&group.kept, reference))
+        .filter_map(|mut group| {
+            group.duplicates.retain(|path| !is_under_any(path, reference));
+            (!group.duplicates.is_empty()).then_some(group)
+        })
+        .collect()
+}
+
+// Drop the trees with a copy under one of the `reference` folders, whose files are never removed
+pub fn leave_out_reference_trees(trees: &mut Vec<DuplicateTree>, reference: &[PathBuf]) {
+    if reference.is_empty() {
+        return;
+    }
+    trees.retain(|tree| !tree.duplicates.iter().any(|copy| is_under_any(copy, reference)));
+}
+
+// Whether a path lies in one of `dirs`, compared as absolute paths
+fn is_under_any(path: &Path, dirs: &[PathBuf]) -> bool {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = absolute(path);
+    dirs.iter().any(|dir| path.starts_with(absolute(dir)))
+}
+
+// Bytes freed by removing every duplicate of the groups; files whose size can't be
+// read don't count and are returned as errors
+pub fn reclaimable_bytes(groups: &[DuplicateGroup]) -> (u64, Vec<OperationError>) {
+    let mut bytes = 0u64;
+    let mut errors = Vec::new();
+    for path in groups.iter().flat_map(|group| &group.duplicates) {
+        match fs::metadata(path) {
+            Ok(metadata) => bytes += metadata.len(),
+            Err(e) => errors.push(OperationError::new(Operation::ReadMetadata, path, e)),
+        }
+    }
+    (bytes, errors)
+}
+
+// Run the organizer on `root` read-only: scan, classify and look for duplicates as
+// `options` say, but never move, remove or write anything. A hash cache in root is
+// read, not updated.
+pub fn analyze(root: &Path, options: OrganizeOptions) -> OrganizeResult {
+    organize(
+        root,
+        OrganizeOptions { move_files: false, copy: false, remove_empty_dirs: false, merge: false, dry_run: true, dedup: true, ..options },
+    )
+}
+
+// Run the organizer on `root` as described by `options`, without printing or prompting.
+// Without a (real) move the files found by the scan are compared where they are, as if
+// they had been moved into their category folders.
+pub fn organize(root: &Path, options: OrganizeOptions) -> OrganizeResult {
+    organize_with_progress(root, options, |_| {})
+}
+
+// `organize`, reporting the progress of every step to `on_event`, from several threads
+// while hashing. A move that would copy more than the destination has room for ends the
+// run before anything is moved, except in a dry run, which only reports the shortage.
+pub fn organize_with_progress(root: &Path, options: OrganizeOptions, on_event: impl Fn(OrganizeEvent) + Sync) -> OrganizeResult {
+    let classifier = &options.classifier;
+    let sources = if options.sources.is_empty() { vec![root.to_path_buf()] } else { options.sources.clone() };
+    let source_filter = |index: usize| options.source_filters.get(index).unwrap_or(&options.filter);
+    let moving = options.move_files || options.copy;
+    let checkpoint = options.checkpoint.as_deref().filter(|_| !options.dry_run);
+    let mut result = OrganizeResult::default();
+    if checkpoint.is_some() && !options.resume {
+        clear_checkpoint(root, &mut result.errors);
+    }
+
+    let on_scanned = |walked| on_event(OrganizeEvent::Scanned(walked));
+    let stream = options.stream
+        && moving
+        && !options.copy
+        && !options.dry_run
+        && !options.merge
+        && sources.len() == 1
+        && same_filesystem(&sources[0], root);
+    if stream {
+        let mut scan = ScanResult::default();
+        let (streamed, errors) = scan_and_move_files(
+            &sources[0],
+            root,
+            options.include_no_ext,
+            source_filter(0),
+            classifier,
+            &options.move_options(),
+            |event| match event {
+                ScanEvent::Walked(walked) => on_scanned(walked),
+                ScanEvent::FollowedLink(path) => scan.followed_links.push(path.to_path_buf()),
+                ScanEvent::LinkLoop(path) => scan.link_loops.push(path.to_path_buf()),
+                ScanEvent::File(..) => {}
+                ScanEvent::Unmatched(path) => scan.unmatched.push(path.to_path_buf()),
+            },
+        );
+        scan.stats = streamed.stats;
+        result.scan = scan;
+        result.sizes = streamed.sizes;
+        result.moved = streamed.moved;
+        result.moved_bytes = streamed.moved_bytes;
+        result.collisions = streamed.collisions;
+        result.errors.extend(errors);
+    } else {
+        result.scan = match checkpoint {
+            Some(settings) => scan_checkpointed(&sources[0], root, &options, source_filter(0), settings, &on_event, &mut result.errors),
+            None => scan_and_classify_files(&sources[0], options.include_no_ext, source_filter(0), classifier, on_scanned),
+        };
+        // The other sources are added to the categories of the first one. Only the first
+        // scan is checkpointed; the others are walked again on resume.
+        for (index, source) in sources.iter().enumerate().skip(1) {
+            let scan = scan_and_classify_files(source, options.include_no_ext, source_filter(index), classifier, on_scanned);
+            add_scan(&mut result.scan, scan);
+        }
+    }
+    if is_interrupted() {
+        result.interrupted = true;
+        return result;
+    }
+    if options.merge && has_category_folders(root, classifier) {
+        let (merged, present, errors) =
+            retain_unsorted_files(&mut result.scan.files, &mut result.scan.stats, root, &options.filter, classifier);
+        result.merged = merged;
+        result.already_present = present;
+        result.errors.extend(errors);
+    }
+    // The streaming move read the sizes as it went
+    if !stream {
+        result.sizes = SizeStats::of_files(&result.scan.files);
+    }
+
+    if moving && !stream {
+        let move_options = options.move_options();
+        // Nothing is copied onto a destination without room for it all
+        let pending = files_to_move(&result.scan.files, root, &options.sources, classifier, &move_options);
+        result.space_shortage = check_space(pending, root, options.copy).err();
+        if result.space_shortage.is_some() && !options.dry_run {
+            return result;
+        }
+        let (moved, errors) = move_files(&result.scan.files, root, &options.sources, classifier, &move_options, |done, total| {
+            on_event(OrganizeEvent::Moved(done, total))
+        });
+        result.moved = moved.moves.len();
+        result.moves = moved.moves;
+        result.moved_bytes = moved.moved_bytes;
+        result.collisions = moved.collisions;
+        result.errors.extend(errors);
+    }
+    if is_interrupted() {
+        result.interrupted = true;
+        return result;
+    }
+    if moving && options.remove_empty_dirs && !options.dry_run && !options.copy {
+        for (index, source) in sources.iter().enumerate() {
+            let (removed, errors) = remove_empty_dirs(source, source_filter(index), classifier);
+            result.removed_dirs.extend(removed);
+            result.errors.extend(errors);
+        }
+    }
+
+    if options.dedup {
+        // Files still where the scan found them are compared too
+        let unmoved = (!moving || options.dry_run).then_some(&result.scan.files);
+        let mut scopes = if options.whole_root_dedup {
+            vec![root_scope(root, &options.filter, classifier, unmoved)]
+        } else {
+            duplicate_scopes(root, classifier, &options.filter, unmoved, options.global_dedup)
+        };
+        let reference = &options.reference_dirs;
+        let reference_filters: Vec<ScanFilter> =
+            (0..reference.len()).map(|index| options.reference_filters.get(index).unwrap_or(&options.filter).clone()).collect();
+        add_reference_files(&mut scopes, reference, &reference_filters, classifier);
+        let cache = hash_store(root, &options, checkpoint.is_some(), &mut result.errors);
+        let on_hashed = |done, total| on_event(OrganizeEvent::Hashed(done, total));
+        if options.duplicate_dirs {
+            let (trees, errors) = find_duplicate_trees(
+                root,
+                &options.filter,
+                options.dedup_options,
+                cache.as_ref(),
+                &options.preferred_dirs,
+                on_hashed,
+            );
+            result.duplicate_trees = trees;
+            leave_out_skipped_trees(&mut result.duplicate_trees, classifier);
+            leave_out_reference_trees(&mut result.duplicate_trees, reference);
+            result.errors.extend(errors);
+        }
+        for scope in &scopes {
+            let (mut duplicates, errors) = find_scope_duplicates(scope, options.dedup_options, cache.as_ref(), on_hashed);
+            result.errors.extend(errors);
+            leave_out_tree_copies(&mut duplicates, &result.duplicate_trees);
+            let groups = if reference.is_empty() {
+                duplicate_groups(&duplicates, &scope.label, options.keep_strategy, &options.preferred_dirs)
+            } else {
+                reference_groups(&duplicates, &scope.label, options.keep_strategy, &options.preferred_dirs, reference)
+            };
+            result.duplicates.extend(groups);
+            result.searched.push(scope.label.clone());
+        }
+        if is_interrupted() {
+            // The hashes are kept for the next run, in the hash cache or the checkpoint
+            if let Some(Err(e)) = cache.as_ref().map(HashCache::flush) {
+                result.errors.push(OperationError::new(Operation::Checkpoint, root, e));
+            }
+            result.interrupted = true;
+            return result;
+        }
+        if let Some(max_distance) = options.similar_images {
+            let (pairs, errors) = find_scope_similar_images(
+                &scopes,
+                classifier,
+                &result.duplicates,
+                options.perceptual_hash,
+                max_distance,
+                options.dedup_options.max_threads,
+            );
+            result.similar_images = pairs;
+            result.errors.extend(errors);
+        }
+        if let Some(max_bit_error) = options.similar_audio {
+            on_event(OrganizeEvent::FingerprintingAudio);
+            let (pairs, errors) = find_scope_similar_audio(
+                &scopes,
+                classifier,
+                &result.duplicates,
+                max_bit_error,
+                options.dedup_options.max_threads,
+            );
+            result.similar_audio = pairs;
+            result.errors.extend(errors);
+        }
+        if let Some(max_distance) = options.similar_videos {
+            on_event(OrganizeEvent::SamplingVideos);
+            let (pairs, errors) = find_scope_similar_videos(
+                &scopes,
+                classifier,
+                &result.duplicates,
+                max_distance,
+                options.dedup_options.max_threads,
+            );
+            result.similar_videos = pairs;
+            result.errors.extend(errors);
+        }
+        if let Some(cache) = cache.filter(|_| options.hash_cache && !options.dry_run) {
+            if let Err(e) = cache.save(root) {
+                result.errors.push(OperationError::new(Operation::HashCache, &root.join(HASH_CACHE_FILE), e));
+            }
+        }
+        let (bytes, errors) = reclaimable_bytes(&result.duplicates);
+        result.reclaimable_bytes = bytes + tree_reclaimable_bytes(&result.duplicate_trees);
+        result.errors.extend(errors);
+    }
+    // The run is over, the next one starts afresh
+    if checkpoint.is_some() {
+        clear_checkpoint(root, &mut result.errors);
+    }
+    result
+}
+
+// Scan `source` with `scan_resumable`, checkpointed into root with `settings`: resumed from
+// the checkpoint there when `options.resume` is set and it was made with the same settings
+fn scan_checkpointed(
+    source: &Path,
+    root: &Path,
+    options: &OrganizeOptions,
+    filter: &ScanFilter,
+    settings: &str,
+    on_event: &impl Fn(OrganizeEvent),
+    errors: &mut Vec<OperationError>,
+) -> ScanResult {
+    let mut checkpoint = match options.resume.then(|| ScanCheckpoint::load(root, settings)) {
+        Some(Ok(Some(checkpoint))) => {
+            on_event(OrganizeEvent::Resuming);
+            checkpoint
+        }
+        Some(Ok(None)) => {
+            on_event(OrganizeEvent::CheckpointOutdated);
+            ScanCheckpoint::new(settings)
+        }
+        Some(Err(e)) => {
+            errors.push(OperationError::new(Operation::Checkpoint, &root.join(CHECKPOINT_FILE), e));
+            ScanCheckpoint::new(settings)
+        }
+        None => ScanCheckpoint::new(settings),
+    };
+    // After a failed write the checkpoint is no longer kept up to date
+    let mut checkpoint_error = None;
+    let on_checkpoint = |checkpoint: &ScanCheckpoint| {
+        if checkpoint_error.is_none() {
+            checkpoint_error = checkpoint.save(root).err();
+        }
+    };
+    let scan = scan_resumable(source, options.include_no_ext, filter, &options.classifier, &mut checkpoint, on_checkpoint, |walked| {
+        on_event(OrganizeEvent::Scanned(walked))
+    });
+    if let Some(e) = checkpoint_error {
+        errors.push(OperationError::new(Operation::Checkpoint, &root.join(CHECKPOINT_FILE), e));
+    }
+    scan
+}
+
+// Add the files another source's scan found to `scan`
+fn add_scan(scan: &mut ScanResult, other: ScanResult) {
+    for (file_type, count) in other.stats {
+        *scan.stats.entry(file_type).or_default() += count;
+    }
+    for (file_type, paths) in other.files {
+        scan.files.entry(file_type).or_default().extend(paths);
+    }
+    scan.followed_links.extend(other.followed_links);
+    scan.link_loops.extend(other.link_loops);
+    scan.unmatched.extend(other.unmatched);
+}
+
+// Where the full hashes are kept while hashing: the hash cache of root with `hash_cache`,
+// otherwise a checkpoint file of their own when `checkpointing`, read back on resume
+fn hash_store(root: &Path, options: &OrganizeOptions, checkpointing: bool, errors: &mut Vec<OperationError>) -> Option<HashCache> {
+    if options.hash_cache {
+        let (cache, error) = load_hash_cache(root);
+        errors.extend(error);
+        Some(if checkpointing { cache.with_autosave(root.join(HASH_CACHE_FILE)) } else { cache })
+    } else if checkpointing {
+        let path = root.join(CHECKPOINT_HASHES_FILE);
+        let cache = if options.resume {
+            HashCache::load_file(&path).unwrap_or_else(|e| {
+                errors.push(OperationError::new(Operation::Checkpoint, &path, e));
+                HashCache::default()
+            })
+        } else {
+            HashCache::default()
+        };
+        Some(cache.with_autosave(path))
+    } else {
+        None
+    }
+}
+
+// Remove the checkpoints of root
+fn clear_checkpoint(root: &Path, errors: &mut Vec<OperationError>) {
+    if let Err(e) = remove_checkpoint(root) {
+        errors.push(OperationError::new(Operation::Checkpoint, &root.join(CHECKPOINT_FILE), e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn organize_moves_the_files_and_finds_their_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("trip")).unwrap();
+        fs::write(root.join("a.jpg"), b"same").unwrap();
+        fs::write(root.join("trip").join("b.jpg"), b"same").unwrap();
+        fs::write(root.join("song.mp3"), b"tune").unwrap();
+        let options = OrganizeOptions { move_files: true, remove_empty_dirs: true, dedup: true, ..OrganizeOptions::default() };
+        let result = organize(root, options);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(!result.interrupted);
+
+        // Both photos and the song were found and moved, and the emptied folder removed
+        assert_eq!(result.scan.stats.get(&FileType::Image), Some(&2));
+        assert_eq!(result.scan.stats.get(&FileType::Audio), Some(&1));
+        assert_eq!(result.moved, 3);
+        assert_eq!(result.moved_bytes, 12);
+        assert!(root.join("image").join("a.jpg").is_file());
+        assert!(root.join("image").join("b.jpg").is_file());
+        assert!(root.join("audio").join("song.mp3").is_file());
+        assert_eq!(result.removed_dirs, [root.join("trip")]);
+
+        // Each category folder was searched; the photos are one group in their new folder
+        assert_eq!(result.searched, ["Image", "Audio"]);
+        assert_eq!(result.duplicates.len(), 1);
+        let group = &result.duplicates[0];
+        assert_eq!(group.category, "Image");
+        assert_eq!(group.kept, root.join("image").join("a.jpg"));
+        assert_eq!(group.duplicates, [root.join("image").join("b.jpg")]);
+        assert_eq!(result.reclaimable_bytes, 4);
+    }
+}
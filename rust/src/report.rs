@@ -0,0 +1,391 @@
+/*
+File: report.rs
+
+Machine-readable JSON report of a run, for scripts and dashboards: how many files
+were scanned, category counts, sizes and the files found per category, the largest
+files (`largest_files`), the files no category claims (`unmatched`, with
+`--unmatched report`), the moves made, the files whose target name was taken with
+the collision policy applied to each, every duplicate group with its hash, the kept file and the files marked for
+deletion, every folder with whole copies elsewhere (`duplicate_trees`), the bytes the
+duplicates take up and the bytes actually reclaimed, and
+the per-file failures. In a dry run the moves and removals are the planned ones.
+Near-duplicates (images and videos that look alike, the same recording in different
+audio encodings) are kept apart from the exact duplicate groups under `near_duplicates`,
+as pairs that are only ever reviewed, never removed; `write_near_duplicates` writes them
+to a review report of their own.
+Duplicate groups can also be exported as CSV (`write_duplicates_csv`), one row per
+file, to be reviewed in a spreadsheet before anything is removed. Paths are written as UTF-8 strings; paths that are not
+valid UTF-8 are converted lossily and listed again under `lossy_paths`.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use crate::error::OperationError;
+use crate::fingerprint::SimilarAudio;
+use crate::mover::Collision;
+use crate::organize::DuplicateGroup;
+use crate::similar::SimilarImage;
+use crate::trees::DuplicateTree;
+use crate::video::SimilarVideo;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub dry_run: bool,
+    // Files classified into a category
+    pub files_scanned: usize,
+    pub categories: Vec<CategoryReport>,
+    // The largest files found, largest first
+    pub largest_files: Vec<FileSizeReport>,
+    // Files no category claims, when they are reported
+    pub unmatched: Vec<String>,
+    pub moves: Vec<MoveReport>,
+    pub moved_bytes: u64,
+    pub collisions: Vec<CollisionReport>,
+    pub duplicate_groups: Vec<DuplicateGroupReport>,
+    // Folders whose copies are removed (or linked) as a whole
+    pub duplicate_trees: Vec<DuplicateTreeReport>,
+    pub near_duplicates: NearDuplicatesReport,
+    // Size of every duplicate found, whether or not it was removed
+    pub reclaimable_bytes: u64,
+    // Duplicates deleted or replaced by links, and the bytes that freed
+    pub removed_duplicates: usize,
+    pub reclaimed_bytes: u64,
+    pub errors: Vec<ErrorReport>,
+    // Paths that could only be represented lossily (invalid UTF-8 replaced by U+FFFD)
+    pub lossy_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryReport {
+    pub name: String,
+    pub count: usize,
+    // Total size of the files
+    pub bytes: u64,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileSizeReport {
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MoveReport {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollisionReport {
+    pub source: String,
+    // The file already holding the name
+    pub existing: String,
+    // Policy applied, e.g. "suffix" (see `CollisionPolicy::describe`)
+    pub policy: String,
+    // Where the file went; null when it was skipped
+    pub moved_to: Option<String>,
+    // The existing file has the same content; moved_to is then the trash folder, or null
+    pub identical: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroupReport {
+    pub category: String,
+    pub hash: String,
+    pub kept: String,
+    pub deleted: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateTreeReport {
+    pub hash: String,
+    pub kept: String,
+    pub deleted: Vec<String>,
+    // Files in each copy and their total size
+    pub files: usize,
+    pub bytes: u64,
+}
+
+// Pairs of files that are alike but not identical, for review
+#[derive(Debug, Default, Serialize)]
+pub struct NearDuplicatesReport {
+    // Images whose perceptual hashes are close
+    pub images: Vec<SimilarImageReport>,
+    // Audio files holding the same recording in different encodings
+    pub audio: Vec<SimilarAudioReport>,
+    // Videos whose sampled frames look alike
+    pub videos: Vec<SimilarVideoReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarImageReport {
+    pub first: String,
+    pub second: String,
+    // Number of differing perceptual hash bits
+    pub distance: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarAudioReport {
+    // The file of higher quality and its quality, e.g. "flac, lossless, 44100 Hz, 16 bit"
+    pub better: String,
+    pub better_quality: String,
+    pub other: String,
+    pub other_quality: String,
+    pub bit_error: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarVideoReport {
+    pub first: String,
+    pub second: String,
+    // Average number of differing bits between their sampled frames
+    pub distance: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    // What was being done, e.g. "move" (see `Operation::describe`)
+    pub operation: String,
+    pub path: String,
+    pub error: String,
+}
+
+impl Report {
+    // Record the files classified into one category and their total size
+    pub fn add_category(&mut self, name: &str, files: &[PathBuf], bytes: u64) {
+        let files: Vec<String> = files.iter().map(|path| self.path_string(path)).collect();
+        self.categories.push(CategoryReport {
+            name: name.to_string(),
+            count: files.len(),
+            bytes,
+            files,
+        });
+    }
+
+    // Record the largest files found
+    pub fn add_largest_files(&mut self, files: &[(PathBuf, u64)]) {
+        for (path, bytes) in files {
+            let path = self.path_string(path);
+            self.largest_files.push(FileSizeReport { path, bytes: *bytes });
+        }
+    }
+
+    // Record the files no category claims
+    pub fn add_unmatched(&mut self, files: &[PathBuf]) {
+        for path in files {
+            let path = self.path_string(path);
+            self.unmatched.push(path);
+        }
+    }
+
+    // Record the files moved (or, in a dry run, that would be) and their total size
+    pub fn add_moves(&mut self, moves: &[(PathBuf, PathBuf)], moved_bytes: u64) {
+        for (from, to) in moves {
+            let report = MoveReport { from: self.path_string(from), to: self.path_string(to) };
+            self.moves.push(report);
+        }
+        self.moved_bytes += moved_bytes;
+    }
+
+    // Record the files whose target name was taken
+    pub fn add_collisions(&mut self, collisions: &[Collision]) {
+        for collision in collisions {
+            let report = CollisionReport {
+                source: self.path_string(&collision.source),
+                existing: self.path_string(&collision.existing),
+                policy: collision.applied.describe().to_string(),
+                moved_to: collision.target.as_ref().map(|target| self.path_string(target)),
+                identical: collision.identical,
+            };
+            self.collisions.push(report);
+        }
+    }
+
+    // Record one duplicate group with the kept file and the files marked for deletion
+    pub fn add_duplicate_group(&mut self, category: &str, hash: &str, kept: &Path, deleted: &[PathBuf]) {
+        let kept = self.path_string(kept);
+        let deleted = deleted.iter().map(|path| self.path_string(path)).collect();
+        self.duplicate_groups.push(DuplicateGroupReport {
+            category: category.to_string(),
+            hash: hash.to_string(),
+            kept,
+            deleted,
+        });
+    }
+
+    // Record one folder with the copies marked for deletion
+    pub fn add_duplicate_tree(&mut self, tree: &DuplicateTree) {
+        let kept = self.path_string(&tree.kept);
+        let deleted = tree.duplicates.iter().map(|path| self.path_string(path)).collect();
+        self.duplicate_trees.push(DuplicateTreeReport {
+            hash: tree.hash.clone(),
+            kept,
+            deleted,
+            files: tree.file_count,
+            bytes: tree.bytes,
+        });
+    }
+
+    // Record one pair of images that look alike
+    pub fn add_similar_image(&mut self, pair: &SimilarImage) {
+        let report = SimilarImageReport {
+            first: self.path_string(&pair.first),
+            second: self.path_string(&pair.second),
+            distance: pair.distance,
+        };
+        self.near_duplicates.images.push(report);
+    }
+
+    // Record one pair of audio files holding the same recording
+    pub fn add_similar_audio(&mut self, pair: &SimilarAudio) {
+        let report = SimilarAudioReport {
+            better: self.path_string(&pair.better),
+            better_quality: pair.better_quality.describe(),
+            other: self.path_string(&pair.other),
+            other_quality: pair.other_quality.describe(),
+            bit_error: pair.bit_error,
+        };
+        self.near_duplicates.audio.push(report);
+    }
+
+    // Record one pair of videos that look alike
+    pub fn add_similar_video(&mut self, pair: &SimilarVideo) {
+        let report = SimilarVideoReport {
+            first: self.path_string(&pair.first),
+            second: self.path_string(&pair.second),
+            distance: pair.distance,
+        };
+        self.near_duplicates.videos.push(report);
+    }
+
+    // Record the failures of the run
+    pub fn add_errors(&mut self, errors: &[OperationError]) {
+        for error in errors {
+            let report = ErrorReport {
+                operation: error.operation.describe().to_string(),
+                path: self.path_string(&error.path),
+                error: error.error.to_string(),
+            };
+            self.errors.push(report);
+        }
+    }
+
+    // Write the report as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    // Write only the near-duplicate pairs, as pretty-printed JSON, for review
+    pub fn write_near_duplicates(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.near_duplicates)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    // Whether any near-duplicate pairs were recorded
+    pub fn has_near_duplicates(&self) -> bool {
+        let near = &self.near_duplicates;
+        !(near.images.is_empty() && near.audio.is_empty() && near.videos.is_empty())
+    }
+
+    // Convert a path to a UTF-8 string, remembering it when the conversion was lossy
+    fn path_string(&mut self, path: &Path) -> String {
+        match path.to_str() {
+            Some(s) => s.to_string(),
+            None => {
+                let lossy = path.to_string_lossy().into_owned();
+                self.lossy_paths.push(lossy.clone());
+                lossy
+            }
+        }
+    }
+}
+
+// Quote a CSV field when it holds a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Write duplicate groups as CSV with the columns hash, size, path, mtime (local time)
+// and decision (`keep` or `delete`), the kept file first in each group. Size and mtime
+// are left empty for files whose metadata can't be read; paths that are not valid UTF-8
+// are converted lossily.
+pub fn write_duplicates_csv(groups: &[DuplicateGroup], path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "hash,size,path,mtime,decision")?;
+    for group in groups {
+        let files = std::iter::once((&group.kept, "keep")).chain(group.duplicates.iter().map(|dup| (dup, "delete")));
+        for (file, decision) in files {
+            let metadata = fs::metadata(file).ok();
+            let size = metadata.as_ref().map(|m| m.len().to_string()).unwrap_or_default();
+            let mtime = metadata
+                .and_then(|m| m.modified().ok())
+                .map(|modified| DateTime::<Local>::from(modified).format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                csv_field(&group.hash),
+                size,
+                csv_field(&file.to_string_lossy()),
+                mtime,
+                decision
+            )?;
+        }
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_are_scanned_moved_and_reported_lossily() {
+        use std::ffi::OsStr;
+        use std::fs;
+        use std::os::unix::ffi::OsStrExt;
+        use crate::classify::{Classifier, FileType};
+        use crate::filter::ScanFilter;
+        use crate::mover::{move_files, MoveOptions};
+        use crate::scan::scan_and_classify_files;
+
+        let (source, dest) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (source, dest) = (source.path(), dest.path());
+        let name = OsStr::from_bytes(b"\xff.jpg");
+        fs::write(source.join(name), b"new").unwrap();
+        // A taken target name gets a suffix without the name having to be UTF-8
+        fs::create_dir(dest.join("image")).unwrap();
+        fs::write(dest.join("image").join(name), b"old").unwrap();
+
+        let classifier = Classifier::builtin();
+        let scan = scan_and_classify_files(source, false, &ScanFilter::default(), &classifier, |_| {});
+        assert_eq!(scan.files[&FileType::Image], [source.join(name)]);
+        let sources = [source.to_path_buf()];
+        let (moved, errors) = move_files(&scan.files, dest, &sources, &classifier, &MoveOptions::default(), |_, _| {});
+        assert!(errors.is_empty());
+        let target = dest.join("image").join(OsStr::from_bytes(b"\xff_1.jpg"));
+        assert_eq!(moved.moves, [(source.join(name), target.clone())]);
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+
+        let mut report = Report::default();
+        report.add_moves(&moved.moves, 3);
+        assert_eq!(report.moves[0].to, target.to_string_lossy());
+        assert!(report.moves[0].to.ends_with("\u{fffd}_1.jpg"));
+        assert_eq!(report.lossy_paths.len(), 2);
+    }
+}
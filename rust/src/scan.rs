@@ -0,0 +1,426 @@
+/*
+File: scan.rs
+
+Walking the directory to organize and sorting its files into the categories of a
+`Classifier`. Nothing is printed; symlinks followed outside the root, and the ones
+skipped because they loop back into a folder being walked, are returned with the
+result so the caller can report them.
+`scan_and_classify_files` keeps every path it finds, which is what a preview or a
+report needs but costs memory in proportion to the number of files; `scan_streaming`
+hands each file to a callback instead and keeps nothing. `scan_resumable` collects like
+`scan_and_classify_files` and checkpoints its progress so an interrupted scan can be
+resumed (see `checkpoint`). `SizeStats` adds up the size of the files found per
+category and keeps the largest of them, for the summary of a run.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use walkdir::DirEntry;
+use crate::checkpoint::{ScanCheckpoint, CHECKPOINT_PERIOD};
+use crate::classify::{normalized_extension, Classifier, FileType, Unmatched};
+use crate::filter::{FollowedLinks, ScanFilter};
+use crate::interrupt::is_interrupted;
+
+// Folder under the root that receives "deleted" duplicates when trash mode is chosen.
+// It is never scanned, so trashed files are not organized again on the next run.
+pub const TRASH_FOLDER: &str = ".duplicates_trash";
+
+// How often (in walked entries) the scan reports progress
+pub const SCAN_PROGRESS_INTERVAL: usize = 1000;
+
+// How many of the largest files `SizeStats` keeps
+pub const LARGEST_FILES: usize = 10;
+
+// Total size of the files found per category, and the largest of them
+#[derive(Debug, Clone, Default)]
+pub struct SizeStats {
+    pub bytes: HashMap<FileType, u64>,
+    // At most LARGEST_FILES files with their sizes, largest first
+    pub largest: Vec<(PathBuf, u64)>,
+}
+
+impl SizeStats {
+    // Sizes of the files of a finished scan, read from disk; a file whose size can't be
+    // read doesn't count
+    pub fn of_files(files: &HashMap<FileType, Vec<PathBuf>>) -> SizeStats {
+        let mut sizes = SizeStats::default();
+        for (file_type, paths) in files {
+            for path in paths {
+                if let Ok(metadata) = fs::metadata(path) {
+                    sizes.add(file_type, path, metadata.len());
+                }
+            }
+        }
+        sizes
+    }
+
+    // Count one file of the given category
+    pub fn add(&mut self, file_type: &FileType, path: &Path, size: u64) {
+        *self.bytes.entry(file_type.clone()).or_default() += size;
+        if self.largest.len() == LARGEST_FILES && self.largest.last().is_some_and(|&(_, smallest)| smallest >= size) {
+            return;
+        }
+        let at = self.largest.partition_point(|&(_, larger)| larger >= size);
+        self.largest.insert(at, (path.to_path_buf(), size));
+        self.largest.truncate(LARGEST_FILES);
+    }
+
+    // Total size of every file counted
+    pub fn total(&self) -> u64 {
+        self.bytes.values().sum()
+    }
+}
+
+// Files found by `scan_and_classify_files`
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    // Number of files per category; every category in the scan has an entry, even with 0 files
+    pub stats: HashMap<FileType, usize>,
+    // Full paths of the files in each category, in walk order
+    pub files: HashMap<FileType, Vec<PathBuf>>,
+    // Symlinks leading outside the root that were followed
+    pub followed_links: Vec<PathBuf>,
+    // Symlinks not followed because they lead back to a folder being walked
+    pub link_loops: Vec<PathBuf>,
+    // Files no category claims, when the classifier reports them (`Unmatched::Report`)
+    pub unmatched: Vec<PathBuf>,
+}
+
+// What `scan_streaming` reports while walking
+#[derive(Debug)]
+pub enum ScanEvent<'a> {
+    // Number of entries walked so far, every SCAN_PROGRESS_INTERVAL entries and once
+    // more when the walk finishes
+    Walked(usize),
+    // A symlink leading outside the root is being followed
+    FollowedLink(&'a Path),
+    // A symlink leading back to a folder being walked is skipped
+    LinkLoop(&'a Path),
+    // A classified file
+    File(FileType, &'a Path),
+    // A file no category claims, when the classifier reports them
+    Unmatched(&'a Path),
+}
+
+// Per-category file counts starting at 0; `other` only when files without an extension,
+// or all unmatched files, are collected
+pub fn empty_stats(classifier: &Classifier, include_no_ext: bool) -> HashMap<FileType, usize> {
+    let collects_other = include_no_ext || classifier.unmatched() == Unmatched::Move;
+    classifier
+        .categories()
+        .iter()
+        .filter(|category| collects_other || category.file_type != FileType::Other)
+        .map(|category| (category.file_type.clone(), 0))
+        .collect()
+}
+
+// Category of a file found by a scan: as `classifier` says, or `Other` for a file without
+// an extension when `include_no_ext` is set (and `other` isn't skipped)
+pub fn classify_file(classifier: &Classifier, path: &Path, include_no_ext: bool) -> Option<FileType> {
+    classifier.classify(path).or_else(|| {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        (include_no_ext && normalized_extension(&file_name).is_empty() && !classifier.is_skipped(&FileType::Other)).then_some(FileType::Other)
+    })
+}
+
+// Scans a directory and returns statistics and full file paths grouped by the
+// categories of `classifier`. When `include_no_ext` is set, files without an extension are collected as `FileType::Other`;
+// files no category claims are listed in `unmatched` when the classifier reports them.
+// Entries excluded by `filter` (hidden files, exclusion globs, symlinks) are skipped entirely;
+// symlinks leading outside the root are followed only if `filter` allows it, and the walk
+// goes only as deep and only as far across filesystems as it allows. A followed
+// link back to a folder being walked is skipped and reported, and a target reached
+// through several links is walked only once.
+// `on_progress` receives the number of entries walked so far, every
+// SCAN_PROGRESS_INTERVAL entries and once more when the walk finishes.
+// Every path is kept until the scan returns, so memory grows with the size of the tree;
+// use `scan_streaming` when the files can be handled one by one.
+pub fn scan_and_classify_files(
+    root: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    mut on_progress: impl FnMut(usize),
+) -> ScanResult {
+    let mut result = ScanResult { stats: empty_stats(classifier, include_no_ext), ..ScanResult::default() };
+    scan_streaming(root, include_no_ext, filter, classifier, |event| match event {
+        ScanEvent::Walked(walked) => on_progress(walked),
+        ScanEvent::FollowedLink(path) => result.followed_links.push(path.to_path_buf()),
+        ScanEvent::LinkLoop(path) => result.link_loops.push(path.to_path_buf()),
+        ScanEvent::File(file_type, path) => {
+            result.stats.entry(file_type.clone()).and_modify(|e| *e += 1);
+            result.files.entry(file_type).or_default().push(path.to_path_buf());
+        }
+        ScanEvent::Unmatched(path) => result.unmatched.push(path.to_path_buf()),
+    });
+    result
+}
+
+// Walk a directory like `scan_and_classify_files`, but hand every classified file to
+// `on_event` as soon as it is found instead of collecting it. Nothing is kept between
+// files, so memory stays flat however many files the tree holds; the price is that
+// nothing is known about the whole tree (counts, duplicates) until the walk is over.
+// The callback may move the file it is given: entries already listed are not revisited,
+// and a file moved into a category folder the walk reaches later is found again there,
+// so it should leave files that are already in place alone.
+pub fn scan_streaming(
+    root: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    mut on_event: impl FnMut(ScanEvent),
+) {
+    walk_classified(root, include_no_ext, filter, classifier, &HashSet::new(), |event| {
+        if let WalkEvent::Scan(event) = event {
+            on_event(event);
+        }
+    });
+}
+
+// Like `scan_and_classify_files`, but the scan can be resumed: files listed in `checkpoint`
+// count as found and the folders it marks as done aren't walked again; a complete
+// checkpoint isn't walked at all. Listed files that no longer exist are dropped.
+// `on_checkpoint` receives the updated checkpoint every CHECKPOINT_PERIOD while walking
+// and once more when the walk is over. Only symlinks followed in this walk are reported.
+// A walk stopped by an interrupt leaves the checkpoint incomplete, to be resumed.
+pub fn scan_resumable(
+    root: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    checkpoint: &mut ScanCheckpoint,
+    mut on_checkpoint: impl FnMut(&ScanCheckpoint),
+    mut on_progress: impl FnMut(usize),
+) -> ScanResult {
+    let mut result = ScanResult { stats: empty_stats(classifier, include_no_ext), ..ScanResult::default() };
+    for files in checkpoint.files.values_mut().chain([&mut checkpoint.unmatched]) {
+        files.retain(|path| path.is_file());
+    }
+    if !checkpoint.complete {
+        let known: HashSet<PathBuf> = checkpoint.files.values().flatten().chain(&checkpoint.unmatched).cloned().collect();
+        let done_dirs = checkpoint.done_dirs.clone();
+        // Folders being walked, the root first; a folder is done once the walk leaves it
+        let mut open: Vec<PathBuf> = Vec::new();
+        let mut last_saved = Instant::now();
+        let walked = walk_classified(root, include_no_ext, filter, classifier, &done_dirs, |event| match event {
+            WalkEvent::Scan(ScanEvent::Walked(walked)) => on_progress(walked),
+            WalkEvent::Scan(ScanEvent::FollowedLink(path)) => result.followed_links.push(path.to_path_buf()),
+            WalkEvent::Scan(ScanEvent::LinkLoop(path)) => result.link_loops.push(path.to_path_buf()),
+            WalkEvent::Scan(ScanEvent::File(file_type, path)) => {
+                if !known.contains(path) {
+                    let folder = classifier.folder_name(&file_type).to_string();
+                    checkpoint.files.entry(folder).or_default().push(path.to_path_buf());
+                }
+            }
+            WalkEvent::Scan(ScanEvent::Unmatched(path)) => {
+                if !known.contains(path) {
+                    checkpoint.unmatched.push(path.to_path_buf());
+                }
+            }
+            WalkEvent::Entry(entry) => {
+                enter_entry(&mut open, checkpoint, entry.path(), entry.depth(), entry.file_type().is_dir());
+                if last_saved.elapsed() >= CHECKPOINT_PERIOD {
+                    on_checkpoint(checkpoint);
+                    last_saved = Instant::now();
+                }
+            }
+        });
+        if walked {
+            checkpoint.complete = true;
+            checkpoint.done_dirs.clear();
+        }
+        on_checkpoint(checkpoint);
+    }
+    for category in classifier.categories() {
+        let Some(files) = checkpoint.files.get(&category.folder) else {
+            continue;
+        };
+        result.stats.insert(category.file_type.clone(), files.len());
+        result.files.insert(category.file_type.clone(), files.clone());
+    }
+    result.unmatched = checkpoint.unmatched.clone();
+    result
+}
+
+// Note an entry walked at `depth` below the root: the `open` folders it lies outside of
+// are done, and a folder is open from now on
+fn enter_entry(open: &mut Vec<PathBuf>, checkpoint: &mut ScanCheckpoint, path: &Path, depth: usize, is_dir: bool) {
+    while open.len() > depth {
+        let dir = open.pop().expect("checked above");
+        mark_done(checkpoint, dir);
+    }
+    if is_dir {
+        open.push(path.to_path_buf());
+    }
+}
+
+// Record a folder as walked completely; its subfolders need no entries of their own
+fn mark_done(checkpoint: &mut ScanCheckpoint, dir: PathBuf) {
+    checkpoint.done_dirs.retain(|done| done.parent() != Some(&dir));
+    checkpoint.done_dirs.insert(dir);
+}
+
+// What `walk_classified` reports: the scan events, and every entry walked before its
+// file event
+enum WalkEvent<'a> {
+    Scan(ScanEvent<'a>),
+    Entry(&'a DirEntry),
+}
+
+// Walk root and classify its files as `scan_streaming` describes, without entering
+// the folders in `skip_dirs`. Returns false when an interrupt stopped the walk early.
+fn walk_classified(
+    root: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    skip_dirs: &HashSet<PathBuf>,
+    mut on_event: impl FnMut(WalkEvent),
+) -> bool {
+    let mut walked = 0usize;
+
+    let trash_folder = root.join(TRASH_FOLDER);
+    // Files in the `other` folder were put there by an earlier run, they aren't reported
+    let other_folder = root.join(classifier.folder_name(&FileType::Other));
+    let mut walker = filter
+        .walk(root)
+        .into_iter()
+        .filter_entry(|e| e.path() != trash_folder && !skip_dirs.contains(e.path()) && !filter.is_excluded(e, root));
+    let mut followed = FollowedLinks::default();
+    while let Some(entry) = walker.next() {
+        if is_interrupted() {
+            return false;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            // WalkDir notices a link back to a folder it is inside of
+            Err(e) => {
+                if let (Some(path), Some(_)) = (e.path(), e.loop_ancestor()) {
+                    on_event(WalkEvent::Scan(ScanEvent::LinkLoop(path)));
+                }
+                continue;
+            }
+        };
+        // A link to a folder holding the root isn't inside a folder being walked yet,
+        // but would walk everything again
+        if filter.is_link_loop(&entry, root) {
+            on_event(WalkEvent::Scan(ScanEvent::LinkLoop(entry.path())));
+            walker.skip_current_dir();
+            continue;
+        }
+        if !followed.is_new(&entry) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        on_event(WalkEvent::Entry(&entry));
+        walked += 1;
+        if walked % SCAN_PROGRESS_INTERVAL == 0 {
+            on_event(WalkEvent::Scan(ScanEvent::Walked(walked)));
+        }
+        // Only links leading outside the root get this far (see ScanFilter)
+        if filter.is_outside_link(&entry, root) {
+            on_event(WalkEvent::Scan(ScanEvent::FollowedLink(entry.path())));
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        match classify_file(classifier, entry.path(), include_no_ext) {
+            Some(file_type) => on_event(WalkEvent::Scan(ScanEvent::File(file_type, entry.path()))),
+            None if classifier.unmatched() == Unmatched::Report
+                && !entry.path().starts_with(&other_folder)
+                && !classifier.is_skipped_file(entry.path()) =>
+            {
+                on_event(WalkEvent::Scan(ScanEvent::Unmatched(entry.path())))
+            }
+            None => {}
+        }
+    }
+    on_event(WalkEvent::Scan(ScanEvent::Walked(walked)));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // root/a/1.jpg, root/a/sub/2.jpg, root/b/3.jpg and root/4.jpg
+    fn photo_tree(root: &Path) -> [PathBuf; 4] {
+        let files = [root.join("a").join("1.jpg"), root.join("a").join("sub").join("2.jpg"), root.join("b").join("3.jpg"), root.join("4.jpg")];
+        for file in &files {
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, file.to_string_lossy().as_bytes()).unwrap();
+        }
+        files
+    }
+
+    fn sorted_images(scan: &ScanResult) -> Vec<PathBuf> {
+        let mut files = scan.files[&FileType::Image].clone();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn walked_folders_are_done_once_the_walk_leaves_them() {
+        let root = Path::new("/root");
+        let mut checkpoint = ScanCheckpoint::new("settings");
+        let mut open = Vec::new();
+        enter_entry(&mut open, &mut checkpoint, root, 0, true);
+        enter_entry(&mut open, &mut checkpoint, &root.join("a"), 1, true);
+        enter_entry(&mut open, &mut checkpoint, &root.join("a/sub"), 2, true);
+        enter_entry(&mut open, &mut checkpoint, &root.join("a/sub/1.jpg"), 3, false);
+        assert!(checkpoint.done_dirs.is_empty());
+
+        // Leaving a/sub and a at once leaves only a, which holds sub
+        enter_entry(&mut open, &mut checkpoint, &root.join("b"), 1, true);
+        assert_eq!(open, [root.to_path_buf(), root.join("b")]);
+        assert_eq!(checkpoint.done_dirs, HashSet::from([root.join("a")]));
+        enter_entry(&mut open, &mut checkpoint, &root.join("b/2.jpg"), 2, false);
+        enter_entry(&mut open, &mut checkpoint, &root.join("3.jpg"), 1, false);
+        assert_eq!(open, [root.to_path_buf()]);
+        assert_eq!(checkpoint.done_dirs, HashSet::from([root.join("a"), root.join("b")]));
+    }
+
+    #[test]
+    fn a_resumed_scan_finds_every_file_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let [one, two, three, four] = photo_tree(root);
+        let classifier = Classifier::builtin();
+
+        // The interrupted run walked root/a and had found 4.jpg; 2.jpg is gone since
+        let mut checkpoint = ScanCheckpoint::new("settings");
+        checkpoint.done_dirs.insert(root.join("a"));
+        checkpoint.files.insert("image".to_string(), vec![one.clone(), two.clone(), four.clone()]);
+        fs::remove_file(&two).unwrap();
+        let mut saves = 0;
+        let scan = scan_resumable(root, false, &ScanFilter::default(), &classifier, &mut checkpoint, |_| saves += 1, |_| {});
+
+        assert_eq!(sorted_images(&scan), [four, one, three]);
+        assert_eq!(scan.stats[&FileType::Image], 3);
+        assert!(checkpoint.complete);
+        assert!(checkpoint.done_dirs.is_empty());
+        assert_eq!(saves, 1);
+
+        // A complete checkpoint isn't walked again: a file added since isn't found
+        fs::write(root.join("b").join("5.jpg"), b"new").unwrap();
+        let again = scan_resumable(root, false, &ScanFilter::default(), &classifier, &mut checkpoint, |_| {}, |_| {});
+        assert_eq!(sorted_images(&again), sorted_images(&scan));
+    }
+
+    #[test]
+    fn a_fresh_checkpoint_scans_like_a_plain_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        photo_tree(root);
+        let classifier = Classifier::builtin();
+        let plain = scan_and_classify_files(root, false, &ScanFilter::default(), &classifier, |_| {});
+        let mut checkpoint = ScanCheckpoint::new("settings");
+        let resumable = scan_resumable(root, false, &ScanFilter::default(), &classifier, &mut checkpoint, |_| {}, |_| {});
+        assert_eq!(sorted_images(&resumable), sorted_images(&plain));
+        assert_eq!(checkpoint.files["image"].len(), 4);
+    }
+}
@@ -0,0 +1,151 @@
+/*
+File: similar.rs
+
+Near-duplicate image detection by perceptual hashing.
+Each image is reduced to a 64-bit perceptual hash (`PerceptualHash`):
+- the difference hash (dHash) shrinks the picture to 9x8 grayscale pixels and every
+  bit records whether a pixel is brighter than its right neighbour;
+- the DCT hash (pHash) shrinks it to 32x32 grayscale pixels and every bit records
+  whether one of the 8x8 lowest frequencies of its discrete cosine transform is above
+  their median, which is more robust to brightness, contrast and small edits.
+Re-saved, resized or re-compressed copies keep (almost) the same bits, so images
+whose hashes differ in at most `max_distance` bits are reported as pairs. Being only
+alike, not identical, these pairs are meant for review and never removed automatically.
+*/
+
+use std::io;
+use std::path::{Path, PathBuf};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageResult};
+use rayon::prelude::*;
+use crate::error::{Operation, OperationError};
+
+// Default maximum Hamming distance between two hashes of "the same" image
+pub const DEFAULT_MAX_DISTANCE: u32 = 5;
+
+// Perceptual hash used to compare images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PerceptualHash {
+    // Fast, and good at resized and re-compressed copies
+    #[default]
+    DHash,
+    // Slower, also matches copies with adjusted brightness or contrast
+    PHash,
+}
+
+impl PerceptualHash {
+    // Compute this hash of an image file
+    pub fn hash(self, path: &Path) -> ImageResult<u64> {
+        match self {
+            PerceptualHash::DHash => dhash(path),
+            PerceptualHash::PHash => phash(path),
+        }
+    }
+}
+
+// Compute the 64-bit difference hash of an image file
+pub fn dhash(path: &Path) -> ImageResult<u64> {
+    Ok(dhash_image(&image::open(path)?))
+}
+
+// Compute the 64-bit difference hash of a decoded image
+pub fn dhash_image(image: &DynamicImage) -> u64 {
+    let image = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = image.get_pixel(x, y)[0];
+            let right = image.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    hash
+}
+
+// Side of the grayscale image the DCT hash is computed from
+const PHASH_SIZE: usize = 32;
+// Side of the block of lowest frequencies it keeps
+const PHASH_FREQUENCIES: usize = 8;
+
+// Compute the 64-bit DCT hash of an image file
+pub fn phash(path: &Path) -> ImageResult<u64> {
+    let image = image::open(path)?
+        .resize_exact(PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)
+        .into_luma8();
+    // cosines[k][n] of the DCT-II, only for the frequencies kept
+    let cosines: Vec<Vec<f64>> = (0..PHASH_FREQUENCIES)
+        .map(|k| {
+            (0..PHASH_SIZE)
+                .map(|n| (std::f64::consts::PI / PHASH_SIZE as f64 * (n as f64 + 0.5) * k as f64).cos())
+                .collect()
+        })
+        .collect();
+    // The 2D transform is separable: rows first, then the columns of the result
+    let rows: Vec<Vec<f64>> = (0..PHASH_SIZE)
+        .map(|y| {
+            cosines
+                .iter()
+                .map(|cosine| (0..PHASH_SIZE).map(|x| f64::from(image.get_pixel(x as u32, y as u32)[0]) * cosine[x]).sum())
+                .collect()
+        })
+        .collect();
+    let rows = &rows;
+    let frequencies: Vec<f64> = cosines
+        .iter()
+        .flat_map(|cosine| {
+            (0..PHASH_FREQUENCIES).map(move |u| rows.iter().zip(cosine).map(|(row, c)| row[u] * c).sum::<f64>())
+        })
+        .collect();
+    let mut sorted = frequencies.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0;
+    Ok(frequencies.iter().fold(0u64, |hash, value| (hash << 1) | u64::from(*value > median)))
+}
+
+// Number of differing bits between two hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// Two images that look alike
+#[derive(Debug, Clone)]
+pub struct SimilarImage {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    // Number of differing bits between their perceptual hashes
+    pub distance: u32,
+}
+
+// Find the pairs of images whose perceptual hashes (`hash`) are within `max_distance` bits
+// of each other. Hashing runs in parallel; files that can't be decoded are skipped and
+// returned as errors. Pairs come sorted by path.
+pub fn find_similar_images(
+    paths: &[PathBuf],
+    hash: PerceptualHash,
+    max_distance: u32,
+) -> (Vec<SimilarImage>, Vec<OperationError>) {
+    let results: Vec<(PathBuf, ImageResult<u64>)> =
+        paths.par_iter().map(|path| (path.clone(), hash.hash(path))).collect();
+    let mut hashed = Vec::new();
+    let mut errors = Vec::new();
+    for (path, hash) in results {
+        match hash {
+            Ok(hash) => hashed.push((hash, path)),
+            Err(e) => errors.push(OperationError::new(Operation::Decode, &path, io::Error::other(e))),
+        }
+    }
+    hashed.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let hashed = &hashed;
+    let pairs = hashed
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, (value, first))| {
+            hashed[i + 1..].iter().filter_map(move |(other, second)| {
+                let distance = hamming_distance(*value, *other);
+                (distance <= max_distance).then(|| SimilarImage { first: first.clone(), second: second.clone(), distance })
+            })
+        })
+        .collect();
+    (pairs, errors)
+}
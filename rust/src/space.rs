@@ -0,0 +1,114 @@
+/*
+File: space.rs
+
+Free space on the filesystem receiving the organized files, checked before anything is
+copied there. Copy mode copies every file, and a move falls back to copying when the
+file lies on another filesystem than its target, so a run that would fill the disk is
+stopped before it starts instead of failing halfway. The free space is the amount
+available to the user, as statvfs (Unix) or GetDiskFreeSpaceExW (Windows) reports it;
+where it can't be read the check passes.
+*/
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A destination without room for the files to be copied there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceShortage {
+    pub dest: PathBuf,
+    // Bytes the copies need and bytes available
+    pub required: u64,
+    pub available: u64,
+}
+
+// Check that `dest` has room for the files moved (or with `copy`, copied) there: the
+// size of every file when copying, otherwise of the files on another filesystem. Returns
+// the bytes needed; files whose size can't be read don't count.
+pub fn check_space<'a>(files: impl IntoIterator<Item = &'a Path>, dest: &Path, copy: bool) -> Result<u64, SpaceShortage> {
+    let required: u64 = files
+        .into_iter()
+        .filter(|path| copy || !same_filesystem(path, dest))
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    if required == 0 {
+        return Ok(0);
+    }
+    match available_space(dest) {
+        Ok(available) if available < required => Err(SpaceShortage { dest: dest.to_path_buf(), required, available }),
+        _ => Ok(required),
+    }
+}
+
+// Bytes available to the user on the filesystem holding `path`; a path that doesn't
+// exist yet (a destination about to be created) is looked up at its nearest existing folder
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    free_bytes(existing_ancestor(path))
+}
+
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|dir| dir.exists()).unwrap_or(path)
+}
+
+// Whether a file and a folder (or its nearest existing parent) are on the same
+// filesystem, so moving the file there is a rename; when either can't be read they count
+// as the same
+#[cfg(unix)]
+pub fn same_filesystem(file: &Path, dest: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(file), fs::metadata(existing_ancestor(dest))) {
+        (Ok(file), Ok(dest)) => file.dev() == dest.dev(),
+        _ => true,
+    }
+}
+
+// Drive letters or UNC shares are compared, as rename works within one volume
+#[cfg(windows)]
+pub fn same_filesystem(file: &Path, dest: &Path) -> bool {
+    let volume = |path: &Path| std::path::absolute(path).ok().and_then(|path| path.components().next().map(|c| c.as_os_str().to_ascii_lowercase()));
+    volume(file) == volume(dest)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn same_filesystem(_file: &Path, _dest: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stats` is large enough for statvfs to fill
+    if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: statvfs succeeded, so it filled `stats`
+    let stats = unsafe { stats.assume_init() };
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = stats.f_bavail as u64 * stats.f_frsize as u64;
+    Ok(available)
+}
+
+#[cfg(windows)]
+fn free_bytes(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut available = 0u64;
+    // SAFETY: `path` is NUL-terminated and the other totals may be null
+    let ok = unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn free_bytes(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "free space can't be read on this platform"))
+}
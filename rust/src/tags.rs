@@ -0,0 +1,183 @@
+/*
+File: tags.rs
+
+Artist, album and title tags of audio files, for the by-artist layout: ID3 (v2, or
+v1 as a fallback) in MP3 files, and Vorbis comments in FLAC and Ogg (Vorbis or Opus)
+files. The container is recognized by its leading bytes, not by the extension.
+*/
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use id3::TagLike;
+
+// What the tags of an audio file say; missing or empty tags are None
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioTags {
+    pub artist: Option<String>,
+    // Artist of the whole album, set on compilations and featuring tracks
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+}
+
+impl AudioTags {
+    // The artist an album is filed under: the album artist, or else the track artist
+    pub fn filing_artist(&self) -> Option<&str> {
+        self.album_artist.as_deref().or(self.artist.as_deref())
+    }
+}
+
+// Largest metadata block or packet read; cover art can make comments big, but not this big
+const MAX_COMMENT_SIZE: usize = 16 * 1024 * 1024;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Trimmed tag value, None when empty
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+// Read the tags of an audio file. Files in another format, or without tags, give an
+// error of kind InvalidData.
+pub fn read_audio_tags(path: &Path) -> io::Result<AudioTags> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    match &magic {
+        b"fLaC" => flac_comments(&mut reader),
+        b"OggS" => ogg_comments(reader),
+        _ => id3_tags(path),
+    }
+}
+
+fn id3_tags(path: &Path) -> io::Result<AudioTags> {
+    let tag = match id3::v1v2::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(id3::Error { kind: id3::ErrorKind::Io(e), .. }) => return Err(e),
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+    };
+    Ok(AudioTags {
+        artist: non_empty(tag.artist()),
+        album_artist: non_empty(tag.album_artist()),
+        album: non_empty(tag.album()),
+        title: non_empty(tag.title()),
+    })
+}
+
+// FLAC metadata blocks follow the magic: one header byte (last-block flag and type),
+// a 24-bit big-endian length, then the data. Type 4 holds the Vorbis comments.
+fn flac_comments(reader: &mut impl Read) -> io::Result<AudioTags> {
+    loop {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header)?;
+        let is_last = header[0] & 0x80 != 0;
+        let block_type = header[0] & 0x7f;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        if block_type == 4 {
+            let mut data = vec![0u8; len];
+            reader.read_exact(&mut data)?;
+            return parse_vorbis_comments(&data);
+        }
+        io::copy(&mut reader.take(len as u64), &mut io::sink())?;
+        if is_last {
+            return Err(invalid_data("no Vorbis comment block"));
+        }
+    }
+}
+
+// The comment header is the second packet of the first logical stream of an Ogg file:
+// "\x03vorbis" + comments for Vorbis, "OpusTags" + comments for Opus.
+fn ogg_comments(mut reader: impl Read) -> io::Result<AudioTags> {
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    // The magic of the first page was read by the caller
+    loop {
+        // version, header type, granule position, serial, sequence, checksum, segment count
+        let mut header = [0u8; 23];
+        reader.read_exact(&mut header)?;
+        let mut lacing = vec![0u8; usize::from(header[22])];
+        reader.read_exact(&mut lacing)?;
+        for segment in lacing {
+            let packet = packets.last_mut().expect("never empty");
+            let start = packet.len();
+            packet.resize(start + usize::from(segment), 0);
+            reader.read_exact(&mut packet[start..])?;
+            if packet.len() > MAX_COMMENT_SIZE {
+                return Err(invalid_data("Ogg packet too large"));
+            }
+            // A segment shorter than 255 bytes ends its packet
+            if segment < 255 {
+                packets.push(Vec::new());
+            }
+        }
+        if packets.len() > 2 {
+            break;
+        }
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"OggS" {
+            return Err(invalid_data("broken Ogg page"));
+        }
+    }
+    let comments = &packets[1];
+    let body = comments
+        .strip_prefix(b"\x03vorbis")
+        .or_else(|| comments.strip_prefix(b"OpusTags"))
+        .ok_or_else(|| invalid_data("no Vorbis comment header"))?;
+    parse_vorbis_comments(body)
+}
+
+// Reads length-prefixed strings out of a Vorbis comment list
+struct CommentReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> CommentReader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid_data("truncated Vorbis comment"));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn length(&mut self) -> io::Result<usize> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn string(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.length()?;
+        self.bytes(len)
+    }
+}
+
+// Parse a Vorbis comment list: a vendor string, then `KEY=value` comments, every
+// string preceded by its little-endian u32 length
+fn parse_vorbis_comments(data: &[u8]) -> io::Result<AudioTags> {
+    let mut reader = CommentReader { data };
+    reader.string()?;
+    let count = reader.length()?;
+    let mut tags = AudioTags::default();
+    for _ in 0..count {
+        let comment = String::from_utf8_lossy(reader.string()?);
+        let Some((key, value)) = comment.split_once('=') else {
+            continue;
+        };
+        // The first value of a repeated key wins
+        let slot = match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => &mut tags.artist,
+            "ALBUMARTIST" | "ALBUM ARTIST" => &mut tags.album_artist,
+            "ALBUM" => &mut tags.album,
+            "TITLE" => &mut tags.title,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = non_empty(Some(value));
+        }
+    }
+    Ok(tags)
+}
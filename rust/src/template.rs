@@ -0,0 +1,193 @@
+/*
+File: template.rs
+
+Destination path templates such as `{category}/{year}/{month}/{filename}` or
+`{category}/{ext}/`, rendered by the mover for every file it moves.
+A template is a `/`-separated relative path whose components mix literal text with
+placeholders; the values come from the file (see `Field`) and are made safe to use
+as a single folder name. Templates that use `{category}` are relative to the
+organized root, all others to the file's category folder. When the last component
+uses `{filename}` or `{stem}` it names the file; otherwise every component is a
+folder and the file keeps its name.
+*/
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// A value a placeholder can take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    // Folder of the file's category
+    Category,
+    // Date the photo was taken, or else the file was last modified: `2023`, `07`, `04`
+    Year,
+    Month,
+    Day,
+    // Lowercase extension without the dot (`tar.gz` for compound ones)
+    Ext,
+    // Whole file name, and the name without its extension
+    Filename,
+    Stem,
+    // Camera model of photos (Exif)
+    Camera,
+    // Album artist (or artist) and album of audio files (tags)
+    Artist,
+    Album,
+}
+
+impl Field {
+    const ALL: [(&'static str, Field); 10] = [
+        ("category", Field::Category),
+        ("year", Field::Year),
+        ("month", Field::Month),
+        ("day", Field::Day),
+        ("ext", Field::Ext),
+        ("filename", Field::Filename),
+        ("stem", Field::Stem),
+        ("camera", Field::Camera),
+        ("artist", Field::Artist),
+        ("album", Field::Album),
+    ];
+
+    fn from_name(name: &str) -> Option<Field> {
+        Field::ALL.iter().find(|(field_name, _)| *field_name == name).map(|(_, field)| *field)
+    }
+}
+
+// Folder name used when a placeholder has no value for a file
+pub const UNKNOWN_VALUE: &str = "unknown";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+// A parsed destination path template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    text: String,
+    components: Vec<Vec<Piece>>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+// Make a value usable as a single path component: separators and control characters
+// become `_`, surrounding spaces and dots are dropped
+pub fn folder_component(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect();
+    name.trim().trim_matches('.').to_string()
+}
+
+impl PathTemplate {
+    // Parse a template; unknown placeholders, unbalanced braces, absolute paths and `..`
+    // are rejected with an error of kind InvalidInput
+    pub fn parse(text: &str) -> io::Result<PathTemplate> {
+        if text.starts_with('/') || text.starts_with('\\') {
+            return Err(invalid(format!("template {:?} must be a relative path", text)));
+        }
+        let mut components = Vec::new();
+        for component in text.split('/').filter(|component| !component.is_empty() && *component != ".") {
+            if component == ".." {
+                return Err(invalid(format!("template {:?} must not leave its folder with ..", text)));
+            }
+            let mut pieces = Vec::new();
+            let mut rest = component;
+            while let Some(open) = rest.find('{') {
+                if open > 0 {
+                    pieces.push(Piece::Text(rest[..open].to_string()));
+                }
+                let close = rest[open..]
+                    .find('}')
+                    .ok_or_else(|| invalid(format!("unclosed {{ in template {:?}", text)))?;
+                let name = &rest[open + 1..open + close];
+                let field = Field::from_name(name).ok_or_else(|| {
+                    let known: Vec<&str> = Field::ALL.iter().map(|(name, _)| *name).collect();
+                    invalid(format!("unknown placeholder {{{}}} in template {:?} (known: {})", name, text, known.join(", ")))
+                })?;
+                pieces.push(Piece::Field(field));
+                rest = &rest[open + close + 1..];
+            }
+            if rest.contains('}') {
+                return Err(invalid(format!("unmatched }} in template {:?}", text)));
+            }
+            if !rest.is_empty() {
+                pieces.push(Piece::Text(rest.to_string()));
+            }
+            components.push(pieces);
+        }
+        Ok(PathTemplate { text: text.to_string(), components })
+    }
+
+    fn uses(&self, field: Field) -> bool {
+        self.components.iter().flatten().any(|piece| *piece == Piece::Field(field))
+    }
+
+    // Whether the rendered path starts at the organized root rather than the category folder
+    pub fn is_relative_to_root(&self) -> bool {
+        self.uses(Field::Category)
+    }
+
+    // Whether the last component renames the file
+    fn names_file(&self) -> bool {
+        self.components
+            .last()
+            .is_some_and(|pieces| pieces.iter().any(|piece| matches!(piece, Piece::Field(Field::Filename | Field::Stem))))
+    }
+
+    // Render the template for one file: the folder path (relative, see `is_relative_to_root`)
+    // and the file name. `value` gives the text of each placeholder, None when the file
+    // has none. A last component that is exactly `{filename}` keeps the original name
+    // byte for byte, even when it is not valid UTF-8.
+    pub fn render(&self, file_name: &OsStr, value: impl Fn(Field) -> Option<String>) -> (PathBuf, OsString) {
+        let render_component = |pieces: &[Piece]| {
+            let text: String = pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => text.clone(),
+                    Piece::Field(field) => value(*field)
+                        .map(|value| folder_component(&value))
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or_else(|| UNKNOWN_VALUE.to_string()),
+                })
+                .collect();
+            folder_component(&text)
+        };
+        let (folders, name) = if self.names_file() {
+            let (last, folders) = self.components.split_last().expect("names_file needs a component");
+            let name = match last.as_slice() {
+                [Piece::Field(Field::Filename)] => file_name.to_os_string(),
+                pieces => match render_component(pieces) {
+                    name if name.is_empty() => file_name.to_os_string(),
+                    name => OsString::from(name),
+                },
+            };
+            (folders, name)
+        } else {
+            (self.components.as_slice(), file_name.to_os_string())
+        };
+        (folders.iter().map(|pieces| render_component(pieces)).collect(), name)
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<PathTemplate> {
+        PathTemplate::parse(text)
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
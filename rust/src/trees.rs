@@ -0,0 +1,176 @@
+/*
+File: trees.rs
+
+Whole duplicate folders, such as the same photo folder in several backups. Listing
+those file by file buries them in thousands of groups, so folders whose files have
+exactly the same contents (the same multiset of full hashes, whatever the file names)
+are reported as one `DuplicateTree`: the kept folder, its redundant copies, and every
+file of the copies paired with the file of the kept folder it matches. The pairs have
+the shape of the file-level `(duplicate, kept)` pairs, so a redundant folder is removed
+(and its emptied subfolders with `remove_emptied_trees`) or merged into the kept one by
+links with the same actions as single duplicates.
+Only folders under the root count, and folders inside a redundant copy aren't
+reported again on their own.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::cache::HashCache;
+use crate::dedupe::{collect_files, find_duplicates_cached, prefer_files_under, DedupOptions};
+use crate::error::{Operation, OperationError};
+use crate::filter::ScanFilter;
+use crate::scan::TRASH_FOLDER;
+
+// A folder and its identical copies
+#[derive(Debug, Clone)]
+pub struct DuplicateTree {
+    // Hash of the sorted hashes of the folder's files
+    pub hash: String,
+    // Folder kept: the first by path, unless a copy lies in a preferred folder
+    pub kept: PathBuf,
+    // Its redundant copies
+    pub duplicates: Vec<PathBuf>,
+    // Files in each copy and their total size
+    pub file_count: usize,
+    pub bytes: u64,
+    // Every file of the copies with the file of the kept folder holding the same contents
+    pub pairs: Vec<(PathBuf, PathBuf)>,
+}
+
+// Find the folders under `root` that hold the same file contents as another folder.
+// Every file under the root that passes `filter` is taken into account (only files
+// with a same-size twin are hashed, see `dedupe::find_duplicates_cached`), so a folder
+// with a single unique file is never reported. Kept folders are chosen in the first of
+// `preferred_dirs` that holds a copy, otherwise by path. Trees come sorted by kept path.
+pub fn find_duplicate_trees(
+    root: &Path,
+    filter: &ScanFilter,
+    options: DedupOptions,
+    cache: Option<&HashCache>,
+    preferred_dirs: &[PathBuf],
+    on_hashed: impl Fn(usize, usize) + Sync,
+) -> (Vec<DuplicateTree>, Vec<OperationError>) {
+    let trash = root.join(TRASH_FOLDER);
+    let files: Vec<PathBuf> = collect_files(root, filter, root).into_iter().filter(|path| !path.starts_with(&trash)).collect();
+    let (duplicates, mut errors) = find_duplicates_cached(&files, options, cache, on_hashed);
+    let hash_of: HashMap<&Path, &str> = duplicates
+        .iter()
+        .flat_map(|(hash, paths)| paths.iter().map(move |path| (path.as_path(), hash.as_str())))
+        .collect();
+
+    // The hashes of the files under every folder; None once a folder holds a file
+    // without a twin
+    let mut folders: BTreeMap<&Path, Option<Vec<&str>>> = BTreeMap::new();
+    for file in &files {
+        let hash = hash_of.get(file.as_path()).copied();
+        for folder in file.ancestors().skip(1).take_while(|folder| *folder != root && folder.starts_with(root)) {
+            let hashes = folders.entry(folder).or_insert_with(|| Some(Vec::new()));
+            match (hashes.as_mut(), hash) {
+                (Some(hashes), Some(hash)) => hashes.push(hash),
+                _ => *hashes = None,
+            }
+        }
+    }
+
+    let mut by_contents: HashMap<String, Vec<&Path>> = HashMap::new();
+    for (folder, hashes) in &folders {
+        if let Some(hashes) = hashes {
+            let mut hashes = hashes.clone();
+            hashes.sort_unstable();
+            let key = blake3::hash(hashes.join("\n").as_bytes()).to_hex().to_string();
+            by_contents.entry(key).or_default().push(folder);
+        }
+    }
+    // A folder holding nothing but one subfolder has the same contents as it, without
+    // being a copy; only the outer one is kept in the group
+    let mut groups: Vec<(String, Vec<&Path>)> = by_contents
+        .into_iter()
+        .filter_map(|(hash, mut folders)| {
+            folders.sort();
+            let outer: Vec<&Path> = folders
+                .iter()
+                .filter(|folder| !folders.iter().any(|other| other != *folder && folder.starts_with(other)))
+                .copied()
+                .collect();
+            (outer.len() > 1).then_some((hash, outer))
+        })
+        .collect();
+    // Outermost folders first, so folders inside a copy already found are skipped
+    groups.sort_by_key(|(_, folders)| (folders.iter().map(|folder| folder.components().count()).min(), folders[0]));
+    let mut copies: Vec<PathBuf> = Vec::new();
+    let mut trees = Vec::new();
+    for (hash, folders) in groups {
+        let mut folders: Vec<PathBuf> = folders
+            .into_iter()
+            .filter(|folder| !copies.iter().any(|copy| folder.starts_with(copy)))
+            .map(Path::to_path_buf)
+            .collect();
+        if folders.len() < 2 {
+            continue;
+        }
+        prefer_files_under(&mut folders, preferred_dirs);
+        let kept = folders.remove(0);
+        copies.extend(folders.iter().cloned());
+        let kept_files: Vec<&PathBuf> = files.iter().filter(|file| file.starts_with(&kept)).collect();
+        let mut bytes = 0u64;
+        for file in &kept_files {
+            match fs::metadata(file) {
+                Ok(metadata) => bytes += metadata.len(),
+                Err(e) => errors.push(OperationError::new(Operation::ReadMetadata, file, e)),
+            }
+        }
+        let pairs = folders.iter().flat_map(|copy| match_files(copy, &kept, &files, &hash_of)).collect();
+        trees.push(DuplicateTree { hash, file_count: kept_files.len(), bytes, kept, duplicates: folders, pairs });
+    }
+    trees.sort_by(|a, b| a.kept.cmp(&b.kept));
+    (trees, errors)
+}
+
+// Pair every file under `copy` with a file under `kept` of the same hash, the file at
+// the same relative path when it has one
+fn match_files(copy: &Path, kept: &Path, files: &[PathBuf], hash_of: &HashMap<&Path, &str>) -> Vec<(PathBuf, PathBuf)> {
+    let mut available: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+    for file in files.iter().filter(|file| file.starts_with(kept)) {
+        if let Some(hash) = hash_of.get(file.as_path()) {
+            available.entry(hash).or_default().push(file);
+        }
+    }
+    let mut pairs = Vec::new();
+    for file in files.iter().filter(|file| file.starts_with(copy)) {
+        let Some(candidates) = hash_of.get(file.as_path()).and_then(|hash| available.get_mut(hash)) else {
+            continue;
+        };
+        let same_place = file.strip_prefix(copy).ok().map(|relative| kept.join(relative));
+        let index = candidates.iter().position(|candidate| Some(*candidate) == same_place.as_ref()).unwrap_or(0);
+        if index < candidates.len() {
+            pairs.push((file.clone(), candidates.swap_remove(index).clone()));
+        }
+    }
+    pairs
+}
+
+// Remove the folders of the redundant copies left empty once their files were deleted,
+// innermost first. Folders still holding something (files the filter skipped, say) stay.
+// Returns the folders removed and the failures.
+pub fn remove_emptied_trees(trees: &[DuplicateTree]) -> (Vec<PathBuf>, Vec<OperationError>) {
+    let mut removed = Vec::new();
+    let mut errors = Vec::new();
+    for copy in trees.iter().flat_map(|tree| &tree.duplicates) {
+        let folders = WalkDir::new(copy)
+            .contents_first(true)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir());
+        for folder in folders {
+            match fs::remove_dir(folder.path()) {
+                Ok(()) => removed.push(folder.into_path()),
+                Err(e) if e.kind() == io::ErrorKind::DirectoryNotEmpty => {}
+                Err(e) => errors.push(OperationError::new(Operation::RemoveFolder, folder.path(), e)),
+            }
+        }
+    }
+    (removed, errors)
+}
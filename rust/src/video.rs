@@ -0,0 +1,150 @@
+/*
+File: video.rs
+
+Near-duplicate video detection by sampling frames, to find the same video stored at
+another bitrate, resolution or in another container, which no content hash can match.
+The length of each video is read with `ffprobe`, and `ffmpeg` decodes one frame at each
+of a few fixed points of it (10%, 30%, 50%, 70% and 90% of the way through). Every frame
+is reduced to its 64-bit difference hash (`similar::dhash_image`); two videos of about
+the same length whose frames differ by at most `max_distance` bits on average are
+reported as a candidate pair. Both programs come from FFmpeg and must be on the PATH;
+nothing is ever removed on the strength of these pairs.
+*/
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use rayon::prelude::*;
+use crate::error::{Operation, OperationError};
+use crate::similar::{dhash_image, hamming_distance};
+
+// Programs used to read and decode videos
+pub const FFMPEG: &str = "ffmpeg";
+pub const FFPROBE: &str = "ffprobe";
+
+// Default largest average Hamming distance between the sampled frames of "the same" video
+pub const DEFAULT_MAX_FRAME_DISTANCE: u32 = 8;
+
+// Points of a video, as fractions of its length, where a frame is sampled
+const SAMPLE_POINTS: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+// Side of the (square) frame ffmpeg hands over for hashing
+const FRAME_SIDE: u32 = 32;
+// Largest difference in length, in seconds, between two copies of the same video
+const MAX_DURATION_DIFFERENCE: f64 = 2.0;
+
+// What a video is compared by
+#[derive(Debug, Clone)]
+pub struct VideoSignature {
+    // Length in seconds
+    pub duration: f64,
+    // Difference hash of the frame at each sample point
+    pub frames: Vec<u64>,
+}
+
+// Two videos whose sampled frames look alike
+#[derive(Debug, Clone)]
+pub struct SimilarVideo {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    // Average number of differing bits between their frames
+    pub distance: f64,
+}
+
+// Run a program and return its standard output; a failure exit is an error carrying its
+// standard error
+fn run(command: &mut Command) -> io::Result<Vec<u8>> {
+    let output = command.stdin(Stdio::null()).output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(if message.is_empty() { output.status.to_string() } else { message }));
+    }
+    Ok(output.stdout)
+}
+
+// Whether ffmpeg and ffprobe can be run
+pub fn ffmpeg_available() -> io::Result<()> {
+    for program in [FFMPEG, FFPROBE] {
+        run(Command::new(program).arg("-version")).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("{} not found on the PATH", program)),
+            _ => e,
+        })?;
+    }
+    Ok(())
+}
+
+// Length of a video in seconds
+fn duration(path: &Path) -> io::Result<f64> {
+    let output = run(Command::new(FFPROBE)
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path))?;
+    String::from_utf8_lossy(&output)
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "unknown duration"))
+}
+
+// Difference hash of the frame shown `seconds` into a video
+fn frame_hash(path: &Path, seconds: f64) -> io::Result<u64> {
+    let scale = format!("scale={}:{}", FRAME_SIDE, FRAME_SIDE);
+    let output = run(Command::new(FFMPEG)
+        .args(["-v", "error", "-ss", &format!("{:.3}", seconds), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-vf", &scale, "-f", "image2pipe", "-c:v", "png", "-"]))?;
+    let image = image::load_from_memory(&output).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(dhash_image(&image))
+}
+
+// Sample and hash the frames of a video
+pub fn signature(path: &Path) -> io::Result<VideoSignature> {
+    let duration = duration(path)?;
+    let frames = SAMPLE_POINTS
+        .iter()
+        .map(|point| frame_hash(path, duration * point))
+        .collect::<io::Result<Vec<u64>>>()?;
+    Ok(VideoSignature { duration, frames })
+}
+
+// Average number of differing bits between the frames of two signatures
+pub fn frame_distance(a: &VideoSignature, b: &VideoSignature) -> f64 {
+    let total: u32 = a.frames.iter().zip(&b.frames).map(|(x, y)| hamming_distance(*x, *y)).sum();
+    f64::from(total) / a.frames.len().min(b.frames.len()).max(1) as f64
+}
+
+// Find the pairs of videos that look like copies of each other: lengths within two
+// seconds and sampled frames at most `max_distance` bits apart on average. Videos are
+// sampled in parallel; files ffmpeg can't read are skipped and returned as errors, and
+// without ffmpeg nothing is compared and the single error names the missing program.
+// Pairs come sorted by path.
+pub fn find_similar_videos(paths: &[PathBuf], max_distance: u32) -> (Vec<SimilarVideo>, Vec<OperationError>) {
+    if paths.len() < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    if let Err(e) = ffmpeg_available() {
+        return (Vec::new(), vec![OperationError::new(Operation::DecodeVideo, Path::new(FFMPEG), e)]);
+    }
+    let results: Vec<(PathBuf, io::Result<VideoSignature>)> =
+        paths.par_iter().map(|path| (path.clone(), signature(path))).collect();
+    let mut signatures = Vec::new();
+    let mut errors = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(signature) => signatures.push((path, signature)),
+            Err(e) => errors.push(OperationError::new(Operation::DecodeVideo, &path, e)),
+        }
+    }
+    signatures.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut pairs = Vec::new();
+    for (i, (first, a)) in signatures.iter().enumerate() {
+        for (second, b) in &signatures[i + 1..] {
+            if (a.duration - b.duration).abs() > MAX_DURATION_DIFFERENCE {
+                continue;
+            }
+            let distance = frame_distance(a, b);
+            if distance <= f64::from(max_distance) {
+                pairs.push(SimilarVideo { first: first.clone(), second: second.clone(), distance });
+            }
+        }
+    }
+    (pairs, errors)
+}
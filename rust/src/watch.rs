@@ -0,0 +1,154 @@
+/*
+File: watch.rs
+
+Watch mode: keep a "hot" folder such as ~/Downloads organized by moving every file
+that appears in it into its category folder, the way `FileMover` does after a scan.
+A file is only moved once it has settled: no change event for the settle time and
+the same size and modification time as at the last check, so downloads and copies
+still being written are left alone until they are complete. A folder moved in is
+walked, since its files raise no events of their own.
+Files already there when watching starts are not touched; organize the folder once
+before watching it.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use notify::{EventKind, RecursiveMode, Watcher};
+use walkdir::WalkDir;
+use crate::classify::Classifier;
+use crate::error::OperationError;
+use crate::filter::ScanFilter;
+use crate::mover::{FileMover, Layout};
+use crate::scan::{classify_file, TRASH_FOLDER};
+
+// Default time a file must stay unchanged before it is moved
+pub const DEFAULT_SETTLE_TIME: Duration = Duration::from_secs(5);
+
+// What `watch_and_organize` reports
+#[derive(Debug)]
+pub enum WatchEvent<'a> {
+    // Watching has started
+    Watching(&'a Path),
+    // A file was moved into its category folder: source and target
+    Moved(&'a Path, &'a Path),
+    // A file that failed to move, or a folder that couldn't be created
+    Failed(OperationError),
+    // The watcher itself reported a problem, e.g. too many watched folders
+    WatchFailed(notify::Error),
+}
+
+// Size and modification time, to tell whether a file is still being written
+type FileState = Option<(u64, SystemTime)>;
+
+fn file_state(path: &Path) -> FileState {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+// A file seen changing, waiting to settle
+struct Pending {
+    last_change: Instant,
+    state: FileState,
+}
+
+// Watch `root` (recursively) and move each new or changed file into its category folder
+// under root, arranged as `layout` says, once it has been unchanged for `settle`.
+// Files are picked and classified like a scan with `include_no_ext`, `filter` and
+// `classifier` would; every move is journaled. Runs until the watcher stops, so in
+// practice until the process is interrupted; only a failure to start watching is
+// returned as an error.
+pub fn watch_and_organize(
+    root: &Path,
+    include_no_ext: bool,
+    filter: &ScanFilter,
+    classifier: &Classifier,
+    layout: &Layout,
+    settle: Duration,
+    mut on_event: impl FnMut(WatchEvent),
+) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+    on_event(WatchEvent::Watching(root));
+
+    let mut mover = FileMover::new(root, classifier, layout, false);
+    for error in mover.take_errors() {
+        on_event(WatchEvent::Failed(error));
+    }
+    let trash_folder = root.join(TRASH_FOLDER);
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    // Settled files are looked for a few times per settle time
+    let tick = (settle / 4).max(Duration::from_millis(100));
+    loop {
+        match receiver.recv_timeout(tick) {
+            Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    if path.starts_with(&trash_folder) {
+                        continue;
+                    }
+                    // A folder moved in: its files were never announced
+                    let paths: Vec<PathBuf> = if path.is_dir() {
+                        WalkDir::new(&path)
+                            .into_iter()
+                            .filter_map(|e| e.ok())
+                            .filter(|e| e.file_type().is_file())
+                            .map(|e| e.into_path())
+                            .collect()
+                    } else {
+                        vec![path]
+                    };
+                    for path in paths {
+                        let state = file_state(&path);
+                        pending.insert(path, Pending { last_change: Instant::now(), state });
+                    }
+                }
+            }
+            // Reads and removals don't bring new files
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => on_event(WatchEvent::WatchFailed(e)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut settled = Vec::new();
+        pending.retain(|path, waiting| {
+            if waiting.last_change.elapsed() < settle {
+                return true;
+            }
+            let state = file_state(path);
+            if state.is_none() {
+                // Gone again, e.g. a temporary file renamed into place
+                return false;
+            }
+            if state != waiting.state {
+                *waiting = Pending { last_change: Instant::now(), state };
+                return true;
+            }
+            settled.push(path.clone());
+            false
+        });
+        for path in settled {
+            if filter.is_path_excluded(&path, root) {
+                continue;
+            }
+            let Some(file_type) = classify_file(classifier, &path, include_no_ext) else {
+                continue;
+            };
+            if let Some((target, _)) = mover.move_file(&file_type, &path) {
+                on_event(WatchEvent::Moved(&path, &target));
+            }
+            // A clash only ever adds a suffix here, which the target shows
+            mover.take_collisions();
+            for error in mover.take_errors() {
+                on_event(WatchEvent::Failed(error));
+            }
+        }
+    }
+    for error in mover.finish() {
+        on_event(WatchEvent::Failed(error));
+    }
+    Ok(())
+}